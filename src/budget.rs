@@ -0,0 +1,150 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::catalog::Message;
+
+/// Allocateur du programme : celui du système, qui tient en plus le compte
+/// des octets alloués et pas encore libérés par chaque thread.
+struct CountingAllocator;
+
+thread_local! {
+    // Un compteur par thread évite une opération atomique à chaque
+    // allocation. Il peut revenir en arrière quand un thread libère ce qu'un
+    // autre a alloué, d'où l'arithmétique modulaire.
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Le compte n'est tenu qu'une fois une limite de mémoire demandée : sans
+/// elle, l'allocateur ne coûte pas plus cher que celui du système.
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+fn count(added: usize, removed: usize) {
+    if !COUNTING.load(Ordering::Relaxed) {
+        return;
+    }
+    // `try_with` : le compteur peut déjà être détruit à la fin du thread.
+    let _ = ALLOCATED.try_with(|allocated| {
+        allocated.set(allocated.get().wrapping_add(added).wrapping_sub(removed));
+    });
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            count(layout.size(), 0);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        count(0, layout.size());
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_pointer = System.realloc(pointer, layout, new_size);
+        if !new_pointer.is_null() {
+            count(new_size, layout.size());
+        }
+        new_pointer
+    }
+}
+
+/// Solde des octets alloués et libérés par le thread courant depuis la
+/// première limite de mémoire : textes, environnements, fonctions, mais aussi
+/// l'arbre syntaxique et le bytecode. Seule sa variation a un sens.
+pub fn allocated() -> usize {
+    ALLOCATED.try_with(Cell::get).unwrap_or(0)
+}
+
+/// Unité de `--memoire-max`.
+pub const MEGABYTE: usize = 1024 * 1024;
+
+/// Limites d'une exécution, données par `--etapes-max`, `--temps-max` et
+/// `--memoire-max`, ou fixées par le programme qui embarque l'interpréteur.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Nombre d'étapes : instructions et expressions évaluées par
+    /// l'interpréteur d'arbre, instructions du bytecode pour la machine
+    /// virtuelle.
+    pub steps: Option<u64>,
+    /// Durée écoulée depuis le début de l'exécution.
+    pub time: Option<Duration>,
+    /// Octets alloués en plus de ceux présents au début de l'exécution.
+    pub memory: Option<usize>,
+}
+
+impl Limits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Limits::default()
+    }
+}
+
+/// Le temps n'est mesuré qu'une fois toutes les `TIME_CHECK_INTERVAL` étapes.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// Consommation d'une exécution, comparée à ses limites à chaque étape.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    limits: Limits,
+    /// Faux sans aucune limite : `charge` ne compte alors rien.
+    limited: bool,
+    steps: u64,
+    started: Instant,
+    memory_base: usize,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        if limits.memory.is_some() {
+            COUNTING.store(true, Ordering::Relaxed);
+        }
+        Budget {
+            limits,
+            limited: !limits.is_unlimited(),
+            steps: 0,
+            started: Instant::now(),
+            memory_base: allocated(),
+        }
+    }
+
+    /// Repart de zéro, au début d'une exécution.
+    pub fn restart(&mut self) {
+        *self = Budget::new(self.limits);
+    }
+
+    /// Compte une étape ; renvoie l'erreur E0319, E0320 ou E0321 quand une
+    /// limite est dépassée.
+    #[inline]
+    pub fn charge(&mut self) -> Result<(), Message> {
+        if !self.limited {
+            return Ok(());
+        }
+        self.steps += 1;
+        if let Some(max) = self.limits.steps {
+            if self.steps > max {
+                return Err(Message::new("E0319", &[&max]));
+            }
+        }
+        if let Some(max) = self.limits.memory {
+            let used = allocated().wrapping_sub(self.memory_base) as isize;
+            if used > max as isize {
+                return Err(Message::new("E0321", &[&(max as f64 / MEGABYTE as f64)]));
+            }
+        }
+        if let Some(max) = self.limits.time {
+            if self.steps.is_multiple_of(TIME_CHECK_INTERVAL) && self.started.elapsed() > max {
+                return Err(Message::new("E0320", &[&max.as_secs_f64()]));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,30 +1,64 @@
-use crate::{value::Value, interpret_visitor::InterpretVisitor, error::RuntimeError};
+use crate::{catalog::Message, gc, value::{NativeFunction, Value}};
 
-pub fn clock(_interpreter: &mut InterpretVisitor, _arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+pub fn clock(_arguments: Vec<Value>) -> Result<Value, Message> {
     let now = std::time::SystemTime::now();
-    let since_the_epoch = now.duration_since(std::time::UNIX_EPOCH).unwrap();
+    let since_the_epoch = now.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
     let time = since_the_epoch.as_secs_f64();
     Ok(Value::Number(time))
 }
 
-pub fn afficher(_interpreter: &mut InterpretVisitor, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+pub fn afficher(arguments: Vec<Value>) -> Result<Value, Message> {
     for arg in arguments {
         println!("{:?}", arg);
     }
     Ok(Value::Nil)
 }
 
-pub fn lire(_interpreter: &mut InterpretVisitor, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+pub fn lire(arguments: Vec<Value>) -> Result<Value, Message> {
     for arg in arguments {
       println!("{:?}", arg.to_string());
     }
 
     let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
+    std::io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| Message::new("E0315", &[&e]))?;
     let input = input.trim();
-    Ok(Value::String(input.to_string()))
+    Ok(Value::String(input.into()))
 }
 
-pub fn to_int(_interpreter: &mut InterpretVisitor, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
-    Ok(Value::Number(arguments[0].to_string().parse::<f64>().unwrap()))
-}
\ No newline at end of file
+pub fn to_int(arguments: Vec<Value>) -> Result<Value, Message> {
+    let text = arguments[0].to_string();
+    match text.trim().parse::<f64>() {
+        Ok(number) => Ok(Value::Number(number)),
+        Err(_) => Err(Message::new("E0316", &[&text])),
+    }
+}
+/// `gc()` : libère les cycles inaccessibles et renvoie le nombre d'objets
+/// libérés.
+pub fn ramasse_miettes(_arguments: Vec<Value>) -> Result<Value, Message> {
+    Ok(Value::Number(gc::collect() as f64))
+}
+
+/// `memoire()` : objets suivis par le ramasse-miettes et bilan des collectes.
+pub fn memoire(_arguments: Vec<Value>) -> Result<Value, Message> {
+    Ok(Value::String(gc::statistics().into()))
+}
+
+/// Fonctions natives définies dans les globales de chaque moteur, avec le nom
+/// sous lequel elles sont visibles.
+pub fn natives() -> Vec<(&'static str, NativeFunction)> {
+    let native = |arity, name: &str, function| NativeFunction {
+        arity,
+        name: name.to_string(),
+        function,
+    };
+    vec![
+        ("clock", native(0, "clock", clock)),
+        ("Nombre", native(1, "entier", to_int)),
+        ("print", native(1, "print", afficher)),
+        ("lire", native(1, "lire", lire)),
+        ("gc", native(0, "gc", ramasse_miettes)),
+        ("memoire", native(0, "memoire", memoire)),
+    ]
+}
@@ -1,7 +1,8 @@
-use crate::{interpret_visitor::InterpretVisitor, value::Value, error::RuntimeError};
+use crate::{interpret_visitor::InterpretVisitor, value::Value, error::RuntimeError, expr::CallExp};
 
 pub trait Callable {
     fn arity(&self) -> usize;
-    fn call(&self, interpreter: &mut InterpretVisitor, arguments: Vec<Value>) -> Result<Value, RuntimeError>;
+    /// `call` est l'expression d'appel, à laquelle les erreurs sont rattachées.
+    fn call(&self, interpreter: &mut InterpretVisitor, arguments: Vec<Value>, call: &CallExp) -> Result<Value, RuntimeError>;
 }
 
@@ -0,0 +1,627 @@
+use std::{fmt, sync::OnceLock};
+
+/// Langue des messages affichés à l'utilisateur.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    French,
+    English,
+}
+
+impl Language {
+    /// `fr` ou `en`, tels qu'acceptés par `--langue`.
+    pub fn parse(code: &str) -> Option<Language> {
+        match code {
+            "fr" => Some(Language::French),
+            "en" => Some(Language::English),
+            _ => None,
+        }
+    }
+
+    /// Langue déduite de la variable LANG (`en_US.UTF-8` donne l'anglais).
+    /// Le français reste la langue par défaut.
+    pub fn from_env() -> Language {
+        match std::env::var("LANG") {
+            Ok(lang) if lang.starts_with("en") => Language::English,
+            _ => Language::French,
+        }
+    }
+}
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Choisit la langue des messages. Seul le premier appel compte.
+pub fn set_language(language: Language) {
+    let _ = LANGUAGE.set(language);
+}
+
+pub fn language() -> Language {
+    LANGUAGE.get().copied().unwrap_or(Language::French)
+}
+
+/// Entrée du catalogue. Les messages d'erreur (`E....`) et d'avertissement
+/// (`W....`) ont un code stable, affiché avec le diagnostic et expliqué par
+/// `frenchy expliquer` ; les autres clés désignent des textes d'accompagnement
+/// (étiquettes, notes, aides).
+struct Entry {
+    key: &'static str,
+    fr: &'static str,
+    en: &'static str,
+}
+
+const fn entry(key: &'static str, fr: &'static str, en: &'static str) -> Entry {
+    Entry { key, fr, en }
+}
+
+const ENTRIES: &[Entry] = &[
+    // Analyse lexicale.
+    entry("E0001", "caractère inattendu '{}'.", "unexpected character '{}'."),
+    entry("E0002", "chaîne de caractères non terminée.", "unterminated string."),
+    entry(
+        "E0003",
+        "mélange de tabulations et d'espaces dans l'indentation.",
+        "indentation mixes tabs and spaces.",
+    ),
+    entry(
+        "E0004",
+        "l'indentation ne correspond à aucun bloc englobant.",
+        "indentation does not match any enclosing block.",
+    ),
+    entry(
+        "E0005",
+        "indentation incohérente : {} ici, {} dans le bloc englobant.",
+        "inconsistent indentation: {} here, {} in the enclosing block.",
+    ),
+    entry("E0006", "littéral numérique mal formé '{}'.", "malformed number literal '{}'."),
+    entry("E0007", "séparateur '_' mal placé dans un nombre.", "misplaced '_' separator in a number."),
+    entry("E0008", "un nombre ne peut pas se terminer par '_'.", "a number cannot end with '_'."),
+    entry("E0009", "chiffre '{}' invalide en base {}.", "invalid digit '{}' in base {}."),
+    entry("E0010", "aucun chiffre après le préfixe '{}'.", "no digits after prefix '{}'."),
+    entry("E0011", "nombre trop grand pour la base {}.", "number too large for base {}."),
+    entry("E0012", "exposant sans chiffres après '{}'.", "exponent has no digits after '{}'."),
+    entry("indentation.tabulation", "une tabulation", "a tab"),
+    entry("indentation.espace", "un espace", "a space"),
+    // Analyse syntaxique.
+    entry(
+        "E0101",
+        "';' ou fin de ligne attendu après la valeur de retour.",
+        "expected ';' or newline after return value.",
+    ),
+    entry("E0102", "'(' attendu après 'for'.", "expected '(' after 'for'."),
+    entry("E0103", "';' attendu après la condition de la boucle.", "expected ';' after loop condition."),
+    entry("E0104", "')' attendu après les clauses du 'for'.", "expected ')' after 'for' clauses."),
+    entry(
+        "E0105",
+        "'finsi' ou 'sinon' attendu après le bloc 'si'.",
+        "expected 'finsi' or 'sinon' after 'si' block.",
+    ),
+    entry("E0106", "'fin' attendu après le bloc.", "expected 'fin' after block."),
+    entry(
+        "E0107",
+        "retour à l'indentation précédente attendu après le bloc indenté.",
+        "expected dedent after indented block.",
+    ),
+    entry("E0108", "'}' attendu après le bloc.", "expected '}' after block."),
+    entry("E0109", "nom de fonction attendu.", "expected function name."),
+    entry("E0110", "'(' attendu après le nom de la fonction.", "expected '(' after function name."),
+    entry(
+        "E0111",
+        "une fonction ne peut pas avoir plus de 255 paramètres.",
+        "a function cannot have more than 255 parameters.",
+    ),
+    entry("E0112", "nom de paramètre attendu.", "expected parameter name."),
+    entry("E0113", "')' attendu après les paramètres.", "expected ')' after parameters."),
+    entry(
+        "E0114",
+        "'debut' attendu avant le corps de la fonction.",
+        "expected 'debut' before function body.",
+    ),
+    entry("E0115", "nom de variable attendu.", "expected variable name."),
+    entry(
+        "E0116",
+        "';' ou fin de ligne attendu après la déclaration de variable.",
+        "expected ';' or newline after variable declaration.",
+    ),
+    entry(
+        "E0117",
+        "';' ou fin de ligne attendu après la valeur à écrire.",
+        "expected ';' or newline after value.",
+    ),
+    entry(
+        "E0118",
+        "';' ou fin de ligne attendu après l'expression.",
+        "expected ';' or newline after expression.",
+    ),
+    entry("E0119", "cible d'affectation invalide.", "invalid assignment target."),
+    entry("E0120", "')' attendu après les arguments.", "expected ')' after arguments."),
+    entry("E0121", "')' attendu après l'expression.", "expected ')' after expression."),
+    entry("E0122", "expression attendue.", "expected expression."),
+    entry(
+        "E0123",
+        "expression trop imbriquée : plus de {} niveaux.",
+        "expression nested too deeply: more than {} levels.",
+    ),
+    entry("syntaxe.trouve", "trouvé '{}'", "found '{}'"),
+    entry("syntaxe.fin-fichier", "fin du fichier", "end of file"),
+    entry("syntaxe.fin-ligne", "fin de ligne", "end of line"),
+    entry("syntaxe.indentation", "changement d'indentation", "indentation change"),
+    // Analyse des noms.
+    entry(
+        "E0201",
+        "'{}' est déjà déclarée dans cette portée.",
+        "'{}' is already declared in this scope.",
+    ),
+    entry("E0202", "'{}' n'est pas déclarée.", "'{}' is not declared."),
+    entry(
+        "E0203",
+        "affectation à '{}', qui n'est pas déclarée.",
+        "assignment to '{}', which is not declared.",
+    ),
+    entry("E0204", "'{}' est utilisée avant sa déclaration.", "'{}' is used before its declaration."),
+    entry(
+        "E0205",
+        "'{}' attend {} argument(s), mais en reçoit {}.",
+        "'{}' expects {} argument(s) but receives {}.",
+    ),
+    entry(
+        "E0206",
+        "'{}' est lue dans sa propre initialisation.",
+        "'{}' is read in its own initializer.",
+    ),
+    entry("E0207", "'retourner' en dehors d'une fonction.", "'retourner' outside of a function."),
+    entry("W0001", "variable '{}' inutilisée.", "unused variable '{}'."),
+    entry("W0002", "paramètre '{}' inutilisé.", "unused parameter '{}'."),
+    entry("W0003", "fonction '{}' jamais appelée.", "function '{}' is never called."),
+    entry("W0004", "code inaccessible.", "unreachable code."),
+    entry("W0005", "condition toujours vraie.", "condition is always true."),
+    entry("W0006", "condition toujours fausse.", "condition is always false."),
+    entry("W0007", "'{}' masque une variable de même nom.", "'{}' shadows a variable with the same name."),
+    entry(
+        "W0008",
+        "comparaison entre un texte lu avec 'lire' et un nombre.",
+        "comparison between text read with 'lire' and a number.",
+    ),
+    entry("noms.declaree-ici", "déclarée ici", "declared here"),
+    entry("noms.fonction-declaree-ici", "fonction déclarée ici", "function declared here"),
+    entry("noms.nouvelle-declaration", "nouvelle déclaration", "new declaration"),
+    entry("noms.renommer", "renommez l'une des deux variables.", "rename one of the two variables."),
+    entry("noms.introuvable", "introuvable dans cette portée", "not found in this scope"),
+    entry(
+        "noms.declarer-avant",
+        "déclarez-la d'abord avec 'variable {} <- ...'.",
+        "declare it first with 'variable {} <- ...'.",
+    ),
+    entry(
+        "noms.initialisation",
+        "une variable n'existe qu'une fois son initialisation terminée.",
+        "a variable only exists once its initializer has finished.",
+    ),
+    entry(
+        "noms.supprimer-variable",
+        "supprimez-la, ou renommez-la '_{}'.",
+        "remove it, or rename it '_{}'.",
+    ),
+    entry(
+        "noms.renommer-parametre",
+        "renommez-le '_{}' s'il est inutile.",
+        "rename it '_{}' if it is not needed.",
+    ),
+    entry("noms.fin-fonction", "la fonction se termine ici", "the function returns here"),
+    entry("noms.variable-masquee", "variable masquée déclarée ici", "shadowed variable declared here"),
+    entry("noms.toujours-faux", "toujours faux", "always false"),
+    entry("noms.toujours-vrai", "toujours vrai", "always true"),
+    // Exécution.
+    entry("E0301", "{} argument(s) attendu(s), {} reçu(s)", "expected {} argument(s), got {}"),
+    entry(
+        "E0302",
+        "impossible d'appeler une valeur de type {}",
+        "cannot call a value of type {}",
+    ),
+    entry("E0303", "opérateur inconnu '{}'", "unknown operator '{}'"),
+    entry("E0304", "nombre invalide '{}'", "invalid number '{}'"),
+    entry(
+        "E0305",
+        "l'opérande doit être un nombre ou un booléen",
+        "operand must be a number or a boolean",
+    ),
+    entry("E0306", "impossible d'additionner {} et {}", "cannot add {} and {}"),
+    entry("E0307", "impossible de soustraire {} et {}", "cannot subtract {} and {}"),
+    entry("E0308", "impossible de multiplier {} et {}", "cannot multiply {} and {}"),
+    entry("E0309", "impossible de diviser {} et {}", "cannot divide {} and {}"),
+    entry(
+        "E0310",
+        "impossible de calculer le modulo de {} et {}",
+        "cannot compute the modulo of {} and {}",
+    ),
+    entry("E0311", "division par zéro", "division by zero"),
+    entry("E0312", "modulo par zéro", "modulo by zero"),
+    entry("E0313", "variable '{}' non définie", "undefined variable '{}'"),
+    entry(
+        "E0314",
+        "portée introuvable pour la variable '{}'",
+        "no scope found for variable '{}'",
+    ),
+    entry("E0315", "impossible de lire l'entrée : {}", "cannot read input: {}"),
+    entry("E0316", "impossible de convertir '{}' en nombre", "cannot convert '{}' to a number"),
+    entry(
+        "E0317",
+        "'retourner' utilisé en dehors d'une fonction.",
+        "'retourner' used outside of a function.",
+    ),
+    entry(
+        "E0318",
+        "débordement de pile : plus de {} appels imbriqués",
+        "stack overflow: more than {} nested calls",
+    ),
+    entry(
+        "E0319",
+        "budget d'étapes épuisé : plus de {} étapes exécutées",
+        "step budget exhausted: more than {} steps executed",
+    ),
+    entry(
+        "E0320",
+        "temps d'exécution dépassé : plus de {} s",
+        "execution time exceeded: more than {} s",
+    ),
+    entry(
+        "E0321",
+        "mémoire dépassée : plus de {} Mo alloués",
+        "memory exceeded: more than {} MB allocated",
+    ),
+    entry(
+        "E0322",
+        "pile du système épuisée après {} appels imbriqués",
+        "system stack exhausted after {} nested calls",
+    ),
+    entry(
+        "memoire.statistiques",
+        "{} environnements, {} fermetures, {} variables capturées ; {} collectes, {} objets libérés",
+        "{} environments, {} closures, {} captured variables; {} collections, {} objects freed",
+    ),
+    entry("type.nombre", "nombre", "number"),
+    entry("type.texte", "texte", "text"),
+    entry("type.booleen", "booléen", "boolean"),
+    entry("type.vide", "vide", "nil"),
+    entry("type.fonction", "fonction", "function"),
+    // Ligne de commande.
+    entry(
+        "E0401",
+        "le fichier doit avoir l'extension .fr, .fri ou .frc : {}",
+        "the file must have a .fr, .fri or .frc extension: {}",
+    ),
+    entry("E0402", "impossible de lire '{}' : {}", "cannot read '{}': {}"),
+    entry("E0403", "option inconnue : {}", "unknown option: {}"),
+    entry("E0404", "un seul script peut être exécuté : {}", "only one script can be run: {}"),
+    entry("E0405", "code d'erreur inconnu : {}", "unknown error code: {}"),
+    entry("E0406", "fichier compilé invalide '{}' : {}", "invalid compiled file '{}': {}"),
+    entry(
+        "E0407",
+        "'{}' a été compilé au format {}, cette version de frenchy lit le format {}",
+        "'{}' was compiled with format {}, this version of frenchy reads format {}",
+    ),
+    entry(
+        "E0408",
+        "la commande '{}' attend un fichier source",
+        "the '{}' command expects a source file",
+    ),
+    entry("E0409", "impossible d'écrire '{}' : {}", "cannot write '{}': {}"),
+    entry(
+        "E0410",
+        "'-o' doit être suivi d'un nom de fichier",
+        "'-o' must be followed by a file name",
+    ),
+    entry(
+        "E0411",
+        "profondeur maximale invalide '{}' : un entier positif est attendu",
+        "invalid maximum depth '{}': a positive integer is expected",
+    ),
+    entry(
+        "E0412",
+        "impossible de réserver une pile de {} octets : {}",
+        "cannot reserve a stack of {} bytes: {}",
+    ),
+    entry("E0413", "valeur invalide '{}' pour {}", "invalid value '{}' for {}"),
+    entry("frc.signature", "signature absente", "missing signature"),
+    entry("frc.tronque", "fichier tronqué", "truncated file"),
+    entry("frc.fonction", "fonction n°{} illisible", "unreadable function #{}"),
+    entry(
+        "frc.instruction",
+        "instruction invalide dans '{}' à la position {}",
+        "invalid instruction in '{}' at offset {}",
+    ),
+    entry("frc.reste", "données en trop après le programme", "trailing data after the program"),
+    // Compilation en bytecode (`--moteur=vm`).
+    entry(
+        "E0501",
+        "trop de constantes dans la fonction '{}' (16777216 au plus)",
+        "too many constants in function '{}' (16777216 at most)",
+    ),
+    entry(
+        "E0502",
+        "trop de variables locales dans la fonction '{}' (256 au plus)",
+        "too many local variables in function '{}' (256 at most)",
+    ),
+    entry(
+        "E0503",
+        "trop de variables capturées par la fonction '{}' (256 au plus)",
+        "too many variables captured by function '{}' (256 at most)",
+    ),
+    entry(
+        "E0504",
+        "bloc trop long pour la machine virtuelle (65535 octets au plus)",
+        "block too long for the virtual machine (65535 bytes at most)",
+    ),
+    entry(
+        "E0505",
+        "trop d'arguments dans un appel (255 au plus)",
+        "too many arguments in a call (255 at most)",
+    ),
+    entry(
+        "cli.usage",
+        "Usage : {} [--virgule-decimale] [--format-erreurs=texte|json] \
+         [--couleur=auto|toujours|jamais] [--langue=fr|en] [--moteur=arbre|vm] [--profondeur-max=<n>] \
+         [--etapes-max=<n>] [--temps-max=<s>] [--memoire-max=<Mo>] \
+         [-O] [--dump-ast-optimise] [--warnings-as-errors] [<script.fr|script.fri|script.frc>]\n        {} compiler <script.fr> [-o <script.frc>]\n        \
+         {} desassembler <script.frc|script.fr>\n        {} expliquer [<code>]\n\
+         Statut de sortie : 0 en cas de succès, 1 en cas d'erreur, 2, 3 ou 4 quand \
+         --etapes-max, --temps-max ou --memoire-max arrête l'exécution.",
+        "Usage: {} [--virgule-decimale] [--format-erreurs=texte|json] \
+         [--couleur=auto|toujours|jamais] [--langue=fr|en] [--moteur=arbre|vm] [--profondeur-max=<n>] \
+         [--etapes-max=<n>] [--temps-max=<s>] [--memoire-max=<MB>] \
+         [-O] [--dump-ast-optimise] [--warnings-as-errors] [<script.fr|script.fri|script.frc>]\n       {} compiler <script.fr> [-o <script.frc>]\n       \
+         {} desassembler <script.frc|script.fr>\n       {} expliquer [<code>]\n\
+         Exit status: 0 on success, 1 on error, 2, 3 or 4 when \
+         --etapes-max, --temps-max or --memoire-max stops the run.",
+    ),
+    entry("cli.lecture", "Erreur de lecture. Fin du REPL.", "Read error. Leaving the REPL."),
+    entry("cli.saisie", "<saisie {}>", "<input {}>"),
+    entry(
+        "cli.codes",
+        "Codes connus (frenchy expliquer <code> pour le détail) :",
+        "Known codes (frenchy expliquer <code> for details):",
+    ),
+    // Désassemblage des fichiers `.frc`.
+    entry(
+        "desassemblage.fonction",
+        "== {} : {} paramètre(s), {} variable(s) capturée(s) ==",
+        "== {}: {} parameter(s), {} captured variable(s) ==",
+    ),
+    entry("desassemblage.locale", "locale {}", "local {}"),
+    entry("desassemblage.capturee", "capturée {}", "captured {}"),
+    // Affichage des diagnostics.
+    entry("diagnostic.erreur", "erreur", "error"),
+    entry("diagnostic.avertissement", "avertissement", "warning"),
+    entry("diagnostic.note", "note", "note"),
+    entry("diagnostic.aide", "aide", "help"),
+    entry(
+        "diagnostic.pile",
+        "pile d'appels (la plus récente en premier) :",
+        "call stack (most recent first):",
+    ),
+    entry("diagnostic.appel", "{}({}) appelée depuis {}", "{}({}) called from {}"),
+    entry("diagnostic.appels-identiques", "... {} appels identiques à {}", "... {} identical calls to {}"),
+    // Suggestions.
+    entry("suggestion.anglais", "en frenchy, '{}' s'écrit '{}'.", "in frenchy, '{}' is written '{}'."),
+    entry("suggestion.proche", "vouliez-vous dire '{}' ?", "did you mean '{}'?"),
+    // Conseils pour débutants.
+    entry(
+        "conseil.texte-calcul",
+        "un texte qui contient des chiffres n'est pas un nombre ; c'est le cas de tout ce que renvoie 'lire'.",
+        "text made of digits is not a number; everything 'lire' returns is text.",
+    ),
+    entry(
+        "conseil.texte-calcul.aide",
+        "convertissez la valeur avec Nombre(...) avant de calculer.",
+        "convert the value with Nombre(...) before computing.",
+    ),
+    entry(
+        "conseil.texte-comparaison",
+        "'lire' renvoie un texte : \"5\" et 5 ne sont jamais égaux.",
+        "'lire' returns text: \"5\" and 5 are never equal.",
+    ),
+    entry(
+        "conseil.texte-comparaison.aide",
+        "lisez un nombre avec Nombre(lire(...)).",
+        "read a number with Nombre(lire(...)).",
+    ),
+    entry(
+        "conseil.texte-comparaison.aide-variable",
+        "lisez un nombre avec Nombre(lire(...)), ou comparez Nombre({}).",
+        "read a number with Nombre(lire(...)), or compare Nombre({}).",
+    ),
+    entry(
+        "conseil.egal-comparaison",
+        "'=' seul n'existe pas en frenchy : pour comparer deux valeurs, on écrit '=='.",
+        "a single '=' does not exist in frenchy: to compare two values, write '=='.",
+    ),
+    entry("conseil.egal-comparaison.aide", "remplacez '=' par '=='.", "replace '=' with '=='."),
+    entry(
+        "conseil.egal-affectation",
+        "en frenchy, on donne une valeur à une variable avec '<-', pas avec '='.",
+        "in frenchy, a variable is given a value with '<-', not '='.",
+    ),
+    entry("conseil.egal-affectation.aide", "écrivez '{} <- ...'.", "write '{} <- ...'."),
+    entry(
+        "conseil.egal-declaration.aide",
+        "écrivez 'variable {} <- ...'.",
+        "write 'variable {} <- ...'.",
+    ),
+    entry(
+        "conseil.mot-isole",
+        "ce '{}' ne correspond à aucun bloc 'si ... alors' encore ouvert.",
+        "this '{}' does not match any open 'si ... alors' block.",
+    ),
+    entry(
+        "conseil.mot-isole.aide",
+        "supprimez ce '{}', ou vérifiez où se ferme le bloc 'si' précédent.",
+        "remove this '{}', or check where the previous 'si' block ends.",
+    ),
+    entry(
+        "conseil.si-sans-alors",
+        "sans 'alors', un 'si' ne contrôle que l'instruction qui le suit : ce '{}' se retrouve seul.",
+        "without 'alors', a 'si' only controls the next statement: this '{}' is left on its own.",
+    ),
+    entry(
+        "conseil.si-sans-alors.aide",
+        "ajoutez 'alors' à la fin de la ligne du 'si'.",
+        "add 'alors' at the end of the 'si' line.",
+    ),
+    entry("conseil.si-sans-alors.origine", "ce 'si' n'a pas de 'alors'", "this 'si' has no 'alors'"),
+    entry(
+        "conseil.sinon-sans-alors",
+        "sans 'alors', un 'sinon' ne contrôle que l'instruction qui le suit : ce '{}' se retrouve seul.",
+        "without 'alors', a 'sinon' only controls the next statement: this '{}' is left on its own.",
+    ),
+    entry(
+        "conseil.sinon-sans-alors.aide",
+        "écrivez 'sinon alors' pour que le bloc aille jusqu'au 'finsi'.",
+        "write 'sinon alors' so that the block runs up to 'finsi'.",
+    ),
+    entry("conseil.sinon-sans-alors.origine", "ce 'sinon' n'a pas de 'alors'", "this 'sinon' has no 'alors'"),
+    entry(
+        "conseil.finsi",
+        "chaque bloc 'si ... alors' doit être refermé par 'finsi'.",
+        "every 'si ... alors' block must be closed with 'finsi'.",
+    ),
+    entry(
+        "conseil.finsi.aide",
+        "ajoutez 'finsi' après la dernière instruction du bloc.",
+        "add 'finsi' after the last statement of the block.",
+    ),
+    entry("conseil.finsi.origine", "bloc ouvert ici", "block opened here"),
+];
+
+/// Noms courts des avertissements, acceptés par `frenchy:ignorer` en plus
+/// des codes.
+const ALIASES: &[(&str, &str)] = &[
+    ("W0001", "inutilise"),
+    ("W0002", "inutilise"),
+    ("W0003", "jamais-appelee"),
+    ("W0004", "inaccessible"),
+    ("W0005", "condition-constante"),
+    ("W0006", "condition-constante"),
+    ("W0007", "masquage"),
+    ("W0008", "texte-nombre"),
+];
+
+/// Texte `key` dans la langue choisie, où chaque `{}` est remplacé dans
+/// l'ordre par les `args`. Une clé inconnue est renvoyée telle quelle.
+pub fn text(key: &str, args: &[&dyn fmt::Display]) -> String {
+    let Some(entry) = ENTRIES.iter().find(|entry| entry.key == key) else {
+        return key.to_string();
+    };
+    let template = match language() {
+        Language::French => entry.fr,
+        Language::English => entry.en,
+    };
+
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        out.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+pub fn alias(code: &str) -> Option<&'static str> {
+    ALIASES
+        .iter()
+        .find(|(candidate, _)| *candidate == code)
+        .map(|(_, alias)| *alias)
+}
+
+/// Codes d'erreur et d'avertissement, dans l'ordre du catalogue.
+pub fn codes() -> impl Iterator<Item = &'static str> {
+    ENTRIES
+        .iter()
+        .map(|entry| entry.key)
+        .filter(|key| is_code(key))
+}
+
+pub fn is_code(key: &str) -> bool {
+    key.len() == 5
+        && key.starts_with(['E', 'W'])
+        && key[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Message d'erreur ou d'avertissement, avec son code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub code: &'static str,
+    pub text: String,
+}
+
+impl Message {
+    pub fn new(code: &'static str, args: &[&dyn fmt::Display]) -> Message {
+        Message {
+            code,
+            text: text(code, args),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explanations::explanation;
+
+    #[test]
+    fn arguments_replace_placeholders_in_order() {
+        assert_eq!(text("E0009", &[&'9', &8]), "chiffre '9' invalide en base 8.");
+        assert_eq!(Message::new("E0001", &[&'€']).to_string(), "caractère inattendu '€'.");
+        // Les arguments manquants laissent le texte qui suit.
+        assert_eq!(text("E0009", &[&'9']), "chiffre '9' invalide en base .");
+        assert_eq!(text("cle.inconnue", &[&1]), "cle.inconnue");
+    }
+
+    #[test]
+    fn entries_are_unique_and_translated_alike() {
+        for (index, entry) in ENTRIES.iter().enumerate() {
+            assert!(
+                ENTRIES[index + 1..].iter().all(|other| other.key != entry.key),
+                "clé en double : {}",
+                entry.key
+            );
+            assert_eq!(entry.fr.matches("{}").count(), entry.en.matches("{}").count(), "{}", entry.key);
+        }
+    }
+
+    #[test]
+    fn every_code_is_explained_in_both_languages() {
+        for code in codes() {
+            assert!(explanation(code, Language::French).is_some(), "{code}");
+            assert!(explanation(code, Language::English).is_some(), "{code}");
+        }
+        assert_eq!(explanation("E9999", Language::French), None);
+    }
+
+    #[test]
+    fn aliases_name_existing_warnings() {
+        for (code, _) in ALIASES {
+            assert!(code.starts_with('W') && codes().any(|other| other == *code), "{code}");
+        }
+        assert_eq!(alias("W0007"), Some("masquage"));
+        assert_eq!(alias("E0202"), None);
+    }
+
+    #[test]
+    fn languages() {
+        assert_eq!(Language::parse("fr"), Some(Language::French));
+        assert_eq!(Language::parse("en"), Some(Language::English));
+        assert_eq!(Language::parse("de"), None);
+        assert!(is_code("E0123") && is_code("W0008"));
+        assert!(!is_code("E012") && !is_code("X0001") && !is_code("noms.fin-fonction"));
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+
+use crate::catalog::{self, Message};
+use crate::span::{FileId, SourceMap, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    /// Nom stable, utilisé dans la sortie JSON.
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "erreur",
+            Severity::Warning => "avertissement",
+        }
+    }
+
+    /// Nom affiché, dans la langue choisie.
+    fn label(&self) -> String {
+        catalog::text(&format!("diagnostic.{}", self.name()), &[])
+    }
+}
+
+/// Portion du source soulignée dans un diagnostic, avec un texte facultatif.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+/// Appel en cours au moment d'une erreur d'exécution.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: String,
+    /// Valeurs des arguments, déjà mises en forme.
+    pub arguments: String,
+    pub call_site: Span,
+}
+
+/// Nombre d'appels identiques consécutifs affichés avant de les résumer.
+const REPEATED_FRAMES_SHOWN: usize = 3;
+
+/// Message destiné à l'utilisateur, produit par n'importe quelle phase
+/// (lexer, parser, résolution, exécution).
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    /// Pile d'appels, l'appel le plus récent en premier.
+    pub trace: Vec<Frame>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: Message) -> Self {
+        Diagnostic {
+            severity,
+            code: Some(message.code.to_string()),
+            message: message.text,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn error(message: Message) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: Message) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    /// Souligne l'endroit principal de l'erreur (`^^^^`).
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    /// Ajoute un endroit secondaire (`----`), par exemple une déclaration.
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_trace(mut self, trace: Vec<Frame>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    fn primary_span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .or(self.labels.first())
+            .map(|label| label.span)
+    }
+}
+
+/// Lignes visées par un commentaire `// frenchy:ignorer code1, code2`. Le
+/// commentaire s'applique à sa ligne, ou à la suivante s'il est seul sur la
+/// sienne. Sans code, tous les avertissements de la ligne sont ignorés.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    lines: HashMap<(FileId, u32), Vec<String>>,
+}
+
+impl Suppressions {
+    pub const MARKER: &'static str = "frenchy:ignorer";
+
+    pub fn add(&mut self, file: FileId, line: u32, codes: Vec<String>) {
+        self.lines.entry((file, line)).or_default().extend(codes);
+    }
+
+    /// Vrai si l'avertissement est désactivé par un commentaire. Les erreurs
+    /// ne peuvent pas être ignorées.
+    pub fn covers(&self, diagnostic: &Diagnostic) -> bool {
+        if diagnostic.severity != Severity::Warning {
+            return false;
+        }
+        let Some(span) = diagnostic.primary_span() else {
+            return false;
+        };
+        self.lines.get(&(span.file, span.line)).is_some_and(|codes| {
+            codes.is_empty()
+                || diagnostic
+                    .code
+                    .as_ref()
+                    .is_some_and(|code| {
+                        codes.contains(code)
+                            || catalog::alias(code).is_some_and(|alias| codes.iter().any(|c| c == alias))
+                    })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Color {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Affiche les diagnostics sur la sortie d'erreur, en texte (avec extraits
+/// du source) ou en JSON, une ligne par diagnostic.
+pub struct Emitter {
+    format: Format,
+    color: bool,
+}
+
+impl Emitter {
+    pub fn new(format: Format, color: Color) -> Self {
+        let color = match color {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => {
+                format == Format::Text
+                    && std::env::var_os("NO_COLOR").is_none()
+                    && io::stderr().is_terminal()
+            }
+        };
+        Emitter { format, color }
+    }
+
+    pub fn emit(&self, diagnostic: &Diagnostic, sources: &SourceMap) {
+        let rendered = self.render(diagnostic, sources);
+        let _ = io::stderr().write_all(rendered.as_bytes());
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        match self.format {
+            Format::Text => self.render_text(diagnostic, sources),
+            Format::Json => render_json(diagnostic, sources),
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn render_text(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        let style = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let header = match &diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity.label(), code),
+            None => diagnostic.severity.label(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(style, &header),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        );
+
+        let mut labels: Vec<&Label> = diagnostic
+            .labels
+            .iter()
+            .filter(|label| sources.get(label.span.file).is_some())
+            .collect();
+        // Le fichier principal d'abord, puis les autres (saisies précédentes
+        // du REPL, par exemple).
+        let primary_file = diagnostic.primary_span().map(|span| span.file);
+        labels.sort_by_key(|label| {
+            (
+                Some(label.span.file) != primary_file,
+                label.span.file,
+                label.span.line,
+                label.span.column,
+            )
+        });
+
+        let width = labels
+            .iter()
+            .map(|label| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+        let bar = self.paint(BLUE, "|");
+
+        if let Some(span) = diagnostic.primary_span() {
+            if let Some(file) = sources.get(span.file) {
+                let _ = writeln!(
+                    out,
+                    "{}{} {}:{}:{}",
+                    gutter,
+                    self.paint(BLUE, "-->"),
+                    file.name,
+                    span.line,
+                    span.column
+                );
+            }
+        }
+
+        if !labels.is_empty() {
+            let _ = writeln!(out, "{} {}", gutter, bar);
+        }
+        let mut previous_line = None;
+        let mut current_file = primary_file;
+        for label in &labels {
+            let file = sources.get(label.span.file).unwrap();
+            if current_file != Some(label.span.file) {
+                current_file = Some(label.span.file);
+                previous_line = None;
+                let _ = writeln!(
+                    out,
+                    "{}{} {}:{}:{}",
+                    gutter,
+                    self.paint(BLUE, ":::"),
+                    file.name,
+                    label.span.line,
+                    label.span.column
+                );
+                let _ = writeln!(out, "{} {}", gutter, bar);
+            }
+            let text = file.line(label.span.line).unwrap_or("");
+            if previous_line.is_some_and(|line| line + 1 < label.span.line) {
+                let _ = writeln!(out, "{}", self.paint(BLUE, "..."));
+            }
+            if previous_line != Some(label.span.line) {
+                let number = format!("{:>width$}", label.span.line, width = width);
+                let _ = writeln!(out, "{} {} {}", self.paint(BLUE, &number), bar, text);
+            }
+            previous_line = Some(label.span.line);
+
+            // Le soulignement reprend les tabulations de la ligne pour rester aligné.
+            let padding: String = text
+                .chars()
+                .take(label.span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let length = if label.span.end_line == label.span.line {
+                label.span.end_column.saturating_sub(label.span.column)
+            } else {
+                text.chars().count() + 1 - label.span.column.min(text.chars().count() + 1)
+            };
+            let (marker, marker_style) = if label.primary {
+                ('^', style)
+            } else {
+                ('-', BLUE)
+            };
+            let underline = marker.to_string().repeat(length.max(1));
+            let mut line = format!("{}{}", padding, underline);
+            if !label.message.is_empty() {
+                line.push(' ');
+                line.push_str(&label.message);
+            }
+            let _ = writeln!(out, "{} {} {}", gutter, bar, self.paint(marker_style, &line));
+        }
+
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "{} {} {}", gutter, self.paint(BLUE, "="), self.paint(BOLD, &format!("{}: {}", catalog::text("diagnostic.note", &[]), note)));
+        }
+        for help in &diagnostic.help {
+            let _ = writeln!(out, "{} {} {}", gutter, self.paint(BLUE, "="), self.paint(BOLD, &format!("{}: {}", catalog::text("diagnostic.aide", &[]), help)));
+        }
+        if !diagnostic.trace.is_empty() {
+            self.render_trace(&mut out, &diagnostic.trace, sources, &gutter);
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Pile d'appels. Les appels récursifs identiques (même fonction, même
+    /// endroit d'appel) qui se suivent sont résumés en une ligne.
+    fn render_trace(&self, out: &mut String, trace: &[Frame], sources: &SourceMap, gutter: &str) {
+        let _ = writeln!(
+            out,
+            "{} {} {}",
+            gutter,
+            self.paint(BLUE, "="),
+            self.paint(BOLD, &catalog::text("diagnostic.pile", &[]))
+        );
+        let mut index = 0;
+        while index < trace.len() {
+            let frame = &trace[index];
+            let run = trace[index..]
+                .iter()
+                .take_while(|other| {
+                    other.function == frame.function && other.call_site == frame.call_site
+                })
+                .count();
+            let shown = if run > REPEATED_FRAMES_SHOWN + 1 {
+                REPEATED_FRAMES_SHOWN
+            } else {
+                run
+            };
+            for frame in &trace[index..index + shown] {
+                let location = match sources.get(frame.call_site.file) {
+                    Some(file) => format!("{}:{}", file.name, frame.call_site),
+                    None => frame.call_site.to_string(),
+                };
+                let call = catalog::text(
+                    "diagnostic.appel",
+                    &[&frame.function, &frame.arguments, &location],
+                );
+                let _ = writeln!(out, "{}     {}", gutter, call);
+            }
+            if shown < run {
+                let repeated = catalog::text(
+                    "diagnostic.appels-identiques",
+                    &[&(run - shown), &frame.function],
+                );
+                let _ = writeln!(out, "{}     {}", gutter, repeated);
+            }
+            index += run;
+        }
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn render_json(diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+    let spans: Vec<String> = diagnostic
+        .labels
+        .iter()
+        .map(|label| {
+            let file = sources
+                .get(label.span.file)
+                .map_or("null".to_string(), |file| json_string(&file.name));
+            format!(
+                "{{\"file\":{},\"byte_start\":{},\"byte_end\":{},\"line_start\":{},\"column_start\":{},\"line_end\":{},\"column_end\":{},\"primary\":{},\"label\":{}}}",
+                file,
+                label.span.start,
+                label.span.end,
+                label.span.line,
+                label.span.column,
+                label.span.end_line,
+                label.span.end_column,
+                label.primary,
+                json_string(&label.message)
+            )
+        })
+        .collect();
+    let notes: Vec<String> = diagnostic.notes.iter().map(|note| json_string(note)).collect();
+    let help: Vec<String> = diagnostic.help.iter().map(|help| json_string(help)).collect();
+    let trace: Vec<String> = diagnostic
+        .trace
+        .iter()
+        .map(|frame| {
+            let file = sources
+                .get(frame.call_site.file)
+                .map_or("null".to_string(), |file| json_string(&file.name));
+            format!(
+                "{{\"function\":{},\"arguments\":{},\"file\":{},\"line\":{},\"column\":{}}}",
+                json_string(&frame.function),
+                json_string(&frame.arguments),
+                file,
+                frame.call_site.line,
+                frame.call_site.column
+            )
+        })
+        .collect();
+    format!(
+        "{{\"severity\":{},\"code\":{},\"message\":{},\"spans\":[{}],\"notes\":[{}],\"help\":[{}],\"trace\":[{}]}}\n",
+        json_string(diagnostic.severity.name()),
+        diagnostic
+            .code
+            .as_deref()
+            .map_or("null".to_string(), json_string),
+        json_string(&diagnostic.message),
+        spans.join(","),
+        notes.join(","),
+        help.join(","),
+        trace.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "variable total <- 0\necrire totl + 1\n";
+
+    /// Span de la première occurrence de `needle` dans `SOURCE`.
+    fn span_of(needle: &str) -> Span {
+        let start = SOURCE.find(needle).unwrap();
+        let line_start = SOURCE[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line = SOURCE[..start].matches('\n').count() as u32 + 1;
+        let column = SOURCE[line_start..start].chars().count() + 1;
+        Span {
+            file: 0,
+            start,
+            end: start + needle.len(),
+            line,
+            column,
+            end_line: line,
+            end_column: column + needle.chars().count(),
+        }
+    }
+
+    fn render(format: Format, color: Color, diagnostic: &Diagnostic) -> String {
+        let mut sources = SourceMap::new();
+        sources.add("essai.fr", SOURCE);
+        Emitter::new(format, color).render(diagnostic, &sources)
+    }
+
+    fn diagnostic() -> Diagnostic {
+        Diagnostic::error(Message::new("E0202", &[&"totl"]))
+            .with_label(span_of("totl"), "")
+            .with_secondary(span_of("total"), "déclarée ici")
+            .with_note("une note")
+            .with_help("une aide")
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(
+            render(Format::Text, Color::Never, &diagnostic()),
+            "erreur[E0202]: 'totl' n'est pas déclarée.
+ --> essai.fr:2:8
+  |
+1 | variable total <- 0
+  |          ----- déclarée ici
+2 | ecrire totl + 1
+  |        ^^^^
+  = note: une note
+  = aide: une aide
+
+"
+        );
+    }
+
+    #[test]
+    fn colored_text() {
+        let rendered = render(Format::Text, Color::Always, &diagnostic());
+        assert!(rendered.starts_with("\x1b[1;31merreur[E0202]\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m       ^^^^\x1b[0m"));
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            render(Format::Json, Color::Always, &diagnostic()),
+            concat!(
+                r#"{"severity":"erreur","code":"E0202","message":"'totl' n'est pas déclarée.","#,
+                r#""spans":[{"file":"essai.fr","byte_start":27,"byte_end":31,"line_start":2,"#,
+                r#""column_start":8,"line_end":2,"column_end":12,"primary":true,"label":""},"#,
+                r#"{"file":"essai.fr","byte_start":9,"byte_end":14,"line_start":1,"column_start":10,"#,
+                r#""line_end":1,"column_end":15,"primary":false,"label":"déclarée ici"}],"#,
+                r#""notes":["une note"],"help":["une aide"],"trace":[]}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn repeated_calls_are_summarized() {
+        let frame = |function: &str, arguments: &str, needle: &str| Frame {
+            function: function.to_string(),
+            arguments: arguments.to_string(),
+            call_site: span_of(needle),
+        };
+        let mut trace = vec![frame("f", "1", "totl"); 6];
+        trace.push(frame("g", "0, \"a\"", "total"));
+        let diagnostic = Diagnostic::error(Message::new("E0202", &[&"totl"]))
+            .with_label(span_of("totl"), "")
+            .with_trace(trace);
+        let rendered = render(Format::Text, Color::Never, &diagnostic);
+        assert!(
+            rendered.ends_with(
+                "  = pile d'appels (la plus récente en premier) :
+      f(1) appelée depuis essai.fr:2:8
+      f(1) appelée depuis essai.fr:2:8
+      f(1) appelée depuis essai.fr:2:8
+      ... 3 appels identiques à f
+      g(0, \"a\") appelée depuis essai.fr:1:10
+
+"
+            ),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn short_runs_are_not_summarized() {
+        let trace = vec![
+            Frame {
+                function: "f".to_string(),
+                arguments: String::new(),
+                call_site: span_of("totl"),
+            };
+            REPEATED_FRAMES_SHOWN + 1
+        ];
+        let diagnostic = Diagnostic::error(Message::new("E0202", &[&"totl"])).with_trace(trace);
+        let rendered = render(Format::Text, Color::Never, &diagnostic);
+        assert_eq!(rendered.matches("f() appelée depuis").count(), REPEATED_FRAMES_SHOWN + 1);
+        assert!(!rendered.contains("identiques"));
+    }
+}
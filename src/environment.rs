@@ -1,120 +1,229 @@
-use std::{ cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use crate::{
+    catalog::Message,
     error::RuntimeError,
-    token::{Token, TokenType},
+    gc::{self, Trace},
+    suggest,
+    symbol::Symbol,
+    token::Token,
     value::Value,
 };
-#[derive(Clone, Debug)]
+
+/// Emplacement d'une variable, calculé par le resolver.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Location {
+    /// Variable locale : case `slot` de l'environnement situé `depth` niveaux
+    /// au-dessus de l'environnement courant.
+    Local { depth: usize, slot: usize },
+    /// Case de la table des globales.
+    Global(usize),
+}
+
+/// Variables d'un bloc ou d'un appel de fonction, rangées dans l'ordre de
+/// leur déclaration.
+#[derive(Clone, Debug, Default)]
 pub struct Environment {
-    pub values: HashMap<String, Value>,
-    pub enclosing: Option<Rc<RefCell<Environment>>>,
-    pub id: usize,
+    values: Vec<Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
-    pub fn new() -> Self {
-        Environment {
-            values: HashMap::new(),
-            enclosing: None,
-            id: 0,
+    /// Nouvel environnement, inscrit auprès du ramasse-miettes des cycles.
+    pub fn new_enclosed(enclosing: Option<&Rc<RefCell<Environment>>>) -> Rc<RefCell<Environment>> {
+        let environment = Rc::new(RefCell::new(Environment {
+            values: Vec::new(),
+            enclosing: enclosing.cloned(),
+        }));
+        gc::track_environment(&environment);
+        environment
+    }
+
+    pub fn missing_ancestor(name: &Token) -> RuntimeError {
+        RuntimeError::new(
+            name,
+            Message::new("E0314", &[&name.lexeme]),
+        )
+    }
+
+    /// Une déclaration sautée (dans un `si` sans bloc) laisse des cases
+    /// vides avant `slot`.
+    pub fn define(&mut self, slot: usize, value: Value) {
+        if slot >= self.values.len() {
+            self.values.resize(slot + 1, Value::Nil);
         }
+        self.values[slot] = value;
     }
 
-    pub fn new_enclosed(enclosing: &Rc<RefCell<Environment>>, id: usize) -> Self {
-        Environment {
-            values: HashMap::new(),
-            enclosing: Some(Rc::clone(enclosing)),
-            id: id,
+    /// `None` signale une résolution incohérente avec les environnements
+    /// d'exécution.
+    pub fn get_at(&self, depth: usize, slot: usize) -> Option<Value> {
+        if depth == 0 {
+            self.values.get(slot).cloned()
+        } else {
+            self.enclosing.as_ref()?.borrow().get_at(depth - 1, slot)
         }
     }
 
-    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
-        // Get first ancestor
-        let parent = self
-            .enclosing
-            .clone()
-            .expect(&format!("No enclosing environment at {}", 1));
-        let mut environment = Rc::clone(&parent);
-       // println!("first ancestor: {:?}", environment);
-        // Get next ancestors
-        for i in 1..distance {
-            let parent = environment
-                .borrow_mut()
-                .enclosing
-                .clone()
-                .expect(&format!("No enclosing environment at {}", i));
-            environment = Rc::clone(&parent);
+    pub fn assign_at(&mut self, depth: usize, slot: usize, value: Value) -> Option<()> {
+        if depth == 0 {
+            *self.values.get_mut(slot)? = value;
+            Some(())
+        } else {
+            self.enclosing.as_ref()?.borrow_mut().assign_at(depth - 1, slot, value)
         }
-      //  println!("\n\tancestor: {:?}\n", environment);
-        environment
     }
+}
 
-    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Value) -> Result<(), RuntimeError> {
-        if distance > 0 {
-            let ancestor = self.ancestor(distance);
-            ancestor.borrow_mut().values.insert(name.lexeme.clone(), value);
-        }else{
-           self.values.insert(name.lexeme.clone(), value);
+impl Trace for Environment {
+    fn trace(&self, visit: &mut dyn FnMut(*const ())) {
+        if let Some(enclosing) = &self.enclosing {
+            visit(Rc::as_ptr(enclosing) as *const ());
         }
-        //self.values.insert(name.lexeme.clone(), value);
-        Ok(())
-    }
-
-    pub fn get_at(&self, distance: usize, name: &str) -> Result<Value, RuntimeError> {
-       // println!("values: {:?}", self.values);
-        
-        if distance > 0 {
-            Ok(self
-                .ancestor(distance)
-                .borrow()
-                .values
-                .get(name)
-                .expect(&format!("Undefined variable '{}'", name))
-                .clone())
-        } else {
-            Ok(self
-                .values
-                .get(name)
-                .expect(&format!("Undefined variable '{}'", name))
-                .clone())
+        for value in &self.values {
+            gc::trace_value(value, visit);
         }
     }
+}
+
+/// Table des variables globales. Le resolver attribue un indice à chaque nom
+/// rencontré ; la case reste vide tant que la globale n'est pas définie.
+#[derive(Clone, Debug, Default)]
+pub struct Globals {
+    indices: HashMap<Symbol, usize>,
+    names: Vec<Symbol>,
+    values: Vec<Option<Value>>,
+}
 
-    pub fn define(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
+impl Globals {
+    /// Indice de la globale `name`, créé au premier appel.
+    pub fn index(&mut self, name: Symbol) -> usize {
+        if let Some(&index) = self.indices.get(&name) {
+            return index;
+        }
+        self.indices.insert(name, self.names.len());
+        self.names.push(name);
+        self.values.push(None);
+        self.names.len() - 1
     }
-    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
-    
-        let key = &*name.lexeme;
 
-        if let Some(value) = self.values.get(key) {
-            Ok((*value).clone())
-        }else{
-            if let Some(enclosing) = &self.enclosing {
-                return enclosing.borrow().get(name);
-            }
-            Err(RuntimeError::Error {
-                token: name.clone(),
-                message: format!("Undefined variable '{}'.", name.lexeme),
-            })
+    pub fn define(&mut self, index: usize, value: Value) {
+        self.values[index] = Some(value);
+    }
+
+    pub fn get(&self, index: usize, name: &Token) -> Result<Value, RuntimeError> {
+        match self.values.get(index) {
+            Some(Some(value)) => Ok(value.clone()),
+            _ => Err(self.undefined(name)),
         }
     }
 
-    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
-        let key = &*name.lexeme;
-        if self.values.contains_key(key) {
-            self.values.insert(name.lexeme.clone(), value);
-            Ok(())
-        } else {
-            if let Some(ref enclosing) = self.enclosing {
-                enclosing.borrow_mut().assign(name, value)
-            } else {
-                Err(RuntimeError::Error {
-                    token: name.clone(),
-                    message: format!("Undefined variable '{}'", key),
-                })
+    pub fn assign(&mut self, index: usize, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        match self.values.get_mut(index) {
+            Some(Some(slot)) => {
+                *slot = value;
+                Ok(())
             }
+            _ => Err(self.undefined(name)),
+        }
+    }
+
+    /// Globales définies, avec leur valeur.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &Value)> {
+        self.names
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(name, value)| Some((*name, value.as_ref()?)))
+    }
+
+    /// Erreur pour une globale lue avant sa définition, en suggérant le nom
+    /// défini le plus proche.
+    fn undefined(&self, name: &Token) -> RuntimeError {
+        let mut message = Message::new("E0313", &[&name.lexeme]);
+        let defined: Vec<_> = self.iter().map(|(name, _)| name.name()).collect();
+        if let Some(help) = suggest::help(&name.lexeme, defined.iter().map(|name| &**name)) {
+            message.text = format!("{} ; {}", message.text, help);
+        }
+        RuntimeError::new(name, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{span::Span, token::TokenType};
+
+    fn name(text: &str) -> Token {
+        Token::new(TokenType::IDENTIFIER, text.to_string(), text.to_string(), 1, Span::default())
+    }
+
+    fn number(value: &Option<Value>) -> f64 {
+        match value {
+            Some(Value::Number(number)) => *number,
+            other => panic!("nombre attendu, trouvé {:?}", other),
         }
     }
+
+    #[test]
+    fn slots_are_found_by_depth() {
+        let outer = Environment::new_enclosed(None);
+        outer.borrow_mut().define(0, Value::Number(1.0));
+        outer.borrow_mut().define(1, Value::Number(2.0));
+        let inner = Environment::new_enclosed(Some(&outer));
+        inner.borrow_mut().define(0, Value::Number(3.0));
+
+        let inner = inner.borrow();
+        assert_eq!(number(&inner.get_at(0, 0)), 3.0);
+        assert_eq!(number(&inner.get_at(1, 1)), 2.0);
+        assert!(inner.get_at(0, 1).is_none());
+        assert!(inner.get_at(2, 0).is_none());
+    }
+
+    #[test]
+    fn assignment_reaches_the_enclosing_environment() {
+        let outer = Environment::new_enclosed(None);
+        outer.borrow_mut().define(0, Value::Number(1.0));
+        let inner = Environment::new_enclosed(Some(&outer));
+        assert_eq!(inner.borrow_mut().assign_at(1, 0, Value::Number(5.0)), Some(()));
+        assert_eq!(number(&outer.borrow().get_at(0, 0)), 5.0);
+        // Une case jamais déclarée ne peut pas être affectée.
+        assert_eq!(inner.borrow_mut().assign_at(0, 0, Value::Number(5.0)), None);
+    }
+
+    #[test]
+    fn skipped_declarations_leave_empty_slots() {
+        let environment = Environment::new_enclosed(None);
+        environment.borrow_mut().define(2, Value::Number(1.0));
+        assert!(matches!(environment.borrow().get_at(0, 0), Some(Value::Nil)));
+        assert_eq!(number(&environment.borrow().get_at(0, 2)), 1.0);
+    }
+
+    #[test]
+    fn globals_keep_their_index() {
+        let mut globals = Globals::default();
+        let total = globals.index(Symbol::intern("total"));
+        let compte = globals.index(Symbol::intern("compte"));
+        assert_ne!(total, compte);
+        assert_eq!(globals.index(Symbol::intern("total")), total);
+
+        globals.define(total, Value::Number(1.0));
+        globals.assign(total, &name("total"), Value::Number(2.0)).unwrap();
+        assert_eq!(number(&globals.get(total, &name("total")).ok()), 2.0);
+        let defined: Vec<_> = globals.iter().map(|(name, _)| name.to_string()).collect();
+        assert_eq!(defined, ["total"]);
+    }
+
+    #[test]
+    fn undefined_globals_are_reported() {
+        let mut globals = Globals::default();
+        let total = globals.index(Symbol::intern("total"));
+        let totl = globals.index(Symbol::intern("totl"));
+        globals.define(total, Value::Number(1.0));
+
+        let error = globals.get(totl, &name("totl")).unwrap_err().to_diagnostic();
+        assert_eq!(error.code.as_deref(), Some("E0313"));
+        assert!(error.message.ends_with("vouliez-vous dire 'total' ?"), "{}", error.message);
+        let error = globals.assign(totl, &name("totl"), Value::Nil).unwrap_err().to_diagnostic();
+        assert_eq!(error.code.as_deref(), Some("E0313"));
+    }
 }
@@ -1,28 +1,120 @@
 use core::fmt;
 use std::error::Error;
 
-use crate::{token::Token, value::Value};
+use crate::{catalog::{self, Message}, diagnostic::{Diagnostic, Frame}, hint::Hint, span::Span, token::{Token, TokenType}, value::{Function, Value}};
 
 
+#[derive(Debug)]
+pub struct LexerError {
+    pub span: Span,
+    pub message: Message,
+}
+
+impl LexerError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.message.clone()).with_label(self.span, "")
+    }
+}
+
+impl Error for LexerError {}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.span, self.message)
+    }
+}
+
 #[derive(Debug)]
 pub struct ParserError{
-    pub token: Token,
-    pub message: String,
+    pub token: Box<Token>,
+    pub message: Message,
+    pub help: Option<String>,
+}
+
+impl ParserError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let label = match self.token.token_type {
+            TokenType::EOF => catalog::text("syntaxe.fin-fichier", &[]),
+            TokenType::NEWLINE => catalog::text("syntaxe.fin-ligne", &[]),
+            TokenType::INDENT | TokenType::DEDENT => catalog::text("syntaxe.indentation", &[]),
+            _ => catalog::text("syntaxe.trouve", &[&self.token.lexeme]),
+        };
+        let diagnostic = Diagnostic::error(self.message.clone()).with_label(self.token.span, label);
+        match &self.help {
+            Some(help) => diagnostic.with_help(help),
+            None => diagnostic,
+        }
+    }
 }
 
 impl Error for ParserError{}
 
 impl fmt::Display for ParserError{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
-        write!(f, "[line {}] [token : {}] Error: {}", self.token.line, self.token, self.message)
+        write!(f, "[line {}] [token : {}] Error: {}", self.token.span, self.token, self.message)
     }
 }
 
 
 #[derive(Debug)]
 pub enum RuntimeError {
-    Error { token: Token, message: String },
+    Error {
+        span: Span,
+        message: Box<Message>,
+        /// Pile d'appels au moment de l'erreur, remplie par l'interpréteur.
+        trace: Vec<Frame>,
+        hint: Option<Box<Hint>>,
+    },
     Return(Value),
+    /// `retourner f(...)` vers une fonction de l'utilisateur : l'appel est
+    /// exécuté par `Function::call`, à la place de l'appel en cours.
+    TailCall {
+        function: Function,
+        arguments: Vec<Value>,
+        call_site: Span,
+    },
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: Message) -> Self {
+        RuntimeError::at(token.span, message)
+    }
+
+    /// Erreur pointant une expression précise plutôt qu'un token.
+    pub fn at(span: Span, message: Message) -> Self {
+        RuntimeError::Error {
+            span,
+            message: Box::new(message),
+            trace: Vec::new(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: Hint) -> Self {
+        if let RuntimeError::Error { hint: slot, .. } = &mut self {
+            *slot = Some(Box::new(hint));
+        }
+        self
+    }
+}
+
+impl RuntimeError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            RuntimeError::Error { span, message, trace, hint, .. } => {
+                let diagnostic = Diagnostic::error((**message).clone())
+                    .with_label(*span, "")
+                    .with_trace(trace.clone());
+                match hint {
+                    Some(hint) => hint.attach(diagnostic),
+                    None => diagnostic,
+                }
+            }
+            RuntimeError::Return(_) | RuntimeError::TailCall { .. } => {
+                Diagnostic::error(Message::new("E0317", &[]))
+            }
+        }
+    }
 }
 
 impl Error for RuntimeError {}
@@ -30,12 +122,15 @@ impl Error for RuntimeError {}
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            RuntimeError::Error { token, message } => {
-                write!(f, "[line {}] Error: {}", token.line, message)
+            RuntimeError::Error { span, message, .. } => {
+                write!(f, "[line {}] Error: {}", span, message)
             }
             RuntimeError::Return(value) => {
                 write!(f, "Return value: {}", value)
             }
+            RuntimeError::TailCall { function, .. } => {
+                write!(f, "Tail call: {:?}", function)
+            }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,1776 @@
+use crate::catalog::Language;
+
+/// Explications longues affichées par `frenchy expliquer <code>`, en
+/// français puis en anglais.
+const EXPLANATIONS: &[(&str, &str, &str)] = &[
+    (
+        "E0001",
+        "Le programme contient un caractère qui ne fait partie d'aucun élément du
+langage : ni opérateur, ni nom, ni nombre, ni texte.
+
+Exemple fautif :
+
+    variable prix <- 10 €
+
+Le symbole `€` n'est pas reconnu. Retirez-le, ou placez-le dans un texte :
+
+    variable prix <- 10
+    ecrire \"10 €\"",
+        "The program contains a character that is not part of any element of the
+language: not an operator, a name, a number or a string.
+
+Erroneous example:
+
+    variable prix <- 10 €
+
+The `€` symbol is not recognized. Remove it, or put it inside a string:
+
+    variable prix <- 10
+    ecrire \"10 €\"",
+    ),
+    (
+        "E0002",
+        "Un texte commence par `\"` mais le guillemet fermant est absent. Seule la
+première ligne du texte est signalée ; la suite du fichier est analysée
+normalement.
+
+Exemple fautif :
+
+    ecrire \"bonjour
+
+Ajoutez le guillemet fermant :
+
+    ecrire \"bonjour\"",
+        "A string starts with `\"` but the closing quote is missing. Only the first
+line of the string is reported; the rest of the file is analyzed normally.
+
+Erroneous example:
+
+    ecrire \"bonjour
+
+Add the closing quote:
+
+    ecrire \"bonjour\"",
+    ),
+    (
+        "E0003",
+        "En mode indentation (fichiers .fri), une ligne est indentée à la fois avec
+des tabulations et des espaces. Leur largeur dépend de l'éditeur : frenchy
+refuse de deviner.
+
+Indentez tout le fichier avec un seul type de caractère, de préférence des
+espaces.",
+        "In indentation mode (.fri files), a line is indented with both tabs and
+spaces. Their width depends on the editor, so frenchy refuses to guess.
+
+Indent the whole file with a single kind of character, preferably spaces.",
+    ),
+    (
+        "E0004",
+        "En mode indentation, une ligne revient à une indentation qui ne correspond
+à aucun bloc ouvert plus haut.
+
+Exemple fautif :
+
+    si x > 0 alors
+            ecrire x
+        ecrire 0
+
+La dernière ligne n'est alignée ni sur le bloc `si`, ni sur son contenu.
+Alignez-la sur l'un des deux.",
+        "In indentation mode, a line returns to an indentation level that does not
+match any block opened above it.
+
+Erroneous example:
+
+    si x > 0 alors
+            ecrire x
+        ecrire 0
+
+The last line is aligned neither with the `si` block nor with its content.
+Align it with one of them.",
+    ),
+    (
+        "E0005",
+        "En mode indentation, une ligne utilise des tabulations là où le bloc qui
+la contient utilise des espaces, ou l'inverse.
+
+Choisissez un seul type d'indentation pour tout le fichier.",
+        "In indentation mode, a line uses tabs where its enclosing block uses
+spaces, or the other way around.
+
+Choose a single kind of indentation for the whole file.",
+    ),
+    (
+        "E0006",
+        "Un nombre est immédiatement suivi de lettres, ce qui ne forme ni un nombre
+ni un nom.
+
+Exemple fautif :
+
+    variable total <- 12abc
+
+Séparez le nombre du nom, ou corrigez le nombre :
+
+    variable total <- 12",
+        "A number is immediately followed by letters, which forms neither a number
+nor a name.
+
+Erroneous example:
+
+    variable total <- 12abc
+
+Separate the number from the name, or fix the number:
+
+    variable total <- 12",
+    ),
+    (
+        "E0007",
+        "Le séparateur `_` sert à grouper les chiffres d'un nombre (`1_000_000`).
+Il doit toujours être placé entre deux chiffres.
+
+Exemple fautif :
+
+    variable n <- 1__000
+
+Correction :
+
+    variable n <- 1_000",
+        "The `_` separator groups the digits of a number (`1_000_000`). It must
+always be placed between two digits.
+
+Erroneous example:
+
+    variable n <- 1__000
+
+Correction:
+
+    variable n <- 1_000",
+    ),
+    (
+        "E0008",
+        "Un nombre se termine par le séparateur `_`, qui doit être suivi d'un
+chiffre.
+
+Exemple fautif :
+
+    variable n <- 1000_
+
+Retirez le `_` final :
+
+    variable n <- 1000",
+        "A number ends with the `_` separator, which must be followed by a digit.
+
+Erroneous example:
+
+    variable n <- 1000_
+
+Remove the trailing `_`:
+
+    variable n <- 1000",
+    ),
+    (
+        "E0009",
+        "Un nombre écrit avec un préfixe de base contient un chiffre qui n'existe
+pas dans cette base : `0b` (binaire) n'accepte que 0 et 1, `0o` (octal) que
+0 à 7.
+
+Exemple fautif :
+
+    variable masque <- 0b102
+
+Correction :
+
+    variable masque <- 0b101",
+        "A number written with a base prefix contains a digit that does not exist
+in that base: `0b` (binary) only accepts 0 and 1, `0o` (octal) only 0 to 7.
+
+Erroneous example:
+
+    variable masque <- 0b102
+
+Correction:
+
+    variable masque <- 0b101",
+    ),
+    (
+        "E0010",
+        "Un préfixe de base (`0x`, `0b`, `0o`) n'est suivi d'aucun chiffre.
+
+Exemple fautif :
+
+    variable couleur <- 0x
+
+Correction :
+
+    variable couleur <- 0xFF",
+        "A base prefix (`0x`, `0b`, `0o`) is not followed by any digit.
+
+Erroneous example:
+
+    variable couleur <- 0x
+
+Correction:
+
+    variable couleur <- 0xFF",
+    ),
+    (
+        "E0011",
+        "Un nombre écrit en hexadécimal, binaire ou octal dépasse la plus grande
+valeur représentable (2^64 - 1).
+
+Utilisez un nombre plus petit, ou écrivez-le en décimal avec un exposant :
+
+    variable grand <- 1.8e19",
+        "A number written in hexadecimal, binary or octal exceeds the largest
+representable value (2^64 - 1).
+
+Use a smaller number, or write it in decimal with an exponent:
+
+    variable grand <- 1.8e19",
+    ),
+    (
+        "E0012",
+        "Un nombre contient un `e` d'exposant qui n'est suivi d'aucun chiffre.
+
+Exemple fautif :
+
+    variable n <- 3e
+
+Correction :
+
+    variable n <- 3e2",
+        "A number contains an exponent `e` that is not followed by any digit.
+
+Erroneous example:
+
+    variable n <- 3e
+
+Correction:
+
+    variable n <- 3e2",
+    ),
+    (
+        "E0101",
+        "Une instruction `retourner` doit se terminer par `;` ou par une fin de
+ligne.
+
+Exemple fautif :
+
+    retourner x y
+
+Correction :
+
+    retourner x",
+        "A `retourner` statement must end with `;` or a newline.
+
+Erroneous example:
+
+    retourner x y
+
+Correction:
+
+    retourner x",
+    ),
+    (
+        "E0102",
+        "Une boucle `for` s'écrit avec ses trois clauses entre parenthèses.
+
+Exemple fautif :
+
+    for variable i <- 0; i < 3; i <- i + 1 { ecrire i }
+
+Correction :
+
+    for (variable i <- 0; i < 3; i <- i + 1) { ecrire i }",
+        "A `for` loop takes its three clauses between parentheses.
+
+Erroneous example:
+
+    for variable i <- 0; i < 3; i <- i + 1 { ecrire i }
+
+Correction:
+
+    for (variable i <- 0; i < 3; i <- i + 1) { ecrire i }",
+    ),
+    (
+        "E0103",
+        "Dans une boucle `for`, la condition doit être suivie de `;`.
+
+Exemple fautif :
+
+    for (variable i <- 0; i < 3 i <- i + 1) { ecrire i }
+
+Correction :
+
+    for (variable i <- 0; i < 3; i <- i + 1) { ecrire i }",
+        "In a `for` loop, the condition must be followed by `;`.
+
+Erroneous example:
+
+    for (variable i <- 0; i < 3 i <- i + 1) { ecrire i }
+
+Correction:
+
+    for (variable i <- 0; i < 3; i <- i + 1) { ecrire i }",
+    ),
+    (
+        "E0104",
+        "Les clauses d'une boucle `for` doivent être refermées par `)`.
+
+Exemple fautif :
+
+    for (variable i <- 0; i < 3; i <- i + 1 { ecrire i }
+
+Correction :
+
+    for (variable i <- 0; i < 3; i <- i + 1) { ecrire i }",
+        "The clauses of a `for` loop must be closed with `)`.
+
+Erroneous example:
+
+    for (variable i <- 0; i < 3; i <- i + 1 { ecrire i }
+
+Correction:
+
+    for (variable i <- 0; i < 3; i <- i + 1) { ecrire i }",
+    ),
+    (
+        "E0105",
+        "Un bloc `si ... alors` doit se terminer par `finsi`, éventuellement après
+une branche `sinon`.
+
+Exemple fautif :
+
+    si x > 0 alors
+        ecrire \"positif\"
+
+Correction :
+
+    si x > 0 alors
+        ecrire \"positif\"
+    finsi",
+        "A `si ... alors` block must end with `finsi`, possibly after a `sinon`
+branch.
+
+Erroneous example:
+
+    si x > 0 alors
+        ecrire \"positif\"
+
+Correction:
+
+    si x > 0 alors
+        ecrire \"positif\"
+    finsi",
+    ),
+    (
+        "E0106",
+        "Un bloc ouvert par `debut` (le corps d'une fonction) doit être refermé par
+`fin`.
+
+Exemple fautif :
+
+    fonction double(x)
+    debut
+        retourner x * 2
+
+Correction :
+
+    fonction double(x)
+    debut
+        retourner x * 2
+    fin",
+        "A block opened with `debut` (a function body) must be closed with `fin`.
+
+Erroneous example:
+
+    fonction double(x)
+    debut
+        retourner x * 2
+
+Correction:
+
+    fonction double(x)
+    debut
+        retourner x * 2
+    fin",
+    ),
+    (
+        "E0107",
+        "En mode indentation, un bloc indenté n'a pas été refermé par un retour à
+l'indentation précédente. Cela arrive surtout quand le fichier se termine au
+milieu d'une expression.
+
+Vérifiez la fin du bloc signalé.",
+        "In indentation mode, an indented block was not closed by returning to the
+previous indentation. This mostly happens when the file ends in the middle
+of an expression.
+
+Check the end of the reported block.",
+    ),
+    (
+        "E0108",
+        "Un bloc ouvert par `{` doit être refermé par `}`.
+
+Exemple fautif :
+
+    tantque n > 0 {
+        n <- n - 1
+
+Correction :
+
+    tantque n > 0 {
+        n <- n - 1
+    }",
+        "A block opened with `{` must be closed with `}`.
+
+Erroneous example:
+
+    tantque n > 0 {
+        n <- n - 1
+
+Correction:
+
+    tantque n > 0 {
+        n <- n - 1
+    }",
+    ),
+    (
+        "E0109",
+        "Le mot-clé `fonction` doit être suivi du nom de la fonction.
+
+Exemple fautif :
+
+    fonction (x) debut retourner x fin
+
+Correction :
+
+    fonction identite(x) debut retourner x fin",
+        "The `fonction` keyword must be followed by the function's name.
+
+Erroneous example:
+
+    fonction (x) debut retourner x fin
+
+Correction:
+
+    fonction identite(x) debut retourner x fin",
+    ),
+    (
+        "E0110",
+        "Le nom d'une fonction doit être suivi de la liste de ses paramètres entre
+parenthèses, même si elle est vide.
+
+Exemple fautif :
+
+    fonction bonjour debut ecrire \"bonjour\" fin
+
+Correction :
+
+    fonction bonjour() debut ecrire \"bonjour\" fin",
+        "A function's name must be followed by its parameter list between
+parentheses, even when it is empty.
+
+Erroneous example:
+
+    fonction bonjour debut ecrire \"bonjour\" fin
+
+Correction:
+
+    fonction bonjour() debut ecrire \"bonjour\" fin",
+    ),
+    (
+        "E0111",
+        "Une fonction est limitée à 255 paramètres. Au-delà, regroupez les valeurs
+ou découpez la fonction.",
+        "A function is limited to 255 parameters. Beyond that, group the values or
+split the function.",
+    ),
+    (
+        "E0112",
+        "Les paramètres d'une fonction doivent être des noms, séparés par des
+virgules.
+
+Exemple fautif :
+
+    fonction somme(a, 2) debut retourner a fin
+
+Correction :
+
+    fonction somme(a, b) debut retourner a + b fin",
+        "A function's parameters must be names, separated by commas.
+
+Erroneous example:
+
+    fonction somme(a, 2) debut retourner a fin
+
+Correction:
+
+    fonction somme(a, b) debut retourner a + b fin",
+    ),
+    (
+        "E0113",
+        "La liste des paramètres d'une fonction doit être refermée par `)`.
+
+Exemple fautif :
+
+    fonction somme(a, b debut retourner a + b fin
+
+Correction :
+
+    fonction somme(a, b) debut retourner a + b fin",
+        "A function's parameter list must be closed with `)`.
+
+Erroneous example:
+
+    fonction somme(a, b debut retourner a + b fin
+
+Correction:
+
+    fonction somme(a, b) debut retourner a + b fin",
+    ),
+    (
+        "E0114",
+        "Le corps d'une fonction commence par `debut` et se termine par `fin` (ou,
+en mode indentation, par un bloc indenté).
+
+Exemple fautif :
+
+    fonction double(x)
+        retourner x * 2
+    fin
+
+Correction :
+
+    fonction double(x)
+    debut
+        retourner x * 2
+    fin",
+        "A function body starts with `debut` and ends with `fin` (or, in
+indentation mode, is an indented block).
+
+Erroneous example:
+
+    fonction double(x)
+        retourner x * 2
+    fin
+
+Correction:
+
+    fonction double(x)
+    debut
+        retourner x * 2
+    fin",
+    ),
+    (
+        "E0115",
+        "Le mot-clé `variable` doit être suivi du nom de la variable.
+
+Exemple fautif :
+
+    variable <- 3
+
+Correction :
+
+    variable n <- 3",
+        "The `variable` keyword must be followed by the variable's name.
+
+Erroneous example:
+
+    variable <- 3
+
+Correction:
+
+    variable n <- 3",
+    ),
+    (
+        "E0116",
+        "Une déclaration de variable doit se terminer par `;` ou par une fin de
+ligne. La valeur initiale se donne avec `<-`.
+
+Exemple fautif :
+
+    variable x = 5
+
+Correction :
+
+    variable x <- 5",
+        "A variable declaration must end with `;` or a newline. The initial value
+is given with `<-`.
+
+Erroneous example:
+
+    variable x = 5
+
+Correction:
+
+    variable x <- 5",
+    ),
+    (
+        "E0117",
+        "L'instruction `ecrire` prend une seule expression, terminée par `;` ou une
+fin de ligne.
+
+Exemple fautif :
+
+    ecrire \"total\" total
+
+Correction :
+
+    ecrire \"total\"
+    ecrire total",
+        "The `ecrire` statement takes a single expression, ended by `;` or a
+newline.
+
+Erroneous example:
+
+    ecrire \"total\" total
+
+Correction:
+
+    ecrire \"total\"
+    ecrire total",
+    ),
+    (
+        "E0118",
+        "Une expression est suivie d'autre chose que `;` ou une fin de ligne.
+C'est souvent un mot-clé mal orthographié, lu comme un nom, ou une
+affectation écrite avec `=`.
+
+Exemples fautifs :
+
+    ecrir x
+    x = 5
+
+Corrections :
+
+    ecrire x
+    x <- 5",
+        "An expression is followed by something other than `;` or a newline. This
+is often a misspelled keyword, read as a name, or an assignment written
+with `=`.
+
+Erroneous examples:
+
+    ecrir x
+    x = 5
+
+Corrections:
+
+    ecrire x
+    x <- 5",
+    ),
+    (
+        "E0119",
+        "Seule une variable peut recevoir une valeur avec `<-`.
+
+Exemple fautif :
+
+    a + b <- 3
+
+Correction :
+
+    a <- 3 - b",
+        "Only a variable can receive a value with `<-`.
+
+Erroneous example:
+
+    a + b <- 3
+
+Correction:
+
+    a <- 3 - b",
+    ),
+    (
+        "E0120",
+        "Les arguments d'un appel de fonction doivent être refermés par `)`.
+
+Exemple fautif :
+
+    ecrire somme(1, 2
+
+Correction :
+
+    ecrire somme(1, 2)",
+        "The arguments of a function call must be closed with `)`.
+
+Erroneous example:
+
+    ecrire somme(1, 2
+
+Correction:
+
+    ecrire somme(1, 2)",
+    ),
+    (
+        "E0121",
+        "Une parenthèse ouverte dans une expression n'est pas refermée.
+
+Exemple fautif :
+
+    ecrire (1 + 2 * 3
+
+Correction :
+
+    ecrire (1 + 2) * 3",
+        "A parenthesis opened in an expression is not closed.
+
+Erroneous example:
+
+    ecrire (1 + 2 * 3
+
+Correction:
+
+    ecrire (1 + 2) * 3",
+    ),
+    (
+        "E0122",
+        "Une valeur était attendue (nombre, texte, nom, appel, expression entre
+parenthèses) mais le programme contient autre chose, par exemple un
+opérateur sans opérande ou un mot-clé isolé.
+
+Exemple fautif :
+
+    variable x <- * 2
+
+Correction :
+
+    variable x <- 3 * 2",
+        "A value was expected (number, string, name, call, parenthesized
+expression) but the program contains something else, such as an operator
+without an operand or a stray keyword.
+
+Erroneous example:
+
+    variable x <- * 2
+
+Correction:
+
+    variable x <- 3 * 2",
+    ),
+    (
+        "E0123",
+        "Une expression imbrique plus de 2000 niveaux : parenthèses, opérateurs
+enchaînés ou appels. Les phases suivantes parcourent l'expression
+récursivement et épuiseraient la pile. Une telle expression est presque
+toujours produite par un programme.
+
+Exemple fautif :
+
+    variable total <- 1 + 1 + 1 + ... (plus de 2000 termes)
+
+Correction : découpez le calcul en plusieurs instructions.
+
+    variable total <- 1 + 1 + 1 + ... (1000 termes)
+    total <- total + 1 + 1 + ... (1000 termes)",
+        "An expression nests more than 2000 levels: parentheses, chained
+operators or calls. The later phases walk the expression recursively and
+would run out of stack. Such an expression is almost always generated by a
+program.
+
+Erroneous example:
+
+    variable total <- 1 + 1 + 1 + ... (more than 2000 terms)
+
+Correction: split the computation into several statements.
+
+    variable total <- 1 + 1 + 1 + ... (1000 terms)
+    total <- total + 1 + 1 + ... (1000 terms)",
+    ),
+    (
+        "E0201",
+        "Un même nom est déclaré deux fois dans la même portée (le même bloc ou la
+même fonction). La seconde déclaration effacerait la première.
+
+Exemple fautif :
+
+    fonction f() debut
+        variable total <- 0
+        variable total <- 1
+    fin
+
+Renommez l'une des deux variables, ou remplacez la seconde déclaration par
+une affectation :
+
+    total <- 1",
+        "The same name is declared twice in the same scope (the same block or
+function). The second declaration would overwrite the first.
+
+Erroneous example:
+
+    fonction f() debut
+        variable total <- 0
+        variable total <- 1
+    fin
+
+Rename one of the two variables, or turn the second declaration into an
+assignment:
+
+    total <- 1",
+    ),
+    (
+        "E0202",
+        "Le nom utilisé n'est déclaré nulle part où il serait visible : ni dans le
+bloc, ni dans les fonctions englobantes, ni au niveau du programme.
+
+Exemple fautif :
+
+    ecrire compteur
+
+Déclarez la variable avant de l'utiliser :
+
+    variable compteur <- 0
+    ecrire compteur",
+        "The name is not declared anywhere it would be visible: not in the block,
+the enclosing functions, or at the top level of the program.
+
+Erroneous example:
+
+    ecrire compteur
+
+Declare the variable before using it:
+
+    variable compteur <- 0
+    ecrire compteur",
+    ),
+    (
+        "E0203",
+        "Une valeur est affectée avec `<-` à un nom qui n'a jamais été déclaré.
+En frenchy, une variable se déclare d'abord avec `variable`.
+
+Exemple fautif :
+
+    total <- 10
+
+Correction :
+
+    variable total <- 10",
+        "A value is assigned with `<-` to a name that was never declared. In
+frenchy, a variable is first declared with `variable`.
+
+Erroneous example:
+
+    total <- 10
+
+Correction:
+
+    variable total <- 10",
+    ),
+    (
+        "E0204",
+        "Une variable du niveau supérieur est utilisée avant la ligne qui la
+déclare. Le programme s'exécute de haut en bas : la variable n'existe pas
+encore.
+
+Exemple fautif :
+
+    ecrire taux
+    variable taux <- 5
+
+Correction :
+
+    variable taux <- 5
+    ecrire taux",
+        "A top-level variable is used before the line that declares it. The
+program runs from top to bottom, so the variable does not exist yet.
+
+Erroneous example:
+
+    ecrire taux
+    variable taux <- 5
+
+Correction:
+
+    variable taux <- 5
+    ecrire taux",
+    ),
+    (
+        "E0205",
+        "Une fonction connue est appelée avec un nombre d'arguments différent de
+son nombre de paramètres.
+
+Exemple fautif :
+
+    fonction somme(a, b) debut retourner a + b fin
+    ecrire somme(1)
+
+Correction :
+
+    ecrire somme(1, 2)",
+        "A known function is called with a number of arguments different from its
+number of parameters.
+
+Erroneous example:
+
+    fonction somme(a, b) debut retourner a + b fin
+    ecrire somme(1)
+
+Correction:
+
+    ecrire somme(1, 2)",
+    ),
+    (
+        "E0206",
+        "Une variable locale est lue dans sa propre valeur initiale. Elle n'existe
+qu'une fois sa déclaration terminée.
+
+Exemple fautif :
+
+    fonction f() debut
+        variable n <- n + 1
+    fin
+
+Donnez-lui une valeur qui ne dépend pas d'elle-même :
+
+    variable n <- 1",
+        "A local variable is read in its own initial value. It only exists once
+its declaration has finished.
+
+Erroneous example:
+
+    fonction f() debut
+        variable n <- n + 1
+    fin
+
+Give it a value that does not depend on itself:
+
+    variable n <- 1",
+    ),
+    (
+        "E0207",
+        "`retourner` n'a de sens que dans le corps d'une fonction : il indique la
+valeur renvoyée à l'appelant.
+
+Exemple fautif :
+
+    retourner 3
+
+Au niveau du programme, utilisez `ecrire` pour afficher une valeur :
+
+    ecrire 3",
+        "`retourner` only makes sense inside a function body: it gives the value
+returned to the caller.
+
+Erroneous example:
+
+    retourner 3
+
+At the top level of the program, use `ecrire` to display a value:
+
+    ecrire 3",
+    ),
+    (
+        "W0001",
+        "Une variable est déclarée mais jamais lue. C'est souvent un oubli ou une
+faute de frappe dans un nom.
+
+Exemple :
+
+    fonction f() debut
+        variable resultat <- 3
+        retourner 0
+    fin
+
+Supprimez la variable, ou préfixez son nom par `_` (`_resultat`) si elle
+est inutilisée volontairement. Nom court : `inutilise`.",
+        "A variable is declared but never read. This is often an oversight or a
+typo in a name.
+
+Example:
+
+    fonction f() debut
+        variable resultat <- 3
+        retourner 0
+    fin
+
+Remove the variable, or prefix its name with `_` (`_resultat`) if it is
+unused on purpose. Short name: `inutilise`.",
+    ),
+    (
+        "W0002",
+        "Un paramètre de fonction n'est jamais lu dans son corps.
+
+Exemple :
+
+    fonction saluer(nom) debut
+        ecrire \"bonjour\"
+    fin
+
+Utilisez le paramètre, ou renommez-le `_nom` s'il est inutile. Nom court :
+`inutilise`.",
+        "A function parameter is never read in the function body.
+
+Example:
+
+    fonction saluer(nom) debut
+        ecrire \"bonjour\"
+    fin
+
+Use the parameter, or rename it `_nom` if it is not needed. Short name:
+`inutilise`.",
+    ),
+    (
+        "W0003",
+        "Une fonction est déclarée mais n'est appelée nulle part dans le fichier.
+Elle peut être supprimée, ou son nom préfixé par `_`. Cet avertissement
+n'est pas émis dans le REPL. Nom court : `jamais-appelee`.",
+        "A function is declared but never called anywhere in the file. It can be
+removed, or its name prefixed with `_`. This warning is not emitted in the
+REPL. Short name: `jamais-appelee`.",
+    ),
+    (
+        "W0004",
+        "Des instructions suivent un `retourner` dans le même bloc : elles ne seront
+jamais exécutées.
+
+Exemple :
+
+    fonction f() debut
+        retourner 1
+        ecrire \"jamais affiché\"
+    fin
+
+Supprimez-les, ou déplacez le `retourner`. Nom court : `inaccessible`.",
+        "Statements follow a `retourner` in the same block: they will never run.
+
+Example:
+
+    fonction f() debut
+        retourner 1
+        ecrire \"never displayed\"
+    fin
+
+Remove them, or move the `retourner`. Short name: `inaccessible`.",
+    ),
+    (
+        "W0005",
+        "La condition d'un `si` est une valeur fixe toujours vraie : la branche
+`sinon` ne sera jamais exécutée. Une boucle `tantque vrai`, qu'arrête un
+`retourner`, n'est pas signalée.
+
+Exemple :
+
+    si vrai alors ecrire 1 finsi
+
+Nom court : `condition-constante`.",
+        "The condition of a `si` is a fixed value that is always true: the `sinon`
+branch will never run. A `tantque vrai` loop, stopped by a `retourner`, is
+not reported.
+
+Example:
+
+    si vrai alors ecrire 1 finsi
+
+Short name: `condition-constante`.",
+    ),
+    (
+        "W0006",
+        "La condition d'un `si` ou d'un `tantque` est une valeur fixe toujours
+fausse : le bloc ne sera jamais exécuté.
+
+Exemple :
+
+    tantque 0 { ecrire \"jamais\" }
+
+Nom court : `condition-constante`.",
+        "The condition of a `si` or `tantque` is a fixed value that is always
+false: the block will never run.
+
+Example:
+
+    tantque 0 { ecrire \"never\" }
+
+Short name: `condition-constante`.",
+    ),
+    (
+        "W0007",
+        "Une variable locale porte le même nom qu'une variable d'un bloc englobant
+ou du programme. Dans le bloc, l'ancienne variable devient inaccessible,
+ce qui surprend souvent.
+
+Exemple :
+
+    variable total <- 0
+    fonction f() debut
+        variable total <- 5
+        retourner total
+    fin
+
+Choisissez un autre nom pour la variable locale. Nom court : `masquage`.",
+        "A local variable has the same name as a variable of an enclosing block or
+of the program. Inside the block, the old variable becomes unreachable,
+which is often surprising.
+
+Example:
+
+    variable total <- 0
+    fonction f() debut
+        variable total <- 5
+        retourner total
+    fin
+
+Choose another name for the local variable. Short name: `masquage`.",
+    ),
+    (
+        "W0008",
+        "`lire` renvoie toujours un texte, même si l'utilisateur tape des chiffres.
+Comparer ce texte à un nombre avec `==` donne toujours faux (et `!=`
+toujours vrai).
+
+Exemple :
+
+    variable age <- lire(\"âge ?\")
+    si age == 18 alors ecrire \"majeur\" finsi
+
+Convertissez le texte en nombre :
+
+    variable age <- Nombre(lire(\"âge ?\"))
+
+Nom court : `texte-nombre`.",
+        "`lire` always returns text, even when the user types digits. Comparing
+that text to a number with `==` is always false (and `!=` always true).
+
+Example:
+
+    variable age <- lire(\"âge ?\")
+    si age == 18 alors ecrire \"majeur\" finsi
+
+Convert the text to a number:
+
+    variable age <- Nombre(lire(\"âge ?\"))
+
+Short name: `texte-nombre`.",
+    ),
+    (
+        "E0301",
+        "Une fonction est appelée avec un nombre d'arguments différent de son
+nombre de paramètres. L'erreur est détectée à l'exécution quand la fonction
+appelée n'est connue qu'à ce moment-là (par exemple, passée en argument).
+
+Exemple fautif :
+
+    fonction appliquer(f) debut retourner f(1, 2) fin
+    fonction double(x) debut retourner x * 2 fin
+    ecrire appliquer(double)
+
+Appelez la fonction avec le bon nombre d'arguments.",
+        "A function is called with a number of arguments different from its number
+of parameters. The error is detected at run time when the called function
+is only known then (for example, passed as an argument).
+
+Erroneous example:
+
+    fonction appliquer(f) debut retourner f(1, 2) fin
+    fonction double(x) debut retourner x * 2 fin
+    ecrire appliquer(double)
+
+Call the function with the right number of arguments.",
+    ),
+    (
+        "E0302",
+        "Seules les fonctions peuvent être appelées avec `(...)`.
+
+Exemple fautif :
+
+    variable n <- 3
+    ecrire n(2)
+
+Vérifiez que le nom désigne bien une fonction ; pour une multiplication,
+écrivez `n * 2`.",
+        "Only functions can be called with `(...)`.
+
+Erroneous example:
+
+    variable n <- 3
+    ecrire n(2)
+
+Check that the name really refers to a function; for a multiplication,
+write `n * 2`.",
+    ),
+    (
+        "E0303",
+        "L'interpréteur a rencontré un opérateur qu'il ne sait pas évaluer. Cette
+erreur ne devrait pas apparaître avec un programme accepté par l'analyse ;
+merci de la signaler avec le programme qui la provoque.",
+        "The interpreter met an operator it cannot evaluate. This error should not
+appear with a program accepted by the analysis; please report it with the
+program that triggers it.",
+    ),
+    (
+        "E0304",
+        "Un littéral numérique n'a pas pu être converti en nombre. Cette erreur ne
+devrait pas apparaître avec un programme accepté par l'analyse lexicale ;
+merci de la signaler.",
+        "A number literal could not be converted to a number. This error should not
+appear with a program accepted by the lexer; please report it.",
+    ),
+    (
+        "E0305",
+        "Les opérateurs `-` (négation) et `!` ne s'appliquent qu'aux nombres et aux
+booléens.
+
+Exemple fautif :
+
+    ecrire -\"trois\"
+
+Correction :
+
+    ecrire -3",
+        "The `-` (negation) and `!` operators only apply to numbers and booleans.
+
+Erroneous example:
+
+    ecrire -\"trois\"
+
+Correction:
+
+    ecrire -3",
+    ),
+    (
+        "E0306",
+        "`+` additionne deux nombres ou colle deux textes, mais ne mélange pas les
+deux.
+
+Exemple fautif :
+
+    variable n <- lire(\"nombre ?\")
+    ecrire n + 1
+
+`lire` renvoie un texte : convertissez-le avec `Nombre` :
+
+    variable n <- Nombre(lire(\"nombre ?\"))
+    ecrire n + 1",
+        "`+` adds two numbers or joins two strings, but does not mix them.
+
+Erroneous example:
+
+    variable n <- lire(\"nombre ?\")
+    ecrire n + 1
+
+`lire` returns text: convert it with `Nombre`:
+
+    variable n <- Nombre(lire(\"nombre ?\"))
+    ecrire n + 1",
+    ),
+    (
+        "E0307",
+        "`-` ne s'applique qu'à deux nombres.
+
+Exemple fautif :
+
+    ecrire \"10\" - 1
+
+Correction :
+
+    ecrire Nombre(\"10\") - 1",
+        "`-` only applies to two numbers.
+
+Erroneous example:
+
+    ecrire \"10\" - 1
+
+Correction:
+
+    ecrire Nombre(\"10\") - 1",
+    ),
+    (
+        "E0308",
+        "`*` ne s'applique qu'à deux nombres ; il ne répète pas un texte.
+
+Exemple fautif :
+
+    ecrire \"ab\" * 3
+
+Pour un calcul, convertissez d'abord les textes avec `Nombre`.",
+        "`*` only applies to two numbers; it does not repeat a string.
+
+Erroneous example:
+
+    ecrire \"ab\" * 3
+
+For a computation, first convert the strings with `Nombre`.",
+    ),
+    (
+        "E0309",
+        "`/` ne s'applique qu'à deux nombres.
+
+Exemple fautif :
+
+    ecrire \"10\" / 2
+
+Correction :
+
+    ecrire Nombre(\"10\") / 2",
+        "`/` only applies to two numbers.
+
+Erroneous example:
+
+    ecrire \"10\" / 2
+
+Correction:
+
+    ecrire Nombre(\"10\") / 2",
+    ),
+    (
+        "E0310",
+        "`%` (reste de la division) ne s'applique qu'à deux nombres.
+
+Exemple fautif :
+
+    ecrire \"10\" % 3
+
+Correction :
+
+    ecrire Nombre(\"10\") % 3",
+        "`%` (remainder) only applies to two numbers.
+
+Erroneous example:
+
+    ecrire \"10\" % 3
+
+Correction:
+
+    ecrire Nombre(\"10\") % 3",
+    ),
+    (
+        "E0311",
+        "Un nombre est divisé par zéro, ce qui n'a pas de résultat.
+
+Exemple fautif :
+
+    variable parts <- 0
+    ecrire 10 / parts
+
+Vérifiez le diviseur avant de diviser :
+
+    si parts != 0 alors ecrire 10 / parts finsi",
+        "A number is divided by zero, which has no result.
+
+Erroneous example:
+
+    variable parts <- 0
+    ecrire 10 / parts
+
+Check the divisor before dividing:
+
+    si parts != 0 alors ecrire 10 / parts finsi",
+    ),
+    (
+        "E0312",
+        "Le reste d'une division par zéro (`x % 0`) n'existe pas.
+
+Vérifiez le diviseur avant le calcul :
+
+    si n != 0 alors ecrire x % n finsi",
+        "The remainder of a division by zero (`x % 0`) does not exist.
+
+Check the divisor before computing:
+
+    si n != 0 alors ecrire x % n finsi",
+    ),
+    (
+        "E0313",
+        "Un nom n'a pas de valeur au moment où il est lu. L'analyse statique
+détecte la plupart de ces cas (E0202) ; à l'exécution, cela arrive surtout
+dans le REPL, quand une saisie précédente a échoué avant de déclarer la
+variable.
+
+Déclarez la variable avec `variable` avant de l'utiliser.",
+        "A name has no value at the moment it is read. Static analysis catches
+most of these cases (E0202); at run time, this mostly happens in the REPL,
+when an earlier input failed before declaring the variable.
+
+Declare the variable with `variable` before using it.",
+    ),
+    (
+        "E0314",
+        "L'interpréteur n'a pas retrouvé la portée où une variable locale a été
+résolue. Cette erreur interne ne devrait pas apparaître ; merci de la
+signaler avec le programme qui la provoque.",
+        "The interpreter could not find the scope in which a local variable was
+resolved. This internal error should not appear; please report it with the
+program that triggers it.",
+    ),
+    (
+        "E0315",
+        "`lire` n'a pas pu lire l'entrée standard, par exemple parce qu'elle n'est
+pas du texte valide en UTF-8.",
+        "`lire` could not read standard input, for example because it is not valid
+UTF-8 text.",
+    ),
+    (
+        "E0316",
+        "`Nombre` convertit un texte en nombre, mais le texte donné ne représente
+pas un nombre.
+
+Exemple fautif :
+
+    ecrire Nombre(\"douze\")
+
+Correction :
+
+    ecrire Nombre(\"12\")",
+        "`Nombre` converts text to a number, but the given text does not represent
+a number.
+
+Erroneous example:
+
+    ecrire Nombre(\"douze\")
+
+Correction:
+
+    ecrire Nombre(\"12\")",
+    ),
+    (
+        "E0317",
+        "Un `retourner` a été exécuté hors de toute fonction. L'analyse statique
+refuse normalement ce programme (E0207) ; cette erreur ne devrait pas
+apparaître.",
+        "A `retourner` ran outside of any function. Static analysis normally
+rejects such a program (E0207); this error should not appear.",
+    ),
+    (
+        "E0318",
+        "Trop d'appels de fonction sont imbriqués : le plus souvent, une fonction
+récursive n'atteint jamais son cas de base. La limite est de 10000 appels ;
+l'option `--profondeur-max=<n>` la modifie.
+
+Exemple fautif :
+
+    fonction somme(n) debut
+        retourner n + somme(n - 1)
+    fin
+    ecrire somme(5)
+
+Correction :
+
+    fonction somme(n) debut
+        si n <= 0 alors
+            retourner 0
+        finsi
+        retourner n + somme(n - 1)
+    fin
+
+Un appel placé directement après `retourner`, comme `retourner f(n - 1)`,
+remplace l'appel en cours et ne compte pas dans la profondeur.",
+        "Too many function calls are nested: most often, a recursive function
+never reaches its base case. The limit is 10000 calls; the
+`--profondeur-max=<n>` option changes it.
+
+Erroneous example:
+
+    fonction somme(n) debut
+        retourner n + somme(n - 1)
+    fin
+    ecrire somme(5)
+
+Correction:
+
+    fonction somme(n) debut
+        si n <= 0 alors
+            retourner 0
+        finsi
+        retourner n + somme(n - 1)
+    fin
+
+A call placed directly after `retourner`, such as `retourner f(n - 1)`,
+replaces the current call and does not count towards the depth.",
+    ),
+    (
+        "E0319",
+        "L'exécution a dépassé le nombre d'étapes fixé par `--etapes-max=<n>`.
+Une étape est une instruction ou une expression évaluée par l'interpréteur
+d'arbre, une instruction du bytecode avec `--moteur=vm` : le même programme
+ne consomme donc pas le même nombre d'étapes selon le moteur. frenchy se
+termine alors avec le statut 2.
+
+Le plus souvent, une boucle ne s'arrête jamais :
+
+    variable i <- 0
+    tantque i < 10 {
+        ecrire i
+    }
+
+Correction :
+
+    variable i <- 0
+    tantque i < 10 {
+        ecrire i
+        i <- i + 1
+    }",
+        "The run exceeded the number of steps set by `--etapes-max=<n>`. A step
+is a statement or an expression evaluated by the tree interpreter, or a
+bytecode instruction with `--moteur=vm`: the same program therefore uses a
+different number of steps depending on the engine. frenchy then exits with
+status 2.
+
+Most often, a loop never stops:
+
+    variable i <- 0
+    tantque i < 10 {
+        ecrire i
+    }
+
+Correction:
+
+    variable i <- 0
+    tantque i < 10 {
+        ecrire i
+        i <- i + 1
+    }",
+    ),
+    (
+        "E0320",
+        "L'exécution a duré plus longtemps que `--temps-max=<secondes>`. Le temps
+compté est celui de l'horloge, attente des saisies comprise. frenchy se
+termine alors avec le statut 3.
+
+Cherchez une boucle qui ne se termine pas, ou un calcul trop coûteux, comme
+une récursion qui recalcule plusieurs fois les mêmes valeurs.",
+        "The run lasted longer than `--temps-max=<seconds>`. The time counted is
+wall-clock time, including waiting for input. frenchy then exits with
+status 3.
+
+Look for a loop that never ends, or a computation that is too costly, such
+as a recursion that computes the same values several times.",
+    ),
+    (
+        "E0321",
+        "Le programme a alloué plus de mémoire que `--memoire-max=<Mo>` depuis le
+début de l'exécution. La mesure est approximative : elle compte les textes et
+les environnements, mais aussi les structures de l'interpréteur. frenchy se
+termine alors avec le statut 4.
+
+Le plus souvent, un texte grandit sans fin dans une boucle :
+
+    variable texte <- \"a\"
+    tantque vrai {
+        texte <- texte + texte
+    }",
+        "The program allocated more memory than `--memoire-max=<MB>` since the
+start of the run. The measure is approximate: it counts strings and
+environments, but also the interpreter's own structures. frenchy then exits
+with status 4.
+
+Most often, a string grows endlessly in a loop:
+
+    variable texte <- \"a\"
+    tantque vrai {
+        texte <- texte + texte
+    }",
+    ),
+    (
+        "E0322",
+        "L'interpréteur d'arbre a épuisé la pile du système avant d'atteindre
+`--profondeur-max` : chaque appel imbrique beaucoup de blocs ou
+d'expressions, et consomme donc plus de pile que prévu. Le message indique
+le nombre d'appels en cours.
+
+Réduisez la récursion, par exemple en plaçant l'appel récursif directement
+après `retourner`, ou exécutez le programme avec `--moteur=vm`, dont la
+pile ne dépend pas de l'imbrication.",
+        "The tree interpreter ran out of system stack before reaching
+`--profondeur-max`: each call nests many blocks or expressions, and so uses
+more stack than planned. The message gives the number of calls in progress.
+
+Reduce the recursion, for example by placing the recursive call directly
+after `retourner`, or run the program with `--moteur=vm`, whose stack does
+not depend on nesting.",
+    ),
+    (
+        "E0401",
+        "frenchy n'exécute que les fichiers `.fr` (blocs délimités par des
+mots-clés) et `.fri` (blocs délimités par l'indentation).
+
+Renommez le fichier, par exemple `programme.fr`.",
+        "frenchy only runs `.fr` files (blocks delimited by keywords) and `.fri`
+files (blocks delimited by indentation).
+
+Rename the file, for example `programme.fr`.",
+    ),
+    (
+        "E0402",
+        "Le fichier donné n'a pas pu être lu : il n'existe pas, n'est pas lisible,
+ou n'est pas du texte en UTF-8. Le message du système précise la cause.",
+        "The given file could not be read: it does not exist, is not readable, or
+is not UTF-8 text. The system message gives the cause.",
+    ),
+    (
+        "E0403",
+        "Une option commençant par `--` n'est pas reconnue. Les options
+disponibles sont listées dans l'aide affichée avec l'erreur.",
+        "An option starting with `--` is not recognized. The available options
+are listed in the usage shown with the error.",
+    ),
+    (
+        "E0404",
+        "Plusieurs scripts sont donnés sur la ligne de commande ; frenchy n'en
+exécute qu'un à la fois.",
+        "Several scripts are given on the command line; frenchy runs only one at a
+time.",
+    ),
+    (
+        "E0405",
+        "`frenchy expliquer` a reçu un code qui n'existe pas. Lancez
+`frenchy expliquer` sans argument pour la liste des codes.",
+        "`frenchy expliquer` received a code that does not exist. Run
+`frenchy expliquer` without an argument for the list of codes.",
+    ),
+    (
+        "E0406",
+        "Le fichier `.frc` ne peut pas être relu : il n'a pas été produit par
+`frenchy compiler`, ou il a été tronqué ou modifié depuis. Le détail indique
+la partie fautive.
+
+Recompilez le source avec `frenchy compiler script.fr`.",
+        "The `.frc` file cannot be read back: it was not produced by `frenchy
+compiler`, or it was truncated or modified since. The detail names the faulty
+part.
+
+Recompile the source with `frenchy compiler script.fr`.",
+    ),
+    (
+        "E0407",
+        "Le format des fichiers `.frc` change avec le jeu d'instructions de la
+machine virtuelle. Un fichier compilé par une autre version de frenchy n'est
+pas exécuté.
+
+Recompilez le source avec `frenchy compiler script.fr`.",
+        "The `.frc` format changes with the virtual machine's instruction set. A
+file compiled by another version of frenchy is not run.
+
+Recompile the source with `frenchy compiler script.fr`.",
+    ),
+    (
+        "E0408",
+        "`frenchy compiler` attend un script `.fr` ou `.fri` ;
+`frenchy desassembler` attend un fichier `.frc` ou un script à compiler.
+
+    frenchy compiler prog.fr -o prog.frc
+    frenchy desassembler prog.frc",
+        "`frenchy compiler` expects a `.fr` or `.fri` script;
+`frenchy desassembler` expects a `.frc` file or a script to compile.
+
+    frenchy compiler prog.fr -o prog.frc
+    frenchy desassembler prog.frc",
+    ),
+    (
+        "E0409",
+        "Le fichier compilé n'a pas pu être écrit : dossier inexistant, droits
+insuffisants ou disque plein. Le message du système donne la cause.",
+        "The compiled file could not be written: missing directory, insufficient
+permissions or full disk. The system message gives the cause.",
+    ),
+    (
+        "E0410",
+        "L'option `-o` de `frenchy compiler` nomme le fichier produit ; elle doit
+être suivie de ce nom. Sans `-o`, le fichier porte le nom du script avec
+l'extension `.frc`.",
+        "The `-o` option of `frenchy compiler` names the output file; it must be
+followed by that name. Without `-o`, the file takes the script's name with the
+`.frc` extension.",
+    ),
+    (
+        "E0411",
+        "`--profondeur-max=<n>` fixe le nombre maximal d'appels de fonction
+imbriqués ; `<n>` doit être un entier strictement positif.
+
+    frenchy --profondeur-max=50000 prog.fr",
+        "`--profondeur-max=<n>` sets the maximum number of nested function
+calls; `<n>` must be a strictly positive integer.
+
+    frenchy --profondeur-max=50000 prog.fr",
+    ),
+    (
+        "E0412",
+        "frenchy réserve une pile proportionnelle à `--profondeur-max` avant
+d'exécuter le programme, et le système a refusé cette réservation. Choisissez
+une profondeur plus petite.",
+        "frenchy reserves a stack proportional to `--profondeur-max` before
+running the program, and the system refused that reservation. Choose a
+smaller depth.",
+    ),
+    (
+        "E0413",
+        "Les limites d'exécution attendent une valeur strictement positive :
+
+    --etapes-max=<n>         nombre entier d'étapes
+    --temps-max=<secondes>   durée, décimales acceptées : --temps-max=0.5
+    --memoire-max=<Mo>       nombre entier de mégaoctets",
+        "Execution limits expect a strictly positive value:
+
+    --etapes-max=<n>         whole number of steps
+    --temps-max=<seconds>    duration, decimals allowed: --temps-max=0.5
+    --memoire-max=<MB>       whole number of megabytes",
+    ),
+    (
+        "E0501",
+        "Avec `--moteur=vm`, les nombres, les textes et les noms de globales
+d'une fonction sont rangés dans une table de constantes limitée à 16777216
+entrées. Seul un programme généré automatiquement peut l'atteindre.
+
+Découpez le programme en plusieurs fonctions, ou utilisez l'interpréteur
+d'arbre (`--moteur=arbre`), qui n'a pas cette limite.",
+        "With `--moteur=vm`, the numbers, strings and global names of a function
+are stored in a constant table limited to 16777216 entries. Only a
+generated program can reach it.
+
+Split the program into several functions, or use the tree interpreter
+(`--moteur=arbre`), which has no such limit.",
+    ),
+    (
+        "E0502",
+        "Avec `--moteur=vm`, une fonction ne peut pas avoir plus de 256 variables
+locales visibles en même temps, paramètres compris.
+
+Regroupez le travail dans des fonctions plus petites, ou fermez des blocs
+pour libérer leurs variables.",
+        "With `--moteur=vm`, a function cannot have more than 256 local variables
+visible at the same time, parameters included.
+
+Move the work into smaller functions, or close blocks to release their
+variables.",
+    ),
+    (
+        "E0503",
+        "Avec `--moteur=vm`, une fonction ne peut pas utiliser plus de 256
+variables des fonctions qui l'englobent.
+
+Passez ces valeurs en paramètres plutôt que de les capturer.",
+        "With `--moteur=vm`, a function cannot use more than 256 variables from
+its enclosing functions.
+
+Pass these values as parameters instead of capturing them.",
+    ),
+    (
+        "E0504",
+        "Avec `--moteur=vm`, le corps d'un `si` ou d'un `tantque` est limité à
+65535 octets de bytecode, soit plusieurs milliers d'instructions.
+
+Déplacez une partie du corps dans une fonction.",
+        "With `--moteur=vm`, the body of a `si` or `tantque` is limited to 65535
+bytes of bytecode, that is several thousand statements.
+
+Move part of the body into a function.",
+    ),
+    (
+        "E0505",
+        "Avec `--moteur=vm`, un appel de fonction transmet au plus 255 arguments.
+
+Regroupez les valeurs ou découpez la fonction appelée.",
+        "With `--moteur=vm`, a function call passes at most 255 arguments.
+
+Group the values or split the called function.",
+    ),
+];
+
+pub fn explanation(code: &str, language: Language) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(candidate, _, _)| *candidate == code)
+        .map(|(_, fr, en)| match language {
+            Language::French => *fr,
+            Language::English => *en,
+        })
+}
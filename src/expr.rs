@@ -1,13 +1,16 @@
 use core::fmt;
+use std::rc::Rc;
 
+use crate::span::Span;
 use crate::token::Token;
 
 use crate::visitor::ExprVisitor;
 
-pub enum Operator {
-    
-}
+/// Identifiant unique d'un nœud, attribué par le parser. Le resolver range
+/// l'emplacement de chaque variable sous l'identifiant du nœud qui la nomme.
+pub type NodeId = usize;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug,Clone)]
 pub enum Expr{
     BinaryExpr(BinaryExpr),
@@ -21,20 +24,23 @@ pub enum Expr{
 }
 #[derive(Debug,Clone)]
 pub struct AssignVarExpr{
+    pub id: NodeId,
     pub name: Token,
     pub value: Box<Expr>,
+    pub span: Span,
 }
 #[derive(Debug,Clone)]
 pub struct LogicalExpr{
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
+    pub span: Span,
 }
 #[derive(Debug,Clone)]
 pub struct CallExp{
     pub callee: Box<Expr>,
-    pub paren: Token,
     pub arguments: Vec<Expr>,
+    pub span: Span,
 }
 
 
@@ -68,6 +74,19 @@ impl fmt::Display for Expr{
 }
 
 impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::BinaryExpr(expr) => expr.span,
+            Expr::GroupingExpr(expr) => expr.span,
+            Expr::LiteralExpr(expr) => expr.span,
+            Expr::UnaryExpr(expr) => expr.span,
+            Expr::VariableExpr(expr) => expr.span,
+            Expr::AssignVarExpr(expr) => expr.span,
+            Expr::LogicalExpr(expr) => expr.span,
+            Expr::CallExpr(expr) => expr.span,
+        }
+    }
+
     pub fn accept<V: ExprVisitor>(&self, visitor: &mut V) -> V::Output {
         match self {
             Expr::BinaryExpr(ref expr) => expr.accept(visitor),
@@ -83,7 +102,9 @@ impl Expr {
 }
 #[derive(Debug,Clone)]
 pub struct VariableExpr{
+    pub id: NodeId,
     pub name: Token,
+    pub span: Span,
 }
 
 impl VariableExpr{
@@ -108,6 +129,7 @@ impl CallExp{
 #[derive(Debug,Clone)]
 pub struct GroupingExpr{
     pub expression: Box<Expr>,
+    pub span: Span,
 }
 
 impl GroupingExpr{
@@ -118,11 +140,14 @@ impl GroupingExpr{
 #[derive(Debug,Clone)]
 pub struct LiteralExpr{
     pub value: Literal,
+    pub span: Span,
 }
 #[derive(Debug,Clone)]
 pub enum Literal {
     Number(Token),
-    String(Token),
+    /// Le texte est préparé par le parser pour ne pas être recopié à chaque
+    /// évaluation.
+    String(Token, Rc<str>),
     Boolean(Token),
     Nil,
 }
@@ -136,6 +161,7 @@ impl LiteralExpr{
 pub struct UnaryExpr{
     pub operator: Token,
     pub right: Box<Expr>,
+    pub span: Span,
 }
 
 impl UnaryExpr{
@@ -149,6 +175,7 @@ pub struct BinaryExpr{
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
+    pub span: Span,
 }
 
 impl BinaryExpr{
@@ -173,7 +200,7 @@ impl fmt::Display for LiteralExpr{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.value {
             Literal::Number(token) => write!(f, "{}", token.lexeme),
-            Literal::String(token) => write!(f, "{}", token.lexeme),
+            Literal::String(token, _) => write!(f, "{}", token.lexeme),
             Literal::Boolean(token) => write!(f, "{}", token.lexeme),
             Literal::Nil => write!(f, "nil"),
         }
@@ -206,9 +233,9 @@ impl fmt::Display for LogicalExpr{
 
 impl fmt::Display for CallExp{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}(", self.callee);
+        write!(f, "({}(", self.callee)?;
         for arg in &self.arguments {
-            write!(f, "{},", arg);
+            write!(f, "{},", arg)?;
         }
         write!(f, "))")
     }
@@ -0,0 +1,296 @@
+//! Ramasse-miettes des cycles. Les environnements, fermetures et variables
+//! capturées sont libérés par comptage de références, sauf quand ils se
+//! désignent mutuellement : une fonction locale est rangée dans
+//! l'environnement qu'elle capture. Chaque objet de ce type est inscrit ici
+//! à sa création ; une collecte retrouve les cycles que plus rien ne désigne
+//! de l'extérieur, et les casse.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    catalog,
+    environment::Environment,
+    value::Value,
+    vm::{Closure, Upvalue},
+};
+
+/// Objet inscrit, sans le maintenir en vie.
+enum Tracked {
+    Environment(Weak<RefCell<Environment>>),
+    Closure(Weak<Closure>),
+    Upvalue(Weak<RefCell<Upvalue>>),
+}
+
+/// Objet inscrit et encore vivant, tenu le temps d'une collecte.
+enum Object {
+    Environment(Rc<RefCell<Environment>>),
+    Closure(Rc<Closure>),
+    Upvalue(Rc<RefCell<Upvalue>>),
+}
+
+/// Objets désignés directement par un objet inscrit, identifiés par leur
+/// adresse.
+pub trait Trace {
+    fn trace(&self, visit: &mut dyn FnMut(*const ()));
+}
+
+/// Adresse de l'objet inscrit désigné par `value`, s'il y en a un.
+pub fn trace_value(value: &Value, visit: &mut dyn FnMut(*const ())) {
+    match value {
+        Value::Function(function) => {
+            if let Some(closure) = &function.closure {
+                visit(Rc::as_ptr(closure) as *const ());
+            }
+        }
+        Value::Closure(closure) => visit(Rc::as_ptr(closure) as *const ()),
+        _ => {}
+    }
+}
+
+/// En dessous de ce nombre d'objets inscrits, aucune collecte automatique.
+const MIN_THRESHOLD: usize = 10_000;
+
+struct Heap {
+    objects: Vec<Tracked>,
+    /// Nombre d'objets inscrits qui déclenche la prochaine collecte.
+    threshold: usize,
+    collections: usize,
+    freed: usize,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = const {
+        RefCell::new(Heap {
+            objects: Vec::new(),
+            threshold: MIN_THRESHOLD,
+            collections: 0,
+            freed: 0,
+        })
+    };
+}
+
+fn track(object: Tracked) {
+    let full = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.objects.push(object);
+        heap.objects.len() >= heap.threshold
+    });
+    if full {
+        collect();
+    }
+}
+
+pub fn track_environment(environment: &Rc<RefCell<Environment>>) {
+    track(Tracked::Environment(Rc::downgrade(environment)));
+}
+
+pub fn track_closure(closure: &Rc<Closure>) {
+    track(Tracked::Closure(Rc::downgrade(closure)));
+}
+
+pub fn track_upvalue(upvalue: &Rc<RefCell<Upvalue>>) {
+    track(Tracked::Upvalue(Rc::downgrade(upvalue)));
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Object> {
+        match self {
+            Tracked::Environment(weak) => weak.upgrade().map(Object::Environment),
+            Tracked::Closure(weak) => weak.upgrade().map(Object::Closure),
+            Tracked::Upvalue(weak) => weak.upgrade().map(Object::Upvalue),
+        }
+    }
+}
+
+impl Object {
+    fn address(&self) -> *const () {
+        match self {
+            Object::Environment(rc) => Rc::as_ptr(rc) as *const (),
+            Object::Closure(rc) => Rc::as_ptr(rc) as *const (),
+            Object::Upvalue(rc) => Rc::as_ptr(rc) as *const (),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Object::Environment(rc) => Rc::strong_count(rc),
+            Object::Closure(rc) => Rc::strong_count(rc),
+            Object::Upvalue(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    /// `false` si l'objet est en cours de modification : il est alors
+    /// considéré comme vivant.
+    fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+        match self {
+            Object::Environment(rc) => rc.try_borrow().map(|e| e.trace(visit)).is_ok(),
+            Object::Closure(rc) => {
+                rc.trace(visit);
+                true
+            }
+            Object::Upvalue(rc) => rc.try_borrow().map(|u| u.trace(visit)).is_ok(),
+        }
+    }
+
+    fn downgrade(&self) -> Tracked {
+        match self {
+            Object::Environment(rc) => Tracked::Environment(Rc::downgrade(rc)),
+            Object::Closure(rc) => Tracked::Closure(Rc::downgrade(rc)),
+            Object::Upvalue(rc) => Tracked::Upvalue(Rc::downgrade(rc)),
+        }
+    }
+}
+
+/// Libère les cycles inaccessibles et renvoie le nombre d'objets qu'ils
+/// contenaient.
+///
+/// Une référence à un objet inscrit qui ne vient d'aucun autre objet inscrit
+/// vient de l'extérieur : globales, pile, variables de l'interpréteur.
+/// Les objets ainsi désignés, et tout ce qu'ils désignent, sont vivants ; les
+/// autres ne forment plus que des cycles.
+pub fn collect() -> usize {
+    let tracked = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().objects));
+    let objects: Vec<Object> = tracked.iter().filter_map(Tracked::upgrade).collect();
+    drop(tracked);
+    let index: HashMap<*const (), usize> = objects
+        .iter()
+        .enumerate()
+        .map(|(i, object)| (object.address(), i))
+        .collect();
+
+    // Références externes : le compte total, moins celle tenue par `objects`
+    // et celles venant d'autres objets inscrits.
+    let mut external: Vec<isize> = objects.iter().map(|o| o.strong_count() as isize - 1).collect();
+    let mut busy = vec![false; objects.len()];
+    for (i, object) in objects.iter().enumerate() {
+        busy[i] = !object.trace(&mut |child| {
+            if let Some(&j) = index.get(&child) {
+                external[j] -= 1;
+            }
+        });
+    }
+
+    let mut alive = vec![false; objects.len()];
+    let mut pending: Vec<usize> = (0..objects.len()).filter(|&i| external[i] > 0 || busy[i]).collect();
+    while let Some(i) = pending.pop() {
+        if alive[i] {
+            continue;
+        }
+        alive[i] = true;
+        objects[i].trace(&mut |child| {
+            if let Some(&j) = index.get(&child) {
+                if !alive[j] {
+                    pending.push(j);
+                }
+            }
+        });
+    }
+
+    // Les valeurs retirées ne sont libérées qu'une fois tous les cycles
+    // cassés, hors de tout emprunt.
+    let mut environments = Vec::new();
+    let mut upvalues = Vec::new();
+    for (object, _) in objects.iter().zip(&alive).filter(|(_, alive)| !**alive) {
+        match object {
+            Object::Environment(rc) => environments.push(std::mem::take(&mut *rc.borrow_mut())),
+            Object::Upvalue(rc) => {
+                upvalues.push(std::mem::replace(&mut *rc.borrow_mut(), Upvalue::Closed(Value::Nil)))
+            }
+            // Tout cycle passant par une fermeture passe aussi par une
+            // variable capturée.
+            Object::Closure(_) => {}
+        }
+    }
+    let freed = alive.iter().filter(|alive| !**alive).count();
+
+    let survivors: Vec<Tracked> = objects
+        .iter()
+        .zip(&alive)
+        .filter(|(_, alive)| **alive)
+        .map(|(object, _)| object.downgrade())
+        .collect();
+    drop(environments);
+    drop(upvalues);
+    drop(objects);
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        // Les objets inscrits pendant la collecte s'ajoutent aux survivants.
+        let created = std::mem::replace(&mut heap.objects, survivors);
+        heap.objects.extend(created);
+        heap.threshold = (heap.objects.len() * 2).max(MIN_THRESHOLD);
+        heap.collections += 1;
+        heap.freed += freed;
+    });
+    freed
+}
+
+/// Statistiques affichées par `memoire()`.
+pub fn statistics() -> String {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        let (mut environments, mut closures, mut upvalues) = (0, 0, 0);
+        for object in &heap.objects {
+            match object {
+                Tracked::Environment(weak) if weak.strong_count() > 0 => environments += 1,
+                Tracked::Closure(weak) if weak.strong_count() > 0 => closures += 1,
+                Tracked::Upvalue(weak) if weak.strong_count() > 0 => upvalues += 1,
+                _ => {}
+            }
+        }
+        catalog::text(
+            "memoire.statistiques",
+            &[&environments, &closures, &upvalues, &heap.collections, &heap.freed],
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Lexer, stmt::Stmt, value::Function};
+
+    /// Environnement qui contient une fonction déclarée chez lui : le cycle
+    /// que laisse chaque appel d'une fonction à fonction locale.
+    fn cycle() -> Rc<RefCell<Environment>> {
+        let mut lexer = Lexer::new("fonction f() debut fin");
+        lexer.scan_tokens();
+        let Stmt::Function(stmt) = Parser::new(lexer.tokens).parse().unwrap().remove(0) else { panic!() };
+        let environment = Environment::new_enclosed(None);
+        let function = Value::Function(Function {
+            stmt,
+            closure: Some(Rc::clone(&environment)),
+        });
+        environment.borrow_mut().define(0, function);
+        environment
+    }
+
+    #[test]
+    fn unreachable_cycles_are_freed() {
+        let environment = Rc::downgrade(&cycle());
+        assert!(environment.upgrade().is_some());
+        assert_eq!(collect(), 1);
+        assert!(environment.upgrade().is_none());
+        assert_eq!(collect(), 0);
+    }
+
+    #[test]
+    fn reachable_cycles_are_kept() {
+        let kept = cycle();
+        let inner = Environment::new_enclosed(Some(&kept));
+        let dropped = Rc::downgrade(&cycle());
+        assert_eq!(collect(), 1);
+        assert!(dropped.upgrade().is_none());
+        assert!(matches!(kept.borrow().get_at(0, 0), Some(Value::Function(_))));
+        drop(inner);
+        assert_eq!(collect(), 0);
+        assert_eq!(
+            statistics(),
+            "1 environnements, 0 fermetures, 0 variables capturées ; 2 collectes, 1 objets libérés"
+        );
+    }
+}
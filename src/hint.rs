@@ -0,0 +1,303 @@
+use crate::{
+    catalog,
+    diagnostic::Diagnostic,
+    error::ParserError,
+    span::Span,
+    token::{Token, TokenType},
+};
+
+/// Explication d'une erreur fréquente chez les débutants, avec la correction
+/// à apporter. Elle s'ajoute au diagnostic sous forme de note et d'aide.
+#[derive(Debug, Clone)]
+pub struct Hint {
+    explanation: String,
+    fix: String,
+    /// Endroit du programme à l'origine de l'erreur, s'il est ailleurs.
+    origin: Option<(Span, String)>,
+}
+
+impl Hint {
+    fn new(explanation: impl Into<String>, fix: impl Into<String>) -> Self {
+        Hint {
+            explanation: explanation.into(),
+            fix: fix.into(),
+            origin: None,
+        }
+    }
+
+    fn with_origin(mut self, span: Span, label: impl Into<String>) -> Self {
+        self.origin = Some((span, label.into()));
+        self
+    }
+
+    pub fn attach(&self, mut diagnostic: Diagnostic) -> Diagnostic {
+        if let Some((span, label)) = &self.origin {
+            diagnostic = diagnostic.with_secondary(*span, label);
+        }
+        diagnostic.with_note(&self.explanation).with_help(&self.fix)
+    }
+}
+
+/// Reconnaît une erreur de syntaxe fréquente d'après les tokens qui
+/// l'entourent : `x = 5`, `finsi` oublié, `si` ou `sinon` sans `alors`.
+pub fn for_parse_error(error: &ParserError, tokens: &[Token]) -> Option<Hint> {
+    let index = tokens.iter().position(|token| token.span == error.token.span)?;
+    match error.token.token_type {
+        TokenType::EQUAL => equal_sign(tokens, index),
+        TokenType::ENDIF => {
+            else_without_then(tokens, index).or_else(|| stray_if_keyword(tokens, index))
+        }
+        TokenType::ELSE => stray_if_keyword(tokens, index),
+        TokenType::EOF | TokenType::END | TokenType::ENDWHILE | TokenType::RIGHT_BRACE => {
+            missing_endif(tokens, index)
+        }
+        _ => None,
+    }
+}
+
+/// Un texte lu au clavier utilisé comme un nombre dans un calcul.
+pub fn text_used_as_number() -> Hint {
+    Hint::new(
+        catalog::text("conseil.texte-calcul", &[]),
+        catalog::text("conseil.texte-calcul.aide", &[]),
+    )
+}
+
+/// Un texte lu au clavier comparé à un nombre : la comparaison est toujours
+/// fausse, sans erreur.
+pub fn text_compared_to_number(name: Option<&str>) -> Hint {
+    let fix = match name {
+        Some(name) => catalog::text("conseil.texte-comparaison.aide-variable", &[&name]),
+        None => catalog::text("conseil.texte-comparaison.aide", &[]),
+    };
+    Hint::new(catalog::text("conseil.texte-comparaison", &[]), fix)
+}
+
+/// Début de l'instruction qui contient le token `index`.
+fn statement_start(tokens: &[Token], index: usize) -> usize {
+    tokens[..index]
+        .iter()
+        .rposition(|token| {
+            matches!(
+                token.token_type,
+                TokenType::NEWLINE
+                    | TokenType::SEMICOLON
+                    | TokenType::THEN
+                    | TokenType::DO
+                    | TokenType::START
+                    | TokenType::INDENT
+                    | TokenType::DEDENT
+            )
+        })
+        .map_or(0, |position| position + 1)
+}
+
+fn equal_sign(tokens: &[Token], index: usize) -> Option<Hint> {
+    let start = statement_start(tokens, index);
+    let statement = &tokens[start..index];
+    let condition = statement
+        .iter()
+        .any(|token| matches!(token.token_type, TokenType::IF | TokenType::WHILE));
+    if condition {
+        return Some(Hint::new(
+            catalog::text("conseil.egal-comparaison", &[]),
+            catalog::text("conseil.egal-comparaison.aide", &[]),
+        ));
+    }
+
+    let target = &tokens[index.checked_sub(1)?];
+    if target.token_type != TokenType::IDENTIFIER {
+        return None;
+    }
+    let fix = if statement.first().map(|token| &token.token_type) == Some(&TokenType::VAR) {
+        catalog::text("conseil.egal-declaration.aide", &[&target.lexeme])
+    } else {
+        catalog::text("conseil.egal-affectation.aide", &[&target.lexeme])
+    };
+    Some(Hint::new(catalog::text("conseil.egal-affectation", &[]), fix))
+}
+
+/// `sinon` ou `finsi` qui ne se rattache à aucun bloc `si ... alors`.
+fn stray_if_keyword(tokens: &[Token], index: usize) -> Option<Hint> {
+    let keyword = &tokens[index].lexeme;
+    let if_index = tokens[..index]
+        .iter()
+        .rposition(|token| token.token_type == TokenType::IF)?;
+
+    if has_then(tokens, if_index, index) {
+        return Some(Hint::new(
+            catalog::text("conseil.mot-isole", &[keyword]),
+            catalog::text("conseil.mot-isole.aide", &[keyword]),
+        ));
+    }
+    Some(
+        Hint::new(
+            catalog::text("conseil.si-sans-alors", &[keyword]),
+            catalog::text("conseil.si-sans-alors.aide", &[]),
+        )
+        .with_origin(tokens[if_index].span, catalog::text("conseil.si-sans-alors.origine", &[])),
+    )
+}
+
+/// Vrai si la ligne du `si` placé en `if_index` contient `alors`, sans
+/// chercher au-delà de `limit`.
+fn has_then(tokens: &[Token], if_index: usize, limit: usize) -> bool {
+    tokens[if_index..limit]
+        .iter()
+        .take_while(|token| token.token_type != TokenType::NEWLINE)
+        .any(|token| token.token_type == TokenType::THEN)
+}
+
+/// `finsi` resté seul parce que le `sinon` qui le précède n'a pas de
+/// `alors` : seule l'instruction suivante appartient au `sinon`.
+fn else_without_then(tokens: &[Token], index: usize) -> Option<Hint> {
+    let else_index = tokens[..index]
+        .iter()
+        .rposition(|token| token.token_type == TokenType::ELSE)?;
+    let next = tokens[else_index + 1..index]
+        .iter()
+        .find(|token| token.token_type != TokenType::NEWLINE)?;
+    if next.token_type == TokenType::THEN {
+        return None;
+    }
+    // Les blocs `si ... alors` ouverts après le `sinon` ont chacun leur
+    // `finsi` ; ce `sinon` n'est en cause que s'ils sont tous refermés, et
+    // qu'aucun `si` plus récent n'a lui-même oublié son `alors`.
+    let mut open = 0;
+    for (position, token) in tokens.iter().enumerate().take(index).skip(else_index + 1) {
+        match token.token_type {
+            TokenType::IF if !has_then(tokens, position, index) => return None,
+            TokenType::THEN if tokens[position - 1].token_type != TokenType::ELSE => open += 1,
+            TokenType::ENDIF if open == 0 => return None,
+            TokenType::ENDIF => open -= 1,
+            _ => {}
+        }
+    }
+    if open != 0 {
+        return None;
+    }
+    let keyword = &tokens[index].lexeme;
+    Some(
+        Hint::new(
+            catalog::text("conseil.sinon-sans-alors", &[keyword]),
+            catalog::text("conseil.sinon-sans-alors.aide", &[]),
+        )
+        .with_origin(tokens[else_index].span, catalog::text("conseil.sinon-sans-alors.origine", &[])),
+    )
+}
+
+/// Fin de bloc ou de fichier atteinte alors qu'un `si ... alors` est ouvert.
+fn missing_endif(tokens: &[Token], index: usize) -> Option<Hint> {
+    let mut open: Vec<usize> = Vec::new();
+    for (position, token) in tokens[..index].iter().enumerate() {
+        match token.token_type {
+            // `sinon alors` prolonge le bloc sans en ouvrir un nouveau, et un
+            // bloc indenté se ferme tout seul.
+            TokenType::THEN
+                if position > 0
+                    && tokens[position - 1].token_type != TokenType::ELSE
+                    && !opens_indented_block(tokens, position) =>
+            {
+                open.push(position)
+            }
+            TokenType::ENDIF => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    let then = *open.last()?;
+    let if_span = tokens[..then]
+        .iter()
+        .rposition(|token| token.token_type == TokenType::IF)
+        .map_or(tokens[then].span, |position| tokens[position].span);
+    Some(
+        Hint::new(
+            catalog::text("conseil.finsi", &[]),
+            catalog::text("conseil.finsi.aide", &[]),
+        )
+        .with_origin(if_span, catalog::text("conseil.finsi.origine", &[])),
+    )
+}
+
+fn opens_indented_block(tokens: &[Token], then: usize) -> bool {
+    tokens[then + 1..]
+        .iter()
+        .find(|token| token.token_type != TokenType::NEWLINE)
+        .is_some_and(|token| token.token_type == TokenType::INDENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Lexer};
+
+    /// Correction proposée pour la première erreur de syntaxe de `source`,
+    /// avec la ligne du `si` ou du `sinon` en cause.
+    fn hint(source: &str) -> Option<(String, Option<u32>)> {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        let mut parser = Parser::new(lexer.tokens);
+        let errors = parser.parse().unwrap_err();
+        let hint = for_parse_error(&errors[0], parser.tokens())?;
+        Some((hint.fix, hint.origin.map(|(span, _)| span.line)))
+    }
+
+    fn fix(text: &str, line: Option<u32>) -> Option<(String, Option<u32>)> {
+        Some((text.to_string(), line))
+    }
+
+    #[test]
+    fn single_equal_sign() {
+        assert_eq!(hint("si a = 1 alors ecrire a finsi"), fix("remplacez '=' par '=='.", None));
+        assert_eq!(hint("variable a = 1"), fix("écrivez 'variable a <- ...'.", None));
+        assert_eq!(hint("variable a <- 1\na = 2"), fix("écrivez 'a <- ...'.", None));
+    }
+
+    #[test]
+    fn missing_endif() {
+        assert_eq!(
+            hint("si vrai alors\necrire 1\necrire 2"),
+            fix("ajoutez 'finsi' après la dernière instruction du bloc.", Some(1))
+        );
+        assert_eq!(
+            hint("fonction f() debut\nsi vrai alors\nretourner 1\nfin"),
+            fix("ajoutez 'finsi' après la dernière instruction du bloc.", Some(2))
+        );
+    }
+
+    #[test]
+    fn if_without_then() {
+        assert_eq!(
+            hint("si vrai\necrire 1\nfinsi"),
+            fix("ajoutez 'alors' à la fin de la ligne du 'si'.", Some(1))
+        );
+    }
+
+    #[test]
+    fn else_without_then() {
+        assert_eq!(
+            hint("si vrai alors\necrire 1\nsinon\necrire 2\nfinsi"),
+            fix("écrivez 'sinon alors' pour que le bloc aille jusqu'au 'finsi'.", Some(3))
+        );
+        // Un bloc complet ouvert après le `sinon` ne change rien.
+        assert_eq!(
+            hint("si vrai alors\necrire 1\nsinon si faux alors ecrire 2 finsi\nfinsi"),
+            fix("écrivez 'sinon alors' pour que le bloc aille jusqu'au 'finsi'.", Some(3))
+        );
+    }
+
+    #[test]
+    fn stray_endif() {
+        assert_eq!(
+            hint("si vrai alors ecrire 1 finsi\nfinsi"),
+            fix("supprimez ce 'finsi', ou vérifiez où se ferme le bloc 'si' précédent.", None)
+        );
+    }
+
+    #[test]
+    fn other_errors_have_no_hint() {
+        assert_eq!(hint("ecrire (1"), None);
+        assert_eq!(hint("variable 1 <- 2"), None);
+    }
+}
@@ -1,78 +1,95 @@
-use std::clone;
-use std::collections::HashMap;
 use std::rc::Rc;
 use std::{ cell::RefCell};
+use std::fmt::Write;
 
-use crate::builtin::{afficher, clock, lire, to_int};
+use crate::budget::{Budget, Limits};
+use crate::builtin::natives;
 use crate::callable::Callable;
 use crate::stmt::ReturnStmt;
-use crate::token::Token;
-use crate::value::{self, Function, NativeFunction};
+use crate::symbol::Symbol;
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::token::{Token, TokenType};
+use crate::value::Function;
 use crate::{
-    environment::Environment,
+    diagnostic::Frame,
+    span::Span,
+    catalog::Message,
+    environment::{Environment, Globals, Location},
     error::RuntimeError,
-    expr::{BinaryExpr, Expr, GroupingExpr, Literal, LiteralExpr, UnaryExpr},
+    hint,
+    expr::{BinaryExpr, Expr, GroupingExpr, Literal, LiteralExpr, NodeId, UnaryExpr},
     stmt::Stmt,
     value::Value,
     visitor::{ExprVisitor, StmtVisitor},
 };
+/// Appel de fonction en cours, conservé pour la pile d'appels des erreurs.
+#[derive(Debug, Clone)]
+struct CallFrame {
+    function: String,
+    call_site: Span,
+    arguments: Vec<Value>,
+}
+
+impl CallFrame {
+    fn to_frame(&self) -> Frame {
+        let arguments: Vec<String> = self.arguments.iter().map(summarize).collect();
+        Frame {
+            function: self.function.clone(),
+            arguments: arguments.join(", "),
+            call_site: self.call_site,
+        }
+    }
+}
+
+/// Résumé court d'un argument : les textes sont entre guillemets et tronqués.
+pub fn summarize(value: &Value) -> String {
+    const MAX: usize = 20;
+    match value {
+        Value::String(text) if text.chars().count() > MAX => {
+            format!("\"{}...\"", text.chars().take(MAX).collect::<String>())
+        }
+        Value::String(text) => format!("\"{}\"", text),
+        other => other.to_string(),
+    }
+}
+
+/// Adresse approximative du sommet de la pile native ; elle décroît à
+/// chaque appel imbriqué.
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
 #[derive(Debug,Clone)]
 pub struct InterpretVisitor {
-    pub global: Rc<RefCell<Environment>>,
-    pub environment: Rc<RefCell<Environment>>,
-    locals: HashMap<Token, usize>,
+    pub globals: Globals,
+    /// Environnement du bloc en cours ; absent au niveau supérieur.
+    pub environment: Option<Rc<RefCell<Environment>>>,
+    /// Emplacement de chaque variable déclarée, lue ou affectée, indexé par
+    /// l'identifiant du nœud qui la nomme.
+    locations: Vec<Option<Location>>,
+    frames: Vec<CallFrame>,
+    /// Nombre maximal d'appels imbriqués avant une erreur E0318.
+    max_depth: usize,
+    /// Pile native disponible pour `interpret`, en octets ; au-delà, un
+    /// appel lève E0322 même sous `max_depth`, car des blocs ou des
+    /// expressions imbriqués dans chaque appel consomment aussi la pile.
+    stack_budget: Option<usize>,
+    /// Adresse de pile sous laquelle un nouvel appel est refusé, calculée à
+    /// chaque `interpret` à partir de `stack_budget`.
+    stack_limit: usize,
+    /// Étapes, durée et mémoire consommées par l'exécution en cours.
+    budget: Budget,
+    /// Reçoit ce qu'écrit `ecrire` à la place de la sortie standard.
+    output: Option<Rc<RefCell<String>>>,
 }
 
 impl ExprVisitor for InterpretVisitor {
     type Output = Result<Value, RuntimeError>;
 
     fn visit_call_expr(&mut self, expr: &crate::expr::CallExp) -> Self::Output {
-        let callee = self.evaluate(&expr.callee)?;
-        
-       let mut arguments = Vec::new();
-        for argument in &expr.arguments {
-            let arg = self.evaluate(argument)?;
-
-            if arg != Value::Nil {
-                arguments.push(arg);
-               
-            }
-        }
-        match callee {
-            Value::NativeFunction(function) => {
-                if arguments.len() != function.arity {
-                    return Err(RuntimeError::Error {
-                        token: expr.paren.clone(),
-                        message: format!(
-                            "Expected {} arguments but got {}.",
-                            function.arity,
-                            arguments.len()
-                        ),
-                    });
-                }
-                function.call(self, arguments)
-            }
-            Value::Function(function) => {
-                if arguments.len() != function.stmt.params.len() {
-                    return Err(RuntimeError::Error {
-                        token: expr.paren.clone(),
-                        message: format!(
-                            "Expected {} arguments but got {}.",
-                            function.stmt.params.len(),
-                            arguments.len()
-                        ),
-                    });
-                }
-              //  println!("Avant l'appel de la fonction : {:?}", self.environment);
-               let res =  function.call(self, arguments);
-               // println!("Après l'appel de la fonction : {:?}", self.environment);
-                Ok(res.unwrap())
-            }
-            _ => Err(RuntimeError::Error {
-                token: expr.paren.clone(),
-                message: "Can only call functions ".to_string(),
-            }),
-        }
+        let (callee, arguments) = self.call_operands(expr)?;
+        self.call_value(callee, arguments, expr)
     }
 
     fn visit_logical_expr(&mut self, expr: &crate::expr::LogicalExpr) -> Self::Output {
@@ -93,17 +110,21 @@ impl ExprVisitor for InterpretVisitor {
 
     fn visit_variable_expr(&mut self, expr: &crate::expr::VariableExpr) -> Self::Output {
       
-        Ok(self.lookup_variable(&expr.name).unwrap())
+        self.lookup_variable(expr.id, &expr.name)
     }
 
     fn visit_assign_var_expr(&mut self, expr: &crate::expr::AssignVarExpr) -> Self::Output {
        
         let val = self.evaluate(&expr.value)?;
 
-        if let Some(distance) = self.locals.get(&expr.name) {
-            self.environment.borrow_mut().assign_at(*distance, &expr.name, val.clone())?;
-        } else {
-            self.global.borrow_mut().assign(&expr.name, val.clone())?;
+        match self.location(expr.id) {
+            Some(Location::Local { depth, slot }) => self
+                .environment
+                .as_ref()
+                .and_then(|environment| environment.borrow_mut().assign_at(depth, slot, val.clone()))
+                .ok_or_else(|| Environment::missing_ancestor(&expr.name))?,
+            Some(Location::Global(index)) => self.globals.assign(index, &expr.name, val.clone())?,
+            None => return Err(RuntimeError::new(&expr.name, Message::new("E0313", &[&expr.name.lexeme]))),
         }
 
         Ok(val)
@@ -112,23 +133,18 @@ impl ExprVisitor for InterpretVisitor {
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Result<Value, RuntimeError> {
         let left = self.evaluate(&expr.left)?;
         let right = self.evaluate(&expr.right)?;
-        match expr.operator.lexeme.as_str() {
-            "+" => Ok(left + right),
-            "-" => Ok(left - right),
-            "*" => Ok(left * right),
-            "/" => Ok(left / right),
-            "%" => Ok(left % right),
-            ">" => Ok(Value::Boolean(left > right)),
-            "<" => Ok(Value::Boolean(left < right)),
-            ">=" => Ok(Value::Boolean(left >= right)),
-            "<=" => Ok(Value::Boolean(left <= right)),
-            "==" => Ok(Value::Boolean(left == right)),
-            "!=" => Ok(Value::Boolean(left != right)),
-            _ => Err(RuntimeError::Error {
-                token: expr.operator.clone(),
-                message: "Unknown operator".to_string(),
-            }),
-        }
+        let text_and_number = matches!(
+            (&left, &right),
+            (Value::String(_), Value::Number(_)) | (Value::Number(_), Value::String(_))
+        );
+        InterpretVisitor::binary(&expr.operator.lexeme, left, right).map_err(|message| {
+            let error = RuntimeError::at(expr.span, message);
+            if text_and_number {
+                error.with_hint(hint::text_used_as_number())
+            } else {
+                error
+            }
+        })
     }
 
     fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Result<Value, RuntimeError> {
@@ -137,9 +153,17 @@ impl ExprVisitor for InterpretVisitor {
 
     fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Result<Value, RuntimeError> {
         match &expr.value {
-            Literal::Number(token) => Ok(Value::Number(token.lexeme.parse::<f64>().unwrap())),
-            Literal::String(token) => Ok(Value::String(token.lexeme.clone())),
-            Literal::Boolean(token) => Ok(Value::Boolean(token.lexeme.parse::<bool>().unwrap())),
+            Literal::Number(token) => token
+                .literal
+                .as_deref()
+                .unwrap_or(&token.lexeme)
+                .parse::<f64>()
+                .map(Value::Number)
+                .map_err(|_| {
+                    RuntimeError::new(token, Message::new("E0304", &[&token.lexeme]))
+                }),
+            Literal::String(_, text) => Ok(Value::String(Rc::clone(text))),
+            Literal::Boolean(token) => Ok(Value::Boolean(token.token_type == TokenType::TRUE)),
             Literal::Nil => Ok(Value::Nil),
         }
     }
@@ -148,18 +172,18 @@ impl ExprVisitor for InterpretVisitor {
         let right = match self.evaluate(&expr.right) {
             Ok(Value::Number(num)) => num,
             Ok(Value::Boolean(bool)) => bool as i32 as f64,
-            _ => Err(RuntimeError::Error {
-                token: expr.operator.clone(),
-                message: "Operand must be a number or boolean".to_string(),
-            })?,
+            _ => Err(RuntimeError::at(
+                expr.right.span(),
+                Message::new("E0305", &[]),
+            ))?,
         };
         match expr.operator.lexeme.as_str() {
             "-" => Ok(Value::Number(-right)),
             "!" => Ok(Value::Boolean(!InterpretVisitor::make_bool_value(right))),
-            _ => Err(RuntimeError::Error {
-                token: expr.operator.clone(),
-                message: "Unknown operator".to_string(),
-            })?,
+            _ => Err(RuntimeError::new(
+                &expr.operator,
+                Message::new("E0303", &[&expr.operator.lexeme]),
+            ))?,
         }
     }
 }
@@ -168,6 +192,19 @@ impl StmtVisitor for InterpretVisitor {
     type Output = Result<(), RuntimeError>;
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::Output {
+        if let (true, Some(Expr::CallExpr(call))) = (stmt.tail_call, &stmt.value) {
+            let (callee, arguments) = self.call_operands(call)?;
+            return match callee {
+                Value::Function(function) if arguments.len() == function.arity() => {
+                    Err(RuntimeError::TailCall {
+                        function,
+                        arguments,
+                        call_site: call.span,
+                    })
+                }
+                callee => Err(RuntimeError::Return(self.call_value(callee, arguments, call)?)),
+            };
+        }
         match stmt.value {
             Some(ref expr) => {
                 let value = self.evaluate(expr)?;
@@ -177,22 +214,17 @@ impl StmtVisitor for InterpretVisitor {
         }
     }
 
-    fn visit_function_stmt(&mut self, stmt: &crate::stmt::FunctionStmt) -> Self::Output {
+    fn visit_function_stmt(&mut self, stmt: &Rc<crate::stmt::FunctionStmt>) -> Self::Output {
         let function = Value::Function(Function {
-            stmt: stmt.clone(),
-            closure: Rc::clone(&self.environment),
+            stmt: Rc::clone(stmt),
+            closure: self.environment.clone(),
         });
-        self.environment
-            .borrow_mut()
-            .define(stmt.name.lexeme.clone(), function);
+        self.define(stmt.id, &stmt.name, function);
         Ok(())
     }
     fn visit_while_stmt(&mut self, stmt: &crate::stmt::WhileStmt) -> Self::Output {
-        //println!("while condition : {:?}", &stmt.condition);
-        println!();
-      //  println!("while body : {:?} ", &stmt.body);
-        while InterpretVisitor::is_truthy(&self.evaluate(&stmt.condition).unwrap()) {
-            self.execute(&stmt.body);
+        while InterpretVisitor::is_truthy(&self.evaluate(&stmt.condition)?) {
+            self.execute(&stmt.body)?;
         }
         Ok(())
     }
@@ -200,7 +232,6 @@ impl StmtVisitor for InterpretVisitor {
     fn visit_if_stmt(&mut self, stmt: &crate::stmt::IfStmt) -> Self::Output {
         let condition = self.evaluate(&stmt.condition)?;
 
-        //println!("\t\t[condition:  {} resultat : {:?}]",&stmt.condition, &condition);
         if InterpretVisitor::is_truthy(&condition) {
             self.execute(&stmt.then_branch)?;
         } else if let Some(else_branch) = &stmt.else_branch {
@@ -224,10 +255,7 @@ impl StmtVisitor for InterpretVisitor {
 
         self.execute_block(
             &stmt.statements,
-            Rc::new(RefCell::new(Environment::new_enclosed(
-                &self.environment,
-                4,
-            ))),
+            Environment::new_enclosed(self.environment.as_ref()),
         )?;
 
         // Restore the previous environment after executing the block
@@ -237,23 +265,21 @@ impl StmtVisitor for InterpretVisitor {
 
     fn visit_print_stmt(&mut self, stmt: &crate::stmt::PrintStmt) -> Self::Output {
         let value = self.evaluate(&stmt.expression)?;
-        println!("{}", self.stringify(&value));
+        match &self.output {
+            Some(output) => writeln!(output.borrow_mut(), "{}", InterpretVisitor::stringify(&value)).unwrap(),
+            None => println!("{}", InterpretVisitor::stringify(&value)),
+        }
 
         Ok(())
     }
 
     fn visit_var_stmt(&mut self, stmt: &crate::stmt::VarStmt) -> Self::Output {
-        let mut val = None;
-        match &stmt.initializer {
-            Some(ref expr) => {
-                val = Some(self.evaluate(expr)?);
-            }
-            None => val = Some(Value::Nil),
+        let val = match &stmt.initializer {
+            Some(ref expr) => self.evaluate(expr)?,
+            None => Value::Nil,
         };
 
-        self.environment
-            .borrow_mut()
-            .define(stmt.name.lexeme.clone(), val.unwrap());
+        self.define(stmt.id, &stmt.name, val);
 
         Ok(())
     }
@@ -261,72 +287,179 @@ impl StmtVisitor for InterpretVisitor {
 
 impl InterpretVisitor {
     pub fn new() -> Self {
-        let global = Rc::new(RefCell::new(Environment::new()));
-        /*  let env = InterpretVisitor {
-            global: Rc::new(RefCell::new(Environment::new())),
-        };*/
-
-        let clock_function = NativeFunction {
-            arity: 0,
-            name: "clock".to_string(),
-            function: clock,
-        };
+        let mut globals = Globals::default();
+        for (name, function) in natives() {
+            let index = globals.index(Symbol::intern(name));
+            globals.define(index, Value::NativeFunction(function));
+        }
 
-        let print_function = NativeFunction {
-            arity: 1,
-            name: "print".to_string(),
-            function: afficher,
-        };
+        InterpretVisitor {
+            globals,
+            environment: None,
+            locations: Vec::new(),
+            frames: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            stack_budget: None,
+            stack_limit: 0,
+            budget: Budget::new(Limits::default()),
+            output: None,
+        }
+    }
 
+    /// Limites appliquées à chaque appel de `interpret`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits);
+    }
 
-        let read_function = NativeFunction {
-            arity: 1,
-            name: "lire".to_string(),
-            function: lire,
-        };
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
 
-        let to_int_function = NativeFunction {
-            arity: 1,
-            name: "entier".to_string(),
-            function: to_int,
-        };
+    /// Détourne la sortie de `ecrire` vers un texte partagé.
+    #[cfg(test)]
+    pub fn capture_output(&mut self) -> Rc<RefCell<String>> {
+        Rc::clone(self.output.insert(Rc::default()))
+    }
 
-        let to_int_value = Value::NativeFunction(to_int_function);
-        let clock_value = Value::NativeFunction(clock_function);
-        let print_value = Value::NativeFunction(print_function);
-        let read_value = Value::NativeFunction(read_function);
-        global.borrow_mut().define("clock".to_string(), clock_value);
-        global.borrow_mut().define("Nombre".to_string(), to_int_value);
-        global.borrow_mut().define("print".to_string(), print_value);
+    /// Pile native, en octets, que `interpret` peut consommer depuis son
+    /// point d'entrée.
+    pub fn set_stack_budget(&mut self, bytes: usize) {
+        self.stack_budget = Some(bytes);
+    }
 
-        global.borrow_mut().define("lire".to_string(), read_value);
+    /// Évalue la fonction appelée puis ses arguments. Comme dans tout
+    /// appel, les arguments VIDE ne sont pas transmis.
+    fn call_operands(&mut self, expr: &crate::expr::CallExp) -> Result<(Value, Vec<Value>), RuntimeError> {
+        let callee = self.evaluate(&expr.callee)?;
 
-        InterpretVisitor {
-            global: Rc::clone(&global),
-            environment: Rc::clone(&global),
-            locals: HashMap::new(),
+        let mut arguments = Vec::new();
+        for argument in &expr.arguments {
+            let arg = self.evaluate(argument)?;
+
+            if arg != Value::Nil {
+                arguments.push(arg);
+            }
         }
+        Ok((callee, arguments))
     }
 
-    pub fn interpret(&mut self, stmts: &Vec<Stmt>) -> Result<(), RuntimeError> {
+    fn call_value(&mut self, callee: Value, arguments: Vec<Value>, expr: &crate::expr::CallExp) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::NativeFunction(function) => {
+                if arguments.len() != function.arity() {
+                    return Err(RuntimeError::at(
+                        expr.span,
+                        Message::new("E0301", &[&function.arity(), &arguments.len()]),
+                    ));
+                }
+                self.frames.push(CallFrame {
+                    function: function.name.clone(),
+                    call_site: expr.span,
+                    arguments: arguments.clone(),
+                });
+                let res = function.call(self, arguments, expr);
+                self.leave_frame(res)
+            }
+            Value::Function(function) => {
+                if arguments.len() != function.arity() {
+                    return Err(RuntimeError::at(
+                        expr.span,
+                        Message::new("E0301", &[&function.arity(), &arguments.len()]),
+                    ));
+                }
+                if self.frames.len() >= self.max_depth {
+                    return Err(RuntimeError::at(expr.span, Message::new("E0318", &[&self.max_depth])));
+                }
+                if stack_address() < self.stack_limit {
+                    return Err(RuntimeError::at(expr.span, Message::new("E0322", &[&self.frames.len()])));
+                }
+                self.frames.push(CallFrame {
+                    function: function.stmt.name.lexeme.clone(),
+                    call_site: expr.span,
+                    arguments: arguments.clone(),
+                });
+                let res = function.call(self, arguments, expr);
+                self.leave_frame(res)
+            }
+            _ => Err(RuntimeError::at(
+                expr.callee.span(),
+                Message::new("E0302", &[&callee.type_name()]),
+            )),
+        }
+    }
+
+    /// Appel terminal : `function` prend la place de l'appel en cours dans la
+    /// pile d'appels.
+    pub fn replace_frame(&mut self, function: &Function, call_site: Span, arguments: &[Value]) {
+        if let Some(frame) = self.frames.last_mut() {
+            *frame = CallFrame {
+                function: function.stmt.name.lexeme.clone(),
+                call_site,
+                arguments: arguments.to_vec(),
+            };
+        }
+    }
+
+    /// Dépile l'appel courant. Une erreur qui remonte pour la première fois
+    /// emporte avec elle la pile d'appels complète.
+    fn leave_frame(&mut self, res: Result<Value, RuntimeError>) -> Result<Value, RuntimeError> {
+        let res = res.map_err(|mut error| {
+            if let RuntimeError::Error { trace, .. } = &mut error {
+                if trace.is_empty() {
+                    *trace = self.frames.iter().rev().map(CallFrame::to_frame).collect();
+                }
+            }
+            error
+        });
+        self.frames.pop();
+        res
+    }
+
+    /// Exécute un programme, ou une saisie du REPL, avec un budget neuf.
+    pub fn interpret(&mut self, stmts: &[Stmt]) -> Result<(), RuntimeError> {
+        self.budget.restart();
+        if let Some(budget) = self.stack_budget {
+            self.stack_limit = stack_address().saturating_sub(budget);
+        }
         for stmt in stmts {
-            stmt.accept(self)?;
+            self.execute(stmt)?;
         }
         Ok(())
     }
 
     pub fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        self.budget.charge().map_err(|message| RuntimeError::at(stmt.span(), message))?;
         stmt.accept(self)
     }
 
     fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.budget.charge().map_err(|message| RuntimeError::at(expr.span(), message))?;
         expr.accept(self)
     }
-    fn make_bool_value(val: f64) -> bool {
+    /// Applique un opérateur binaire à deux valeurs ; l'optimiseur s'en sert
+    /// aussi pour calculer les expressions constantes.
+    pub fn binary(operator: &str, left: Value, right: Value) -> Result<Value, Message> {
+        match operator {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            ">" => Ok(Value::Boolean(left > right)),
+            "<" => Ok(Value::Boolean(left < right)),
+            ">=" => Ok(Value::Boolean(left >= right)),
+            "<=" => Ok(Value::Boolean(left <= right)),
+            "==" => Ok(Value::Boolean(left == right)),
+            "!=" => Ok(Value::Boolean(left != right)),
+            _ => Err(Message::new("E0303", &[&operator])),
+        }
+    }
+
+    pub fn make_bool_value(val: f64) -> bool {
         val == 1.0
     }
 
-    fn stringify(&self, value: &Value) -> String {
+    pub fn stringify(value: &Value) -> String {
         match value {
             Value::Nil => "nil".to_string(),
             Value::Number(num) => num.to_string(),
@@ -334,53 +467,191 @@ impl InterpretVisitor {
             Value::Boolean(bool) => bool.to_string(),
             Value::NativeFunction(function) => format!("{:?}", function),
             Value::Function(function) => format!("{:?}", function),
+            Value::Closure(closure) => format!("{:?}", closure),
         }
     }
 
     pub fn execute_block(
         &mut self,
-        stmts: &Vec<Stmt>,
+        stmts: &[Stmt],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), RuntimeError> {
-        let previous = self.environment.clone();
-        self.environment = environment;
-    
-        let result = self.interpret(stmts);
-    
+        let previous = self.environment.replace(environment);
+
+        let result = stmts.iter().try_for_each(|stmt| self.execute(stmt));
+
         self.environment = previous;
     
-        match result {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
+        result
     }
 
-    fn is_truthy(value: &Value) -> bool {
+    pub fn is_truthy(value: &Value) -> bool {
         match value {
             Value::Nil => false,
             Value::Number(num) => *num != 0.0,
             Value::String(string) => !string.is_empty(),
             Value::Boolean(bool) => *bool,
             Value::NativeFunction(_) => true,
-            Value::Function(_) => true,
+            Value::Function(_) | Value::Closure(_) => true,
         }
     }
 
-    pub fn resolve(&mut self, name: &Token, depth: usize) {
-        //println!("inserting variable : {} at {} ", &name.lexeme, depth);
-        self.locals.insert(name.clone(), depth );
+    pub fn resolve(&mut self, id: NodeId, location: Location) {
+        if id >= self.locations.len() {
+            self.locations.resize(id + 1, None);
+        }
+        self.locations[id] = Some(location);
     }
 
-    fn lookup_variable(&self, name: &Token) -> Result<Value, RuntimeError> {
+    fn location(&self, id: NodeId) -> Option<Location> {
+        self.locations.get(id).copied().flatten()
+    }
 
-        if let Some(distance) = self.locals.get(name) {
-          //  println!("distance : {:?}",distance);
-          //  println!("envitonnement : {:?}",self.environment);
-          //  println!("locals : {:?}",self.locals);
-            self.environment.borrow().get_at(*distance,&name.lexeme)
-           
-        } else {
-            self.global.borrow().get(&name)
+    /// Définit la variable déclarée par le nœud `id` à l'emplacement choisi
+    /// par le resolver.
+    fn define(&mut self, id: NodeId, name: &Token, value: Value) {
+        match self.location(id) {
+            Some(Location::Local { slot, .. }) => {
+                if let Some(environment) = &self.environment {
+                    environment.borrow_mut().define(slot, value);
+                }
+            }
+            Some(Location::Global(index)) => self.globals.define(index, value),
+            None => {
+                let index = self.globals.index(name.symbol);
+                self.globals.define(index, value);
+            }
         }
     }
+
+    fn lookup_variable(&self, id: NodeId, name: &Token) -> Result<Value, RuntimeError> {
+        match self.location(id) {
+            Some(Location::Local { depth, slot }) => self
+                .environment
+                .as_ref()
+                .ok_or_else(|| Environment::missing_ancestor(name))?
+                .borrow()
+                .get_at(depth, slot)
+                .ok_or_else(|| RuntimeError::new(name, Message::new("E0313", &[&name.lexeme]))),
+            Some(Location::Global(index)) => self.globals.get(index, name),
+            None => Err(RuntimeError::new(name, Message::new("E0313", &[&name.lexeme]))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::resolver_visitor::ResolverVisitor;
+    use crate::scanner::Lexer;
+
+    /// Analyse et exécute `source` ; renvoie ce qu'il a écrit et le
+    /// résultat de l'exécution. Comme dans le REPL, les identifiants de
+    /// nœuds reprennent après ceux des sources déjà exécutées.
+    fn run_with(interpreter: &mut InterpretVisitor, source: &str) -> (String, Result<(), RuntimeError>) {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        assert!(lexer.errors.is_empty());
+        let mut parser = Parser::new(lexer.tokens);
+        parser.set_first_id(interpreter.locations.len());
+        let statements = parser.parse().unwrap();
+        let mut resolver = ResolverVisitor::new(interpreter);
+        resolver.resolve(&statements);
+        assert!(resolver.errors.is_empty(), "{:?}", resolver.errors);
+        let output = interpreter.capture_output();
+        let result = interpreter.interpret(&statements);
+        let output = output.take();
+        (output, result)
+    }
+
+    fn run(source: &str) -> (String, Result<(), RuntimeError>) {
+        run_with(&mut InterpretVisitor::new(), source)
+    }
+
+    /// Code, texte et portion du source désignée par l'erreur d'exécution.
+    fn error(source: &str) -> (&'static str, String, &str) {
+        match run(source).1 {
+            Err(RuntimeError::Error { span, message, .. }) => {
+                (message.code, message.text, &source[span.start..span.end])
+            }
+            other => panic!("erreur attendue : {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_operation() {
+        assert_eq!(
+            error("variable a <- \"un\"\necrire a + 1"),
+            ("E0306", "impossible d'additionner texte et nombre".to_string(), "a + 1")
+        );
+        assert_eq!(error("ecrire 1 / (2 - 2)"), ("E0311", "division par zéro".to_string(), "1 / (2 - 2)"));
+        assert_eq!(error("ecrire -\"a\"").0, "E0305");
+    }
+
+    #[test]
+    fn errors_stop_execution_without_aborting() {
+        let (output, result) = run("ecrire 1\necrire 1 % 0\necrire 2");
+        assert_eq!(output, "1\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn functions_and_texts_share_the_syntax_tree() {
+        let mut lexer = Lexer::new("fonction f() debut retourner \"bonjour\" fin\nvariable a <- f()");
+        lexer.scan_tokens();
+        let statements = Parser::new(lexer.tokens).parse().unwrap();
+        let mut interpreter = InterpretVisitor::new();
+        ResolverVisitor::new(&mut interpreter).resolve(&statements);
+        interpreter.interpret(&statements).unwrap();
+
+        let global = |name: &str| {
+            let symbol = Symbol::intern(name);
+            interpreter.globals.iter().find(|(global, _)| *global == symbol).unwrap().1.clone()
+        };
+        let Stmt::Function(declaration) = &statements[0] else { panic!() };
+        let Value::Function(function) = global("f") else { panic!() };
+        assert!(Rc::ptr_eq(&function.stmt, declaration));
+
+        let Stmt::Return(ret) = &declaration.body[0] else { panic!() };
+        let Some(Expr::LiteralExpr(LiteralExpr { value: Literal::String(_, literal), .. })) = &ret.value else {
+            panic!()
+        };
+        let Value::String(text) = global("a") else { panic!() };
+        assert!(Rc::ptr_eq(&text, literal));
+    }
+
+    const COUNT: &str = "fonction compte(n) debut
+        si n == 0 alors retourner 0 finsi
+        retourner compte(n - 1) + 1
+    fin
+    ";
+
+    fn code(result: Result<(), RuntimeError>) -> Option<&'static str> {
+        match result {
+            Ok(()) => None,
+            Err(RuntimeError::Error { message, .. }) => Some(message.code),
+            Err(other) => panic!("erreur inattendue : {:?}", other),
+        }
+    }
+
+    #[test]
+    fn call_depth_limit_is_exact() {
+        let mut interpreter = InterpretVisitor::new();
+        interpreter.set_max_depth(10);
+        // compte(9) imbrique dix appels.
+        assert_eq!(code(run_with(&mut interpreter, &format!("{COUNT}ecrire compte(9)")).1), None);
+        assert_eq!(code(run_with(&mut interpreter, "ecrire compte(10)").1), Some("E0318"));
+    }
+
+    #[test]
+    fn native_stack_is_checked_before_the_depth_limit() {
+        let mut interpreter = InterpretVisitor::new();
+        interpreter.set_max_depth(usize::MAX);
+        interpreter.set_stack_budget(256 * 1024);
+        assert_eq!(code(run_with(&mut interpreter, &format!("{COUNT}ecrire compte(10)")).1), None);
+        assert_eq!(code(run_with(&mut interpreter, "ecrire compte(1000000)").1), Some("E0322"));
+        // La saisie suivante repart avec toute sa pile.
+        assert_eq!(code(run_with(&mut interpreter, "ecrire compte(10)").1), None);
+    }
 }
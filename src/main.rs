@@ -1,12 +1,15 @@
 use std::env;
 use std::fs;
 use std::io::{self, Write};
+use std::thread;
 
+mod budget;
 mod error;
 mod expr;
 mod interpret_visitor;
 mod parser;
 mod print_visitor;
+mod optimizer_visitor;
 mod scanner;
 mod token;
 mod value;
@@ -16,115 +19,911 @@ mod environment;
 mod callable;
 mod builtin;
 mod resolver_visitor;
+mod options;
+mod span;
+mod diagnostic;
+mod suggest;
+mod hint;
+mod catalog;
+mod vm;
+mod explanations;
+mod gc;
+mod symbol;
 
-/// Exécute un fichier .fr donné en argument
-fn run_file(path: &str) -> Result<(), String> {
-    if !path.ends_with(".fr") {
-        return Err(format!("Le fichier doit avoir l'extension .fr : {}", path));
+use catalog::{Language, Message};
+use diagnostic::{Diagnostic, Emitter, Severity};
+use options::{Command, Engine, Options};
+use span::{FileId, SourceMap};
+use stmt::Stmt;
+use token::TokenType;
+
+/// État d'exécution conservé entre les saisies du REPL. L'interpréteur
+/// d'arbre reçoit aussi les résolutions du `ResolverVisitor`.
+struct Engines {
+    interpreter: interpret_visitor::InterpretVisitor,
+    vm: vm::Vm,
+    /// Premier identifiant de nœud libre pour la prochaine saisie.
+    next_node_id: expr::NodeId,
+}
+
+impl Engines {
+    fn new(options: &Options) -> Self {
+        let mut interpreter = interpret_visitor::InterpretVisitor::new();
+        interpreter.set_max_depth(options.depth_limit());
+        interpreter.set_limits(options.limits);
+        // La moitié de `BASE_STACK` reste libre pour l'analyse qui précède
+        // l'exécution et pour les expressions du dernier appel accepté.
+        interpreter.set_stack_budget(stack_size(options) - BASE_STACK / 2);
+        let mut vm = vm::Vm::new();
+        vm.set_max_depth(options.depth_limit());
+        vm.set_limits(options.limits);
+        Engines {
+            interpreter,
+            vm,
+            next_node_id: 0,
+        }
+    }
+}
+
+/// Pile réservée pour chaque appel imbriqué de l'interpréteur d'arbre,
+/// environ trois fois ce qu'il consomme ; une compilation sans optimisation
+/// en demande bien plus.
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) { 32 * 1024 } else { 8 * 1024 };
+/// Pile réservée en plus des appels : analyse, expressions imbriquées.
+const BASE_STACK: usize = 64 * 1024 * 1024;
+
+/// Pile maximale du fil d'exécution : au-delà, le système peut refuser de
+/// la réserver.
+const MAX_STACK: usize = 1024 * 1024 * 1024;
+
+/// Taille de la pile du fil d'exécution. La récursion de l'interpréteur
+/// d'arbre suit celle du programme : sa pile doit pouvoir contenir
+/// `--profondeur-max` appels imbriqués. Une limite trop grande pour
+/// `MAX_STACK` est laissée à la vérification de pile de l'interpréteur
+/// (E0322).
+fn stack_size(options: &Options) -> usize {
+    options
+        .depth_limit()
+        .saturating_mul(STACK_PER_CALL)
+        .saturating_add(BASE_STACK)
+        .min(MAX_STACK)
+}
+
+/// Échec d'une commande ; `main` le traduit en statut de sortie.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Failure {
+    /// Erreur de lecture, d'analyse ou d'exécution.
+    Error,
+    /// `--etapes-max` a arrêté l'exécution (E0319).
+    Steps,
+    /// `--temps-max` a arrêté l'exécution (E0320).
+    Time,
+    /// `--memoire-max` a arrêté l'exécution (E0321).
+    Memory,
+}
+
+impl Failure {
+    fn of(error: &error::RuntimeError) -> Failure {
+        match error {
+            error::RuntimeError::Error { message, .. } => match message.code {
+                "E0319" => Failure::Steps,
+                "E0320" => Failure::Time,
+                "E0321" => Failure::Memory,
+                _ => Failure::Error,
+            },
+            _ => Failure::Error,
+        }
     }
 
-    let contenu = fs::read_to_string(path)
-        .map_err(|e| format!("Impossible de lire '{}': {}", path, e))?;
+    /// Statut de sortie, décrit dans l'aide de la ligne de commande.
+    fn status(self) -> i32 {
+        match self {
+            Failure::Error => 1,
+            Failure::Steps => 2,
+            Failure::Time => 3,
+            Failure::Memory => 4,
+        }
+    }
+}
 
-    let mut interpreter = interpret_visitor::InterpretVisitor::new();
+/// Les étapes qui précèdent l'exécution échouent toujours avec le statut 1.
+impl From<()> for Failure {
+    fn from(_: ()) -> Failure {
+        Failure::Error
+    }
+}
 
-    let mut lexer = scanner::Lexer::new(&contenu);
+/// Affiche les diagnostics d'une phase.
+fn report(diagnostics: &[Diagnostic], sources: &SourceMap, emitter: &Emitter) {
+    for diagnostic in diagnostics {
+        emitter.emit(diagnostic, sources);
+    }
+}
+
+/// Analyse une source déjà enregistrée dans `sources` : lexique, syntaxe puis
+/// résolution, pour le moteur `engine`. Les diagnostics de chaque phase sont
+/// affichés au fur et à mesure ; `Err` indique qu'au moins une erreur a été
+/// signalée.
+fn analyze(
+    engines: &mut Engines,
+    sources: &SourceMap,
+    file: FileId,
+    engine: Engine,
+    options: &Options,
+    emitter: &Emitter,
+) -> Result<Vec<Stmt>, ()> {
+    let report = |diagnostics: &[Diagnostic]| report(diagnostics, sources, emitter);
+    let source = sources.get(file).unwrap();
+
+    let mut lexer = scanner::Lexer::new(&source.text);
+    lexer.set_file(file);
+    if source.name.ends_with(".fri") {
+        lexer.set_indentation(true);
+    }
+    lexer.set_decimal_comma(options.decimal_comma);
     lexer.scan_tokens();
+    if !lexer.errors.is_empty() {
+        let diagnostics: Vec<Diagnostic> = lexer.errors.iter().map(|e| e.to_diagnostic()).collect();
+        report(&diagnostics);
+        return Err(());
+    }
 
     let mut parser = parser::Parser::new(lexer.tokens);
-    let expr = parser.parse();
+    parser.set_first_id(engines.next_node_id);
+    let statements = parser.parse();
+    engines.next_node_id = parser.next_id();
+    let statements = match statements {
+        Ok(statements) => statements,
+        Err(errors) => {
+            let diagnostics: Vec<Diagnostic> = errors
+                .iter()
+                .map(|e| match hint::for_parse_error(e, parser.tokens()) {
+                    Some(hint) => hint.attach(e.to_diagnostic()),
+                    None => e.to_diagnostic(),
+                })
+                .collect();
+            report(&diagnostics);
+            return Err(());
+        }
+    };
 
-    {
-        let mut resolver = resolver_visitor::ResolverVisitor::new(&mut interpreter);
-        resolver.resolve(&expr);
+    let mut resolver = resolver_visitor::ResolverVisitor::new(&mut engines.interpreter);
+    if engine == Engine::Vm {
+        resolver.declare_globals(engines.vm.globals());
     }
+    // Dans le REPL, une fonction peut être appelée par une saisie suivante.
+    resolver.set_check_unused_globals(options.script.is_some());
+    resolver.resolve(&statements);
 
-    match interpreter.interpret(&expr) {
+    let mut diagnostics = resolver.errors;
+    diagnostics.extend(
+        resolver
+            .warnings
+            .into_iter()
+            .filter(|warning| !lexer.suppressions.covers(warning))
+            .map(|mut warning| {
+                if options.warnings_as_errors {
+                    warning.severity = Severity::Error;
+                }
+                warning
+            }),
+    );
+    diagnostics.sort_by_key(|diagnostic| diagnostic.labels.first().map(|label| label.span.start));
+    report(&diagnostics);
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(());
+    }
+    if !options.optimize {
+        return Ok(statements);
+    }
+
+    // Les blocs fusionnés déplacent des variables : l'arbre optimisé est
+    // résolu à nouveau. Ses diagnostics ont déjà été signalés sur l'original.
+    let statements = optimizer_visitor::OptimizerVisitor::optimize(&statements);
+    let mut resolver = resolver_visitor::ResolverVisitor::new(&mut engines.interpreter);
+    if engine == Engine::Vm {
+        resolver.declare_globals(engines.vm.globals());
+    }
+    resolver.resolve(&statements);
+    Ok(statements)
+}
+
+/// Analyse et exécute une source déjà enregistrée dans `sources`.
+fn run_source(
+    engines: &mut Engines,
+    sources: &SourceMap,
+    file: FileId,
+    options: &Options,
+    emitter: &Emitter,
+) -> Result<(), Failure> {
+    let report = |diagnostics: &[Diagnostic]| report(diagnostics, sources, emitter);
+    let statements = analyze(engines, sources, file, options.engine, options, emitter)?;
+    if options.dump_ast {
+        print!("{}", print_visitor::PrintVisitor::print(&statements));
+        return Ok(());
+    }
+
+    let result = match options.engine {
+        Engine::Tree => engines.interpreter.interpret(&statements),
+        Engine::Vm => match vm::Compiler::compile(&statements) {
+            Ok(script) => engines.vm.interpret(script),
+            Err(diagnostic) => {
+                report(&[*diagnostic]);
+                return Err(Failure::Error);
+            }
+        },
+    };
+    finish(result, sources, emitter)
+}
+
+/// Affiche le résultat d'une exécution, ou l'erreur qui l'a interrompue.
+fn finish(
+    result: Result<impl std::fmt::Debug, error::RuntimeError>,
+    sources: &SourceMap,
+    emitter: &Emitter,
+) -> Result<(), Failure> {
+    match result {
         Ok(value) => {
             println!("{:?}", value);
             Ok(())
         }
-        Err(e) => Err(e.to_string()),
+        Err(e) => {
+            report(&[e.to_diagnostic()], sources, emitter);
+            Err(Failure::of(&e))
+        }
     }
 }
 
-fn run_repl() {
-    let mut interpreter = interpret_visitor::InterpretVisitor::new();
-    
+/// Erreur de la ligne de commande, sans source associée.
+fn fail<T>(message: Message, emitter: &Emitter) -> Result<T, ()> {
+    emitter.emit(&Diagnostic::error(message), &SourceMap::new());
+    Err(())
+}
+
+fn is_source(path: &str) -> bool {
+    path.ends_with(".fr") || path.ends_with(".fri")
+}
+
+/// Lit un script .fr ou .fri et l'enregistre dans `sources`.
+fn load_source(path: &str, sources: &mut SourceMap, emitter: &Emitter) -> Result<FileId, ()> {
+    match fs::read_to_string(path) {
+        Ok(contenu) => Ok(sources.add(path, &contenu)),
+        Err(e) => fail(Message::new("E0402", &[&path, &e]), emitter),
+    }
+}
+
+/// Relit un fichier .frc ; le source embarqué est enregistré dans `sources`
+/// pour les diagnostics.
+fn load_compiled(path: &str, sources: &mut SourceMap, emitter: &Emitter) -> Result<vm::Program, ()> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return fail(Message::new("E0402", &[&path, &e]), emitter),
+    };
+    // `sources` est neuf : le source embarqué sera son premier fichier.
+    let program = match vm::read(&bytes, path, 0) {
+        Ok(program) => program,
+        Err(message) => return fail(message, emitter),
+    };
+    sources.add(&program.source_name, &program.source_text);
+    Ok(program)
+}
+
+/// Analyse et compile un script en bytecode.
+fn compile_source(path: &str, options: &Options, emitter: &Emitter) -> Result<(SourceMap, vm::Program), ()> {
+    let mut sources = SourceMap::new();
+    let file = load_source(path, &mut sources, emitter)?;
+    let statements = analyze(&mut Engines::new(options), &sources, file, Engine::Vm, options, emitter)?;
+    let script = match vm::Compiler::compile(&statements) {
+        Ok(script) => script,
+        Err(diagnostic) => {
+            report(&[*diagnostic], &sources, emitter);
+            return Err(());
+        }
+    };
+    let source = sources.get(file).unwrap();
+    let program = vm::Program {
+        source_name: source.name.clone(),
+        source_text: source.text.clone(),
+        script,
+    };
+    Ok((sources, program))
+}
+
+/// Exécute un fichier .fr (ou .fri, en mode indentation) donné en argument,
+/// ou un fichier .frc déjà compilé, toujours sur la machine virtuelle.
+fn run_file(path: &str, options: &Options, emitter: &Emitter) -> Result<(), Failure> {
+    let mut sources = SourceMap::new();
+
+    if path.ends_with(".frc") {
+        let program = load_compiled(path, &mut sources, emitter)?;
+        let result = Engines::new(options).vm.interpret(program.script);
+        return finish(result, &sources, emitter);
+    }
+    if !is_source(path) {
+        return fail(Message::new("E0401", &[&path]), emitter).map_err(Failure::from);
+    }
+
+    let file = load_source(path, &mut sources, emitter)?;
+    run_source(&mut Engines::new(options), &sources, file, options, emitter)
+}
+
+/// `frenchy compiler <script> [-o <fichier.frc>]`. Par défaut, le fichier
+/// produit porte le nom du script avec l'extension .frc.
+fn compile_file(path: &str, output: Option<&str>, options: &Options, emitter: &Emitter) -> Result<(), ()> {
+    if !is_source(path) {
+        return fail(Message::new("E0408", &[&"compiler"]), emitter);
+    }
+    let (_, program) = compile_source(path, options, emitter)?;
+
+    let output = match output {
+        Some(output) => output.to_string(),
+        None => std::path::Path::new(path)
+            .with_extension("frc")
+            .to_string_lossy()
+            .into_owned(),
+    };
+    match fs::write(&output, vm::write(&program)) {
+        Ok(()) => Ok(()),
+        Err(e) => fail(Message::new("E0409", &[&output, &e]), emitter),
+    }
+}
+
+/// `frenchy desassembler <fichier.frc|script>` : un script est d'abord
+/// compilé.
+fn disassemble_file(path: &str, options: &Options, emitter: &Emitter) -> Result<(), ()> {
+    let (sources, program) = if path.ends_with(".frc") {
+        let mut sources = SourceMap::new();
+        let program = load_compiled(path, &mut sources, emitter)?;
+        (sources, program)
+    } else if is_source(path) {
+        compile_source(path, options, emitter)?
+    } else {
+        return fail(Message::new("E0408", &[&"desassembler"]), emitter);
+    };
+    print!("{}", vm::disassemble(&program.script, sources.get(0)));
+    Ok(())
+}
+
+/// Indique si la saisie du REPL forme une suite d'instructions complète :
+/// tous les blocs et parenthèses sont refermés et la dernière ligne ne se
+/// termine pas par un opérateur.
+fn saisie_complete(input: &str) -> bool {
+    let mut lexer = scanner::Lexer::new(input);
+    lexer.scan_tokens();
+
+    let mut profondeur: i32 = 0;
+    let mut parentheses: i32 = 0;
+    let mut precedent: Option<&token::TokenType> = None;
+    for token in &lexer.tokens {
+        match token.token_type {
+            TokenType::FUN | TokenType::LEFT_BRACE => profondeur += 1,
+            TokenType::END | TokenType::RIGHT_BRACE | TokenType::ENDIF => profondeur -= 1,
+            TokenType::THEN if precedent != Some(&TokenType::ELSE) => profondeur += 1,
+            TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET => parentheses += 1,
+            TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACKET => parentheses -= 1,
+            _ => {}
+        }
+        if token.token_type != TokenType::EOF {
+            precedent = Some(&token.token_type);
+        }
+    }
+
+    let continuation = matches!(
+        precedent,
+        Some(
+            TokenType::PLUS
+                | TokenType::MINUS
+                | TokenType::STAR
+                | TokenType::SLASH
+                | TokenType::MODULO
+                | TokenType::COMMA
+                | TokenType::ASSIGN
+                | TokenType::AND
+                | TokenType::OR
+                | TokenType::BANG_EQUAL
+                | TokenType::EQUAL_EQUAL
+                | TokenType::GREATER
+                | TokenType::GREATER_EQUAL
+                | TokenType::LESS
+                | TokenType::LESS_EQUAL
+        )
+    );
+
+    profondeur <= 0 && parentheses <= 0 && !continuation
+}
+
+fn run_repl(options: &Options, emitter: &Emitter) {
+    let mut engines = Engines::new(options);
+    let mut sources = SourceMap::new();
+    let mut saisies = 0;
 
     loop {
         let mut input = String::new();
         let mut first_line = true;
-        let mut in_a_function = false;
 
         loop {
             if first_line {
                 print!("> ");
                 first_line = false;
-            } else if in_a_function {
-                print!(".... ");
             } else {
                 print!(".. ");
             }
-            io::stdout().flush().unwrap();
+            let _ = io::stdout().flush();
 
             let mut line = String::new();
-            if io::stdin().read_line(&mut line).is_err() {
-                println!("Erreur de lecture. Fin du REPL.");
-                return;
+            match io::stdin().read_line(&mut line) {
+                Ok(0) => return,
+                Ok(_) => {}
+                Err(_) => {
+                    println!("{}", catalog::text("cli.lecture", &[]));
+                    return;
+                }
             }
 
             input.push_str(&line);
 
-            let trimmed = line.trim_end();
-            if trimmed.ends_with("debut") {
-                in_a_function = true;
-            }
-            if trimmed.ends_with("fin") {
-                in_a_function = false;
-                break;
-            }
-            if trimmed.ends_with(';') && !in_a_function {
+            if saisie_complete(&input) {
                 break;
             }
         }
 
-        // Lexing
-        let mut lexer = scanner::Lexer::new(&input);
-        lexer.scan_tokens();
-        // Parsing
-        let mut parser = parser::Parser::new(lexer.tokens);
-        let expr = parser.parse();
-        // Résolution
-        {
-            let mut resolver = resolver_visitor::ResolverVisitor::new(&mut interpreter);
-            resolver.resolve(&expr);
+        // Chaque saisie est un fichier source distinct pour les diagnostics.
+        saisies += 1;
+        let file = sources.add(&catalog::text("cli.saisie", &[&saisies]), &input);
+        let _ = run_source(&mut engines, &sources, file, options, emitter);
+    }
+}
+
+/// `frenchy expliquer [<code>]` : explication d'un code, ou liste des codes
+/// avec leur message court.
+fn explain(code: Option<&str>, emitter: &Emitter) -> Result<(), ()> {
+    let Some(code) = code else {
+        println!("{}", catalog::text("cli.codes", &[]));
+        for code in catalog::codes() {
+            println!("  {}  {}", code, catalog::text(code, &[&"…", &"…", &"…"]));
         }
-        // Interprétation
-        match interpreter.interpret(&expr) {
-            Ok(value) => println!("{:?}", value),
-            Err(e) => println!("Erreur: {}", e),
+        return Ok(());
+    };
+
+    let code = code.to_uppercase();
+    match explanations::explanation(&code, catalog::language()) {
+        Some(text) => {
+            println!("{}\n\n{}", code, text);
+            Ok(())
+        }
+        None => {
+            emitter.emit(&Diagnostic::error(Message::new("E0405", &[&code])), &SourceMap::new());
+            Err(())
         }
     }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    catalog::set_language(options::language(&args[1..]).unwrap_or_else(Language::from_env));
+
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            let emitter = Emitter::new(Default::default(), Default::default());
+            emitter.emit(&Diagnostic::error(message), &SourceMap::new());
+            eprintln!(
+                "{}",
+                catalog::text("cli.usage", &[&args[0], &args[0], &args[0], &args[0]])
+            );
+            std::process::exit(1);
+        }
+    };
+    let stack_size = stack_size(&options);
+    let worker = thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(move || execute(&options));
+    let result = match worker {
+        Ok(worker) => worker.join().unwrap_or(Err(Failure::Error)),
+        Err(e) => {
+            let emitter = Emitter::new(Default::default(), Default::default());
+            emitter.emit(&Diagnostic::error(Message::new("E0412", &[&stack_size, &e])), &SourceMap::new());
+            Err(Failure::Error)
+        }
+    };
+    if let Err(failure) = result {
+        std::process::exit(failure.status());
+    }
+}
+
+/// Exécute la commande demandée.
+fn execute(options: &Options) -> Result<(), Failure> {
+    let emitter = Emitter::new(options.error_format, options.color);
+
+    if let Command::Explain(code) = &options.command {
+        return explain(code.as_deref(), &emitter).map_err(Failure::from);
+    }
+
+    let result = match (&options.command, &options.script) {
+        (Command::Compile { output }, Some(script)) => {
+            compile_file(script, output.as_deref(), options, &emitter)
+        }
+        (Command::Disassemble, Some(script)) => disassemble_file(script, options, &emitter),
+        (Command::Compile { .. }, None) => fail(Message::new("E0408", &[&"compiler"]), &emitter),
+        (Command::Disassemble, None) => fail(Message::new("E0408", &[&"desassembler"]), &emitter),
+        // Seule l'exécution distingue les arrêts dus aux limites.
+        (_, Some(script)) => return run_file(script, options, &emitter),
+        (_, None) => {
+            run_repl(options, &emitter);
+            Ok(())
+        }
+    };
+    result.map_err(Failure::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    match args.len() {
-        1 => {
-            run_repl();
+    /// Options de la ligne de commande `args test.fr`.
+    fn options(args: &[&str]) -> Options {
+        let args: Vec<String> = args.iter().chain(&["test.fr"]).map(|arg| arg.to_string()).collect();
+        Options::parse(&args).unwrap()
+    }
+
+    /// Analyse puis exécute `source` comme `run_source` ; renvoie ce qu'a
+    /// écrit le programme et le résultat.
+    fn run_file_source(source: &str, options: &Options) -> (String, Result<(), Failure>) {
+        let emitter = Emitter::new(options.error_format, options.color);
+        let mut engines = Engines::new(options);
+        let output = match options.engine {
+            Engine::Tree => engines.interpreter.capture_output(),
+            Engine::Vm => engines.vm.capture_output(),
+        };
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.fr", source);
+        let result = run_source(&mut engines, &sources, file, options, &emitter);
+        let output = output.take();
+        (output, result)
+    }
+
+    /// Exécute les saisies l'une après l'autre sur les mêmes moteurs, comme
+    /// le REPL ; renvoie ce qu'elles ont écrit.
+    fn run_inputs(inputs: &[&str], options: &Options) -> String {
+        let emitter = Emitter::new(options.error_format, options.color);
+        let mut engines = Engines::new(options);
+        let output = match options.engine {
+            Engine::Tree => engines.interpreter.capture_output(),
+            Engine::Vm => engines.vm.capture_output(),
+        };
+        let mut sources = SourceMap::new();
+        for (index, input) in inputs.iter().enumerate() {
+            let file = sources.add(&format!("saisie {}", index + 1), input);
+            run_source(&mut engines, &sources, file, options, &emitter).unwrap();
         }
-        2 => {
-            let script = &args[1];
-            if let Err(err_msg) = run_file(script) {
-                eprintln!("Erreur : {}", err_msg);
-                std::process::exit(1);
+        output.take()
+    }
+
+    /// Exécute `source` sur `engine` avec au plus 100 appels imbriqués ;
+    /// renvoie ce qu'a écrit le programme, suivi de l'erreur qui l'a
+    /// éventuellement interrompu, avec sa pile d'appels.
+    fn run(source: &str, engine: Engine) -> String {
+        let options = options(&["--profondeur-max=100"]);
+        let emitter = Emitter::new(options.error_format, options.color);
+        let mut engines = Engines::new(&options);
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.fr", source);
+        let statements = analyze(&mut engines, &sources, file, engine, &options, &emitter).unwrap();
+        let (output, result) = match engine {
+            Engine::Tree => {
+                let output = engines.interpreter.capture_output();
+                (output, engines.interpreter.interpret(&statements))
+            }
+            Engine::Vm => {
+                let output = engines.vm.capture_output();
+                let script = vm::Compiler::compile(&statements).unwrap();
+                (output, engines.vm.interpret(script))
             }
+        };
+        let mut text = output.borrow().clone();
+        if let Err(error) = result {
+            text.push_str(&format!("{:?}", error.to_diagnostic()));
         }
-        _ => {
-            eprintln!("Usage : {} [<script.fr>]", args[0]);
-            std::process::exit(1);
+        text
+    }
+
+    /// Appels en cours quand `source` s'arrête sur une erreur, sous la forme
+    /// `fonction(arguments)@ligne`.
+    fn trace(source: &str, engine: Engine) -> Vec<String> {
+        let options = options(&[]);
+        let emitter = Emitter::new(options.error_format, options.color);
+        let mut engines = Engines::new(&options);
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.fr", source);
+        let statements = analyze(&mut engines, &sources, file, engine, &options, &emitter).unwrap();
+        let result = match engine {
+            Engine::Tree => engines.interpreter.interpret(&statements),
+            Engine::Vm => engines.vm.interpret(vm::Compiler::compile(&statements).unwrap()),
+        };
+        let diagnostic = result.unwrap_err().to_diagnostic();
+        diagnostic
+            .trace
+            .iter()
+            .map(|frame| format!("{}({})@{}", frame.function, frame.arguments, frame.call_site.line))
+            .collect()
+    }
+
+    fn assert_same_output(source: &str, expected: &str) {
+        let tree = run(source, Engine::Tree);
+        assert!(tree.starts_with(expected), "sortie inattendue :\n{}", tree);
+        assert_eq!(tree, run(source, Engine::Vm));
+    }
+
+    #[test]
+    fn closures() {
+        assert_same_output(
+            "fonction compteur() debut
+                variable n <- 0
+                fonction suivant() debut
+                    n <- n + 1
+                    retourner n
+                fin
+                retourner suivant
+            fin
+            variable c <- compteur()
+            variable d <- compteur()
+            ecrire c()
+            ecrire c()
+            ecrire d()
+            ecrire c()",
+            "1\n2\n1\n3\n",
+        );
+    }
+
+    #[test]
+    fn control_flow_and_operators() {
+        assert_same_output(
+            "fonction bruyant(valeur) debut
+                ecrire \"evalue\"
+                retourner valeur
+            fin
+            variable i <- 0
+            tantque i < 3 faire i <- i + 1
+            ecrire i
+            si i >= 3 et !(i == 4) alors ecrire \"trois\" sinon alors ecrire \"autre\" finsi
+            ecrire faux et bruyant(vrai)
+            ecrire 1 et bruyant(2)
+            ecrire \"a\" + \"b\"
+            ecrire -(2 * 3) % 4",
+            "3\ntrois\nfalse\nevalue\n2\nab\n-2\n",
+        );
+    }
+
+    #[test]
+    fn same_name_twice_on_a_line() {
+        assert_same_output(
+            "variable x <- 1
+            fonction f(x) debut retourner x * 10 fin
+            fonction g() debut
+                variable x <- 2
+                x <- x + f(x)
+                retourner x
+            fin
+            ecrire g()
+            x <- x + f(x)
+            ecrire x",
+            "22\n11\n",
+        );
+    }
+
+    #[test]
+    fn later_inputs_see_earlier_definitions() {
+        let inputs = [
+            "variable x <- 1\nfonction f(x) debut retourner x * 10 fin",
+            "x <- x + f(x)\nfonction g(y) debut retourner f(y) + x fin",
+            "ecrire g(x)",
+        ];
+        for engine in ["--moteur=arbre", "--moteur=vm"] {
+            assert_eq!(run_inputs(&inputs, &options(&[engine])), "121\n");
+        }
+    }
+
+    #[test]
+    fn tail_calls() {
+        assert_same_output(
+            "fonction somme(n, total) debut
+                si n == 0 alors retourner total finsi
+                retourner somme(n - 1, total + n)
+            fin
+            ecrire somme(1000, 0)",
+            "500500\n",
+        );
+    }
+
+    #[test]
+    fn tail_calls_between_functions() {
+        assert_same_output(
+            "fonction pair(n) debut
+                si n == 0 alors retourner vrai finsi
+                retourner impair(n - 1)
+            fin
+            fonction impair(n) debut
+                si n == 0 alors retourner faux finsi
+                retourner pair(n - 1)
+            fin
+            fonction echange(a, b, n) debut
+                si n == 0 alors retourner a - b finsi
+                retourner echange(b, a, n - 1)
+            fin
+            ecrire pair(10001)
+            ecrire echange(1, 2, 1001)",
+            "false\n1\n",
+        );
+    }
+
+    #[test]
+    fn calls_that_are_not_tail_calls_use_the_stack() {
+        let source = "fonction compte(n) debut
+                si n == 0 alors retourner 0 finsi
+                retourner compte(n - 1) + 1
+            fin
+            ecrire compte(50)
+            ecrire compte(1000)";
+        assert_same_output(source, "50\nDiagnostic { severity: Error, code: Some(\"E0318\")");
+    }
+
+    #[test]
+    fn stack_grows_with_the_depth_limit_up_to_a_cap() {
+        let small = stack_size(&options(&["--profondeur-max=100"]));
+        let default = stack_size(&options(&[]));
+        assert!(BASE_STACK < small && small < default && default < MAX_STACK);
+        assert_eq!(stack_size(&options(&["--profondeur-max=100000000"])), MAX_STACK);
+    }
+
+    #[test]
+    fn depth_limit_is_the_same_in_both_engines() {
+        let source = "fonction compte(n) debut
+                si n == 0 alors retourner 0 finsi
+                retourner compte(n - 1) + 1
+            fin
+            ecrire compte(9)
+            ecrire compte(10)";
+        for engine in ["--moteur=arbre", "--moteur=vm"] {
+            let (output, result) = run_file_source(source, &options(&["--profondeur-max=10", engine]));
+            assert_eq!((output.as_str(), result), ("9\n", Err(Failure::Error)), "{engine}");
+        }
+    }
+
+    #[test]
+    fn limits_stop_the_run_with_their_own_status() {
+        let endless = "variable i <- 0\ntantque vrai faire i <- i + 1";
+        let growing = "variable t <- \"x\"\ntantque vrai faire t <- t + t";
+        let cases = [
+            (endless, "--etapes-max=1000", Failure::Steps, 2),
+            (endless, "--temps-max=0.05", Failure::Time, 3),
+            (growing, "--memoire-max=1", Failure::Memory, 4),
+        ];
+        for (source, limit, failure, status) in cases {
+            for engine in ["--moteur=arbre", "--moteur=vm"] {
+                let (_, result) = run_file_source(source, &options(&[limit, engine]));
+                assert_eq!(result, Err(failure), "{limit} {engine}");
+                assert_eq!(failure.status(), status);
+            }
+        }
+        assert_eq!(Failure::Error.status(), 1);
+        assert_eq!(run_file_source("ecrire 1 / 0", &options(&["--etapes-max=1000"])).1, Err(Failure::Error));
+    }
+
+    #[test]
+    fn limits_leave_short_programs_alone() {
+        let options = options(&["--etapes-max=1000", "--temps-max=10", "--memoire-max=64"]);
+        assert_eq!(run_file_source("ecrire \"a\" + \"b\"", &options), ("ab\n".to_string(), Ok(())));
+    }
+
+    #[test]
+    fn gc_frees_cycles_left_by_local_functions() {
+        assert_same_output(
+            "fonction cree() debut
+                fonction boucle(n) debut
+                    si n == 0 alors retourner 0 finsi
+                    retourner boucle(n - 1)
+                fin
+                retourner boucle(3)
+            fin
+            variable i <- 0
+            tantque i < 100 faire {
+                cree()
+                i <- i + 1
+            }
+            ecrire gc() >= 100
+            ecrire gc()",
+            "true\n0\n",
+        );
+    }
+
+    #[test]
+    fn optimized_programs_behave_the_same() {
+        let source = "fonction f(a) debut
+                si vrai alors { variable b <- a * (2 + 3); ecrire b } finsi
+                retourner a + 1
+                ecrire \"jamais\"
+            fin
+            ecrire f(1) + 2 * 3
+            si 1 > 2 alors ecrire \"non\" sinon alors ecrire \"oui\" finsi
+            ecrire 10 / (4 - 4)";
+        for engine in ["--moteur=arbre", "--moteur=vm"] {
+            let expected = run_file_source(source, &options(&[engine]));
+            assert_eq!(expected, ("5\n8\noui\n".to_string(), Err(Failure::Error)));
+            assert_eq!(run_file_source(source, &options(&["-O", engine])), expected, "{engine}");
+        }
+    }
+
+    #[test]
+    fn error_traces() {
+        assert_same_output(
+            "fonction interieur(x) debut
+                retourner 1 + x()
+            fin
+            fonction exterieur(y) debut
+                retourner interieur(y) + 1
+            fin
+            ecrire \"avant\"
+            ecrire exterieur(2)",
+            "avant\nDiagnostic",
+        );
+        assert_same_output(
+            "fonction descendre(n) debut
+                retourner descendre(n + 1) + 1
+            fin
+            ecrire descendre(0)",
+            "Diagnostic",
+        );
+    }
+
+    #[test]
+    fn traces_list_calls_most_recent_first() {
+        let source = "fonction interieur(x, texte) debut
+                retourner x / 0
+            fin
+            fonction exterieur(y) debut
+                retourner interieur(y + 1, \"a\") + 1
+            fin
+            ecrire exterieur(2)";
+        for engine in [Engine::Tree, Engine::Vm] {
+            assert_eq!(trace(source, engine), ["interieur(3, \"a\")@5", "exterieur(2)@7"]);
+        }
+        assert_eq!(trace("ecrire 1 / 0", Engine::Tree), Vec::<String>::new());
+    }
+
+    #[test]
+    fn static_errors_prevent_execution() {
+        for engine in ["--moteur=arbre", "--moteur=vm"] {
+            let (output, result) = run_file_source("ecrire 1\necrire inconnu", &options(&[engine]));
+            assert_eq!(output, "");
+            assert_eq!(result, Err(Failure::Error));
+        }
+    }
+
+    #[test]
+    fn warnings_as_errors_prevent_execution() {
+        let source = "si vrai alors ecrire 2 finsi";
+        assert_eq!(run_file_source(source, &options(&[])), ("2\n".to_string(), Ok(())));
+        assert_eq!(
+            run_file_source(source, &options(&["--warnings-as-errors"])),
+            (String::new(), Err(Failure::Error))
+        );
+    }
+
+    #[test]
+    fn ignored_warnings_are_not_errors() {
+        let options = options(&["--warnings-as-errors"]);
+        for source in [
+            "si vrai alors ecrire 2 finsi // frenchy:ignorer condition-constante",
+            "si vrai alors ecrire 2 finsi // frenchy:ignorer W0005",
+            "// frenchy:ignorer\nsi vrai alors ecrire 2 finsi",
+        ] {
+            assert_eq!(run_file_source(source, &options), ("2\n".to_string(), Ok(())), "{source}");
+        }
+        for source in [
+            "si vrai alors ecrire 2 finsi // frenchy:ignorer masquage",
+            "si vrai alors ecrire 2 finsi\n// frenchy:ignorer",
+        ] {
+            assert_eq!(run_file_source(source, &options).1, Err(Failure::Error), "{source}");
         }
     }
 }
@@ -0,0 +1,486 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::{
+    expr::{
+        AssignVarExpr, BinaryExpr, CallExp, Expr, GroupingExpr, Literal, LiteralExpr, LogicalExpr,
+        UnaryExpr, VariableExpr,
+    },
+    interpret_visitor::InterpretVisitor,
+    span::Span,
+    stmt::{
+        BlockStmt, ExpressionStmt, FunctionStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarStmt,
+        WhileStmt,
+    },
+    symbol::Symbol,
+    token::{Token, TokenType},
+    value::Value,
+    visitor::{ExprVisitor, StmtVisitor},
+};
+
+/// Réécrit l'arbre déjà résolu (`-O`) : calcule les expressions constantes,
+/// garde la seule branche exécutée d'un `si` constant, retire le code qui suit
+/// un `retourner` et fusionne les blocs imbriqués inutiles.
+///
+/// Les résultats sont ceux de l'interpréteur, erreurs comprises : une
+/// opération qui échouerait, comme une division par zéro, est laissée telle
+/// quelle pour échouer à l'exécution.
+pub struct OptimizerVisitor;
+
+impl OptimizerVisitor {
+    pub fn optimize(statements: &[Stmt]) -> Vec<Stmt> {
+        OptimizerVisitor.statements(statements, &[], false)
+    }
+
+    /// Optimise les instructions d'une même portée. `reserved` contient les
+    /// noms qui y sont déjà déclarés (paramètres d'une fonction) ; `local` est
+    /// faux au niveau supérieur, où une déclaration crée une globale.
+    fn statements(&mut self, statements: &[Stmt], reserved: &[Symbol], local: bool) -> Vec<Stmt> {
+        let mut optimized = Vec::new();
+        for stmt in statements {
+            optimized.extend(stmt.accept(self));
+            if optimized.last().is_some_and(returns) {
+                break;
+            }
+        }
+
+        let mentions: Vec<HashSet<Symbol>> = optimized.iter().map(mentioned).collect();
+        let mut flattened = Vec::with_capacity(optimized.len());
+        for (index, stmt) in optimized.into_iter().enumerate() {
+            match stmt {
+                Stmt::Block(block) if can_flatten(&block, index, &mentions, reserved, local) => {
+                    flattened.extend(block.statements);
+                }
+                stmt => flattened.push(stmt),
+            }
+        }
+        flattened
+    }
+
+    /// Instruction placée sous un `si` ou un `tantque` : `None` si elle a
+    /// disparu.
+    fn branch(&mut self, stmt: &Stmt) -> Option<Stmt> {
+        let mut optimized = stmt.accept(self);
+        match optimized.len() {
+            0 => None,
+            1 => optimized.pop(),
+            _ => Some(Stmt::Block(BlockStmt {
+                statements: optimized,
+                span: stmt.span(),
+            })),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Expr {
+        expr.accept(self)
+    }
+}
+
+/// Vrai si l'instruction se termine toujours par un `retourner` : ce qui la
+/// suit ne sera jamais exécuté.
+fn returns(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) => true,
+        Stmt::Block(block) => block.statements.last().is_some_and(returns),
+        _ => false,
+    }
+}
+
+/// Valeur d'une expression réduite à un littéral.
+fn constant(expr: &Expr) -> Option<Value> {
+    let Expr::LiteralExpr(literal) = expr else {
+        return None;
+    };
+    match &literal.value {
+        Literal::Number(token) => token
+            .literal
+            .as_deref()
+            .unwrap_or(&token.lexeme)
+            .parse::<f64>()
+            .ok()
+            .map(Value::Number),
+        Literal::String(_, text) => Some(Value::String(Rc::clone(text))),
+        Literal::Boolean(token) => Some(Value::Boolean(token.token_type == TokenType::TRUE)),
+        Literal::Nil => Some(Value::Nil),
+    }
+}
+
+/// Littéral produisant `value`, à l'emplacement de l'expression calculée.
+fn literal(value: Value, span: Span) -> Option<Expr> {
+    let token = |token_type, lexeme: String, literal: String| {
+        Token::new(token_type, lexeme, literal, span.line, span)
+    };
+    let value = match value {
+        Value::Number(number) => {
+            Literal::Number(token(TokenType::NUMBER, number.to_string(), number.to_string()))
+        }
+        Value::String(text) => Literal::String(
+            token(TokenType::STRING, text.to_string(), text.to_string()),
+            text,
+        ),
+        Value::Boolean(true) => Literal::Boolean(token(TokenType::TRUE, "vrai".into(), String::new())),
+        Value::Boolean(false) => Literal::Boolean(token(TokenType::FALSE, "faux".into(), String::new())),
+        Value::Nil => Literal::Nil,
+        _ => return None,
+    };
+    Some(Expr::LiteralExpr(LiteralExpr { value, span }))
+}
+
+/// Un bloc imbriqué peut rejoindre la portée qui le contient s'il ne déclare
+/// rien, ou, hors du niveau supérieur, si ses déclarations ne croisent aucun
+/// nom utilisé ailleurs dans cette portée.
+fn can_flatten(
+    block: &BlockStmt,
+    index: usize,
+    mentions: &[HashSet<Symbol>],
+    reserved: &[Symbol],
+    local: bool,
+) -> bool {
+    let declared: Vec<Symbol> = block
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Var(var) => Some(var.name.symbol),
+            Stmt::Function(function) => Some(function.name.symbol),
+            _ => None,
+        })
+        .collect();
+    if declared.is_empty() {
+        return true;
+    }
+    local
+        && declared.iter().all(|name| {
+            !reserved.contains(name)
+                && mentions
+                    .iter()
+                    .enumerate()
+                    .all(|(other, names)| other == index || !names.contains(name))
+        })
+}
+
+/// Noms lus, affectés ou déclarés par une instruction, à toute profondeur.
+fn mentioned(stmt: &Stmt) -> HashSet<Symbol> {
+    let mut names = HashSet::new();
+    mention_stmt(stmt, &mut names);
+    names
+}
+
+fn mention_stmt(stmt: &Stmt, names: &mut HashSet<Symbol>) {
+    match stmt {
+        Stmt::Expression(stmt) => mention_expr(&stmt.expression, names),
+        Stmt::Print(stmt) => mention_expr(&stmt.expression, names),
+        Stmt::Var(stmt) => {
+            names.insert(stmt.name.symbol);
+            if let Some(initializer) = &stmt.initializer {
+                mention_expr(initializer, names);
+            }
+        }
+        Stmt::Block(stmt) => stmt.statements.iter().for_each(|stmt| mention_stmt(stmt, names)),
+        Stmt::If(stmt) => {
+            mention_expr(&stmt.condition, names);
+            mention_stmt(&stmt.then_branch, names);
+            if let Some(else_branch) = &stmt.else_branch {
+                mention_stmt(else_branch, names);
+            }
+        }
+        Stmt::While(stmt) => {
+            mention_expr(&stmt.condition, names);
+            mention_stmt(&stmt.body, names);
+        }
+        Stmt::Function(stmt) => {
+            names.insert(stmt.name.symbol);
+            names.extend(stmt.params.iter().map(|param| param.symbol));
+            stmt.body.iter().for_each(|stmt| mention_stmt(stmt, names));
+        }
+        Stmt::Return(stmt) => {
+            if let Some(value) = &stmt.value {
+                mention_expr(value, names);
+            }
+        }
+    }
+}
+
+fn mention_expr(expr: &Expr, names: &mut HashSet<Symbol>) {
+    match expr {
+        Expr::BinaryExpr(expr) => {
+            mention_expr(&expr.left, names);
+            mention_expr(&expr.right, names);
+        }
+        Expr::LogicalExpr(expr) => {
+            mention_expr(&expr.left, names);
+            mention_expr(&expr.right, names);
+        }
+        Expr::GroupingExpr(expr) => mention_expr(&expr.expression, names),
+        Expr::UnaryExpr(expr) => mention_expr(&expr.right, names),
+        Expr::LiteralExpr(_) => {}
+        Expr::VariableExpr(expr) => {
+            names.insert(expr.name.symbol);
+        }
+        Expr::AssignVarExpr(expr) => {
+            names.insert(expr.name.symbol);
+            mention_expr(&expr.value, names);
+        }
+        Expr::CallExpr(expr) => {
+            mention_expr(&expr.callee, names);
+            expr.arguments.iter().for_each(|argument| mention_expr(argument, names));
+        }
+    }
+}
+
+impl ExprVisitor for OptimizerVisitor {
+    type Output = Expr;
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Expr {
+        let left = self.expr(&expr.left);
+        let right = self.expr(&expr.right);
+        if let (Some(a), Some(b)) = (constant(&left), constant(&right)) {
+            if let Some(folded) = InterpretVisitor::binary(&expr.operator.lexeme, a, b)
+                .ok()
+                .and_then(|value| literal(value, expr.span))
+            {
+                return folded;
+            }
+        }
+        Expr::BinaryExpr(BinaryExpr {
+            left: Box::new(left),
+            right: Box::new(right),
+            ..expr.clone()
+        })
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Expr {
+        let inner = self.expr(&expr.expression);
+        if let Some(value) = constant(&inner) {
+            if let Some(folded) = literal(value, expr.span) {
+                return folded;
+            }
+        }
+        Expr::GroupingExpr(GroupingExpr {
+            expression: Box::new(inner),
+            span: expr.span,
+        })
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Expr {
+        Expr::LiteralExpr(expr.clone())
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Expr {
+        let right = self.expr(&expr.right);
+        let operand = match constant(&right) {
+            Some(Value::Number(number)) => Some(number),
+            Some(Value::Boolean(boolean)) => Some(boolean as i32 as f64),
+            _ => None,
+        };
+        let folded = match (expr.operator.lexeme.as_str(), operand) {
+            ("-", Some(operand)) => literal(Value::Number(-operand), expr.span),
+            ("!", Some(operand)) => {
+                literal(Value::Boolean(!InterpretVisitor::make_bool_value(operand)), expr.span)
+            }
+            _ => None,
+        };
+        folded.unwrap_or_else(|| {
+            Expr::UnaryExpr(UnaryExpr {
+                right: Box::new(right),
+                ..expr.clone()
+            })
+        })
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> Expr {
+        Expr::VariableExpr(expr.clone())
+    }
+
+    fn visit_assign_var_expr(&mut self, expr: &AssignVarExpr) -> Expr {
+        Expr::AssignVarExpr(AssignVarExpr {
+            value: Box::new(self.expr(&expr.value)),
+            ..expr.clone()
+        })
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Expr {
+        let left = self.expr(&expr.left);
+        // Même évaluation que l'interpréteur : une partie gauche fausse est
+        // le résultat, sinon c'est la partie droite.
+        if let Some(value) = constant(&left) {
+            return if InterpretVisitor::is_truthy(&value) {
+                self.expr(&expr.right)
+            } else {
+                left
+            };
+        }
+        Expr::LogicalExpr(LogicalExpr {
+            left: Box::new(left),
+            right: Box::new(self.expr(&expr.right)),
+            ..expr.clone()
+        })
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExp) -> Expr {
+        Expr::CallExpr(CallExp {
+            callee: Box::new(self.expr(&expr.callee)),
+            arguments: expr.arguments.iter().map(|argument| self.expr(argument)).collect(),
+            ..expr.clone()
+        })
+    }
+}
+
+impl StmtVisitor for OptimizerVisitor {
+    type Output = Vec<Stmt>;
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> Vec<Stmt> {
+        vec![Stmt::Expression(ExpressionStmt {
+            expression: self.expr(&stmt.expression),
+            span: stmt.span,
+        })]
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Vec<Stmt> {
+        vec![Stmt::Print(PrintStmt {
+            expression: self.expr(&stmt.expression),
+            span: stmt.span,
+        })]
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Vec<Stmt> {
+        vec![Stmt::Var(VarStmt {
+            initializer: stmt.initializer.as_ref().map(|initializer| self.expr(initializer)),
+            ..stmt.clone()
+        })]
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Vec<Stmt> {
+        vec![Stmt::Block(BlockStmt {
+            statements: self.statements(&stmt.statements, &[], true),
+            span: stmt.span,
+        })]
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Vec<Stmt> {
+        let condition = self.expr(&stmt.condition);
+        if let Some(value) = constant(&condition) {
+            let taken = if InterpretVisitor::is_truthy(&value) {
+                Some(&stmt.then_branch)
+            } else {
+                stmt.else_branch.as_ref()
+            };
+            return taken.map(|branch| branch.accept(self)).unwrap_or_default();
+        }
+        let then_branch = self.branch(&stmt.then_branch).unwrap_or_else(|| {
+            Stmt::Block(BlockStmt {
+                statements: Vec::new(),
+                span: stmt.then_branch.span(),
+            })
+        });
+        vec![Stmt::If(IfStmt {
+            condition,
+            then_branch: Box::new(then_branch),
+            else_branch: stmt.else_branch.as_ref().and_then(|branch| self.branch(branch)).map(Box::new),
+            span: stmt.span,
+        })]
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Vec<Stmt> {
+        let condition = self.expr(&stmt.condition);
+        if matches!(constant(&condition), Some(value) if !InterpretVisitor::is_truthy(&value)) {
+            return Vec::new();
+        }
+        let body = self.branch(&stmt.body).unwrap_or_else(|| {
+            Stmt::Block(BlockStmt {
+                statements: Vec::new(),
+                span: stmt.body.span(),
+            })
+        });
+        vec![Stmt::While(WhileStmt {
+            condition,
+            body: Box::new(body),
+            span: stmt.span,
+        })]
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) -> Vec<Stmt> {
+        let params: Vec<Symbol> = stmt.params.iter().map(|param| param.symbol).collect();
+        vec![Stmt::Function(Rc::new(FunctionStmt {
+            body: self.statements(&stmt.body, &params, true),
+            ..(**stmt).clone()
+        }))]
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Vec<Stmt> {
+        vec![Stmt::Return(ReturnStmt {
+            value: stmt.value.as_ref().map(|value| self.expr(value)),
+            ..stmt.clone()
+        })]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, print_visitor::PrintVisitor, scanner::Lexer};
+
+    /// Arbre optimisé de `source`, affiché.
+    fn optimize(source: &str) -> String {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        let statements = Parser::new(lexer.tokens).parse().unwrap();
+        PrintVisitor::print(&OptimizerVisitor::optimize(&statements))
+    }
+
+    #[test]
+    fn constant_expressions_are_computed() {
+        assert_eq!(
+            optimize("ecrire 1 + 2 * 3\necrire \"a\" + \"b\"\necrire -(2 - 5) < 4\necrire x + 1 * 2"),
+            "ecrire 7\necrire \"ab\"\necrire vrai\necrire (+ x 2)\n"
+        );
+        assert_eq!(optimize("ecrire faux et x\necrire 3 et x"), "ecrire faux\necrire x\n");
+    }
+
+    #[test]
+    fn failing_operations_are_left_for_run_time() {
+        assert_eq!(
+            optimize("ecrire 1 / 0\necrire \"a\" - 1"),
+            "ecrire (/ 1 0)\necrire (- \"a\" 1)\n"
+        );
+    }
+
+    #[test]
+    fn constant_conditions_keep_the_branch_taken() {
+        assert_eq!(
+            optimize(
+                "si vrai alors ecrire 1 sinon alors ecrire 2 finsi\nsi 1 == 2 alors ecrire 3 finsi\n\
+                 tantque faux faire ecrire 4\nsi x alors si faux alors ecrire 5 finsi finsi"
+            ),
+            "ecrire 1\nsi x\n  bloc\n"
+        );
+    }
+
+    #[test]
+    fn code_after_return_is_dropped() {
+        assert_eq!(
+            optimize("fonction f(a) debut\nretourner a\necrire a\nfin"),
+            "fonction f(a)\n  retourner a\n"
+        );
+        assert_eq!(
+            optimize("fonction f(a) debut\n{\nretourner a\n}\necrire a\nfin"),
+            "fonction f(a)\n  retourner a\n"
+        );
+    }
+
+    #[test]
+    fn nested_blocks_are_flattened() {
+        // Le dernier bloc redéclare le paramètre : il garde sa portée.
+        assert_eq!(
+            optimize(
+                "fonction f(a) debut\n{\necrire a\n{\nvariable b <- a\necrire b\n}\n}\n\
+                 {\nvariable a <- 1\necrire a\n}\nfin"
+            ),
+            "fonction f(a)\n  ecrire a\n  variable b <- a\n  ecrire b\n  bloc\n    variable a <- 1\n    ecrire a\n"
+        );
+        // Au niveau supérieur, une déclaration deviendrait une globale.
+        assert_eq!(
+            optimize("{\nvariable b <- 1\necrire b\n}\n{\necrire 2\n}"),
+            "bloc\n  variable b <- 1\n  ecrire b\necrire 2\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use crate::budget::{Limits, MEGABYTE};
+use crate::catalog::{Language, Message};
+use crate::diagnostic::{Color, Format};
+
+/// Action demandée sur la ligne de commande.
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    /// Exécute le script, ou lance le REPL.
+    #[default]
+    Run,
+    /// `expliquer [<code>]` : explication détaillée d'un code d'erreur, ou
+    /// liste des codes.
+    Explain(Option<String>),
+    /// `compiler <script> [-o <fichier.frc>]` : écrit le programme compilé
+    /// sans l'exécuter.
+    Compile { output: Option<String> },
+    /// `desassembler <fichier.frc|script>` : affiche le listing du bytecode.
+    Disassemble,
+}
+
+/// Moteur d'exécution choisi par `--moteur=arbre|vm`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Engine {
+    /// Interpréteur parcourant l'arbre syntaxique.
+    #[default]
+    Tree,
+    /// Compilation en bytecode puis exécution par la machine virtuelle.
+    Vm,
+}
+
+/// Nombre d'appels imbriqués autorisés sans `--profondeur-max`.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// Options de la ligne de commande.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub command: Command,
+    /// Script à exécuter, compiler ou désassembler ; le REPL est lancé s'il
+    /// est absent.
+    pub script: Option<String>,
+    /// `--virgule-decimale` : accepte `3,14` comme littéral numérique.
+    pub decimal_comma: bool,
+    /// `--format-erreurs=texte|json` : présentation des diagnostics.
+    pub error_format: Format,
+    /// `--couleur=auto|toujours|jamais` : couleurs dans les diagnostics texte.
+    pub color: Color,
+    /// `--warnings-as-errors` : un avertissement empêche l'exécution.
+    pub warnings_as_errors: bool,
+    pub engine: Engine,
+    /// `--profondeur-max=<n>` : nombre maximal d'appels de fonction
+    /// imbriqués.
+    pub max_depth: Option<usize>,
+    /// `--etapes-max=<n>`, `--temps-max=<secondes>` et `--memoire-max=<Mo>` :
+    /// au-delà, l'exécution est interrompue.
+    pub limits: Limits,
+    /// `-O` : optimise l'arbre syntaxique avant l'exécution.
+    pub optimize: bool,
+    /// `--dump-ast-optimise` : affiche l'arbre optimisé au lieu de
+    /// l'exécuter.
+    pub dump_ast: bool,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, Message> {
+        let mut options = Options::default();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--virgule-decimale" => options.decimal_comma = true,
+                "--format-erreurs=texte" => options.error_format = Format::Text,
+                "--format-erreurs=json" => options.error_format = Format::Json,
+                "--couleur=auto" => options.color = Color::Auto,
+                "--couleur=toujours" => options.color = Color::Always,
+                "--couleur=jamais" => options.color = Color::Never,
+                "--warnings-as-errors" => options.warnings_as_errors = true,
+                "--moteur=arbre" => options.engine = Engine::Tree,
+                "--moteur=vm" => options.engine = Engine::Vm,
+                "-O" => options.optimize = true,
+                "--dump-ast-optimise" => {
+                    options.optimize = true;
+                    options.dump_ast = true;
+                }
+                flag if flag.starts_with("--profondeur-max=") => {
+                    let value = &flag["--profondeur-max=".len()..];
+                    match value.parse::<usize>() {
+                        Ok(depth) if depth > 0 => options.max_depth = Some(depth),
+                        _ => return Err(Message::new("E0411", &[&value])),
+                    }
+                }
+                flag if flag.starts_with("--etapes-max=") => {
+                    let value = &flag["--etapes-max=".len()..];
+                    match value.parse::<u64>() {
+                        Ok(steps) if steps > 0 => options.limits.steps = Some(steps),
+                        _ => return Err(Message::new("E0413", &[&value, &"--etapes-max"])),
+                    }
+                }
+                flag if flag.starts_with("--temps-max=") => {
+                    let value = &flag["--temps-max=".len()..];
+                    match value.parse::<f64>().ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok()) {
+                        Some(time) if !time.is_zero() => options.limits.time = Some(time),
+                        _ => return Err(Message::new("E0413", &[&value, &"--temps-max"])),
+                    }
+                }
+                flag if flag.starts_with("--memoire-max=") => {
+                    let value = &flag["--memoire-max=".len()..];
+                    match value.parse::<usize>().ok().and_then(|mb| mb.checked_mul(MEGABYTE)) {
+                        Some(memory) if memory > 0 => options.limits.memory = Some(memory),
+                        _ => return Err(Message::new("E0413", &[&value, &"--memoire-max"])),
+                    }
+                }
+                // Déjà lue par `language` avant l'analyse des options.
+                flag if language_flag(flag).is_some() => {}
+                flag if flag.starts_with("--") => {
+                    return Err(Message::new("E0403", &[&flag]));
+                }
+                "expliquer" if options.command == Command::Run && options.script.is_none() => {
+                    options.command = Command::Explain(None);
+                }
+                "compiler" if options.command == Command::Run && options.script.is_none() => {
+                    options.command = Command::Compile { output: None };
+                }
+                "desassembler" if options.command == Command::Run && options.script.is_none() => {
+                    options.command = Command::Disassemble;
+                }
+                "-o" if matches!(options.command, Command::Compile { .. }) => {
+                    let output = args.next().ok_or_else(|| Message::new("E0410", &[]))?;
+                    options.command = Command::Compile {
+                        output: Some(output.clone()),
+                    };
+                }
+                code if options.command == Command::Explain(None) => {
+                    options.command = Command::Explain(Some(code.to_string()));
+                }
+                script => {
+                    if options.script.is_some() || matches!(options.command, Command::Explain(_)) {
+                        return Err(Message::new("E0404", &[&script]));
+                    }
+                    options.script = Some(script.to_string());
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn depth_limit(&self) -> usize {
+        self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+}
+
+/// Langue demandée par `--langue=fr|en`, à lire avant le reste des options
+/// pour que leurs erreurs soient déjà traduites.
+pub fn language(args: &[String]) -> Option<Language> {
+    args.iter().rev().find_map(|arg| language_flag(arg))
+}
+
+fn language_flag(arg: &str) -> Option<Language> {
+    arg.strip_prefix("--langue=").and_then(Language::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Options::parse(&args).map_err(|message| message.text)
+    }
+
+    #[test]
+    fn limits() {
+        let options = parse(&["--etapes-max=500", "--temps-max=1.5", "--memoire-max=64", "a.fr"]).unwrap();
+        assert_eq!(
+            options.limits,
+            Limits {
+                steps: Some(500),
+                time: Some(Duration::from_millis(1500)),
+                memory: Some(64 * MEGABYTE),
+            }
+        );
+        assert_eq!(parse(&["a.fr"]).unwrap().limits, Limits::default());
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        for (flag, value) in [
+            ("--etapes-max", "0"),
+            ("--etapes-max", "-3"),
+            ("--etapes-max", "beaucoup"),
+            ("--temps-max", "0"),
+            ("--temps-max", "-1"),
+            ("--temps-max", "inf"),
+            ("--memoire-max", "0"),
+            ("--memoire-max", "1.5"),
+        ] {
+            assert_eq!(
+                parse(&[&format!("{flag}={value}"), "a.fr"]).err(),
+                Some(format!("valeur invalide '{value}' pour {flag}"))
+            );
+        }
+    }
+}
@@ -1,20 +1,79 @@
-use core::panic;
+use std::rc::Rc;
 
 use crate::{
+    catalog::Message,
     error::ParserError,
-    expr::{BinaryExpr, Expr, GroupingExpr, Literal, LiteralExpr, UnaryExpr, VariableExpr, LogicalExpr, CallExp},
+    span::Span,
+    suggest,
+    expr::{BinaryExpr, Expr, GroupingExpr, Literal, LiteralExpr, NodeId, UnaryExpr, VariableExpr, LogicalExpr, CallExp},
     stmt::{BlockStmt, ExpressionStmt, PrintStmt, Stmt, VarStmt, IfStmt, WhileStmt, FunctionStmt, ReturnStmt},
     token::{Token, TokenType},
 };
 
+type ParseResult<T> = Result<T, ParserError>;
+
+/// Profondeur maximale d'une expression. La résolution, l'optimisation, la
+/// compilation et l'évaluation parcourent l'arbre récursivement : au-delà,
+/// elles risqueraient d'épuiser la pile.
+const MAX_EXPRESSION_DEPTH: usize = 2000;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<ParserError>,
+    /// Identifiant du prochain nœud qui nomme une variable.
+    next_id: NodeId,
+    /// Profondeur, dans l'arbre, de l'expression en cours d'analyse.
+    depth: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            next_id: 0,
+            depth: 0,
+        }
+    }
+
+    /// Premier identifiant de nœud à attribuer. Dans le REPL, chaque saisie
+    /// reprend où la précédente s'est arrêtée : les fonctions déjà définies
+    /// gardent leurs identifiants.
+    pub fn set_first_id(&mut self, id: NodeId) {
+        self.next_id = id;
+    }
+
+    pub fn next_id(&self) -> NodeId {
+        self.next_id
+    }
+
+    fn node_id(&mut self) -> NodeId {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// Descend d'un niveau dans l'expression en cours ; l'appelant rétablit
+    /// la profondeur une fois le sous-arbre construit.
+    fn nest(&mut self) -> ParseResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(ParserError {
+                token: Box::new(self.peek().clone()),
+                message: Message::new("E0123", &[&MAX_EXPRESSION_DEPTH]),
+                help: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn error(&self, token: &Token, code: &'static str) -> ParserError {
+        ParserError {
+            token: Box::new(token.clone()),
+            message: Message::new(code, &[]),
+            help: None,
+        }
     }
 
     fn match_tokens(&mut self, types: Vec<TokenType>) -> bool {
@@ -27,10 +86,10 @@ impl Parser {
         false
     }
 
-    fn match_tokens_na(&mut self, types: Vec<TokenType>) -> bool {
+    fn match_tokens_na(&self, types: Vec<TokenType>) -> bool {
         for token_type in types.iter() {
             if self.check(token_type) {
-                
+
                 return true;
             }
         }
@@ -45,14 +104,11 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, ParserError> {
+    fn consume(&mut self, token_type: TokenType, code: &'static str) -> ParseResult<&Token> {
         if self.check(&token_type) {
             Ok(self.advance())
         } else {
-            Err(ParserError {
-                token: self.peek().clone(),
-                message: message.to_string(),
-            })
+            Err(self.error(self.peek(), code))
         }
     }
     fn check(&self, token_type: &TokenType) -> bool {
@@ -78,31 +134,150 @@ impl Parser {
         self.peek().token_type == TokenType::EOF
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    /// Span allant de `start` au dernier token consommé.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(&self.previous().span)
+    }
+
+    fn nil_literal(span: Span) -> Expr {
+        Expr::LiteralExpr(LiteralExpr {
+            value: Literal::Nil,
+            span,
+        })
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.check(&TokenType::NEWLINE) {
+            self.advance();
+        }
+    }
+
+    /// Entre deux instructions, un `;` isolé est une instruction vide : il
+    /// suit souvent un mot-clé de fin de bloc (`finsi;`, `fin;`).
+    fn skip_separators(&mut self) {
+        while self.match_tokens(vec![TokenType::NEWLINE, TokenType::SEMICOLON]) {}
+    }
+
+    /// Regarde le prochain token en ignorant les fins de ligne, sans les consommer.
+    fn check_after_newlines(&self, token_type: &TokenType) -> bool {
+        self.tokens[self.current..]
+            .iter()
+            .find(|token| token.token_type != TokenType::NEWLINE)
+            .is_some_and(|token| &token.token_type == token_type)
+    }
+
+    /// Vrai si l'instruction courante est syntaxiquement terminée : `;`, fin de
+    /// ligne, ou mot-clé fermant le bloc englobant.
+    fn at_terminator(&self) -> bool {
+        self.is_at_end()
+            || self.at_block_end()
+            || self.match_tokens_na(vec![TokenType::SEMICOLON, TokenType::NEWLINE])
+    }
+
+    /// Mots-clés qui ferment un bloc. La resynchronisation s'arrête devant eux
+    /// sans les consommer : c'est le bloc englobant qui s'en charge.
+    fn at_block_end(&self) -> bool {
+        self.match_tokens_na(vec![
+            TokenType::END,
+            TokenType::ENDIF,
+            TokenType::ELSE,
+            TokenType::ENDWHILE,
+            TokenType::RIGHT_BRACE,
+            TokenType::DEDENT,
+        ])
+    }
+
+    fn consume_terminator(&mut self, code: &'static str) -> ParseResult<()> {
+        if self.match_tokens(vec![TokenType::SEMICOLON, TokenType::NEWLINE]) || self.at_terminator() {
+            Ok(())
+        } else {
+            Err(self.error(self.peek(), code))
+        }
+    }
+
+    /// Après une erreur, saute les tokens jusqu'au début probable de
+    /// l'instruction suivante : après un `;` ou une fin de ligne, ou devant un
+    /// mot-clé d'instruction ou de fin de bloc (`fin`, `finsi`, ...).
+    fn synchronize(&mut self) {
+        while !self.is_at_end() {
+            if self.match_tokens(vec![TokenType::SEMICOLON, TokenType::NEWLINE]) {
+                return;
+            }
+            if self.at_block_end()
+                || self.match_tokens_na(vec![
+                    TokenType::VAR,
+                    TokenType::FUN,
+                    TokenType::IF,
+                    TokenType::WHILE,
+                    TokenType::FOR,
+                    TokenType::RETURN,
+                    TokenType::PRINT,
+                ])
+            {
+                return;
+            }
+            self.advance();
+        }
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Analyse tout le programme. Les erreurs de syntaxe sont toutes
+    /// renvoyées ensemble.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
         let mut statements = Vec::new();
+        self.skip_separators();
         while !self.is_at_end() {
-            statements.push(self.declaration());
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+            self.skip_separators();
+        }
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
-        return statements;
     }
 
-    fn statement(&mut self) -> Stmt {
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        self.skip_newlines();
         match self.peek().token_type {
             TokenType::PRINT => {
                 self.advance();
                 return self.print_statement();
             }
             TokenType::LEFT_BRACE => {
-                self.advance();
-                return Stmt::Block(BlockStmt {
-                    statements: self.brace_block(),
-                });
+                let start = self.advance().span;
+                let statements = self.brace_block()?;
+                return Ok(Stmt::Block(BlockStmt {
+                    statements,
+                    span: self.span_from(start),
+                }));
             }
             TokenType::THEN => {
-                self.advance();
-                return Stmt::Block(BlockStmt {
-                    statements: self.if_block(),
-                });
+                let start = self.advance().span;
+                let statements = if self.check_after_newlines(&TokenType::INDENT) {
+                    self.skip_newlines();
+                    self.advance();
+                    self.indented_block()?
+                } else {
+                    self.if_block()?
+                };
+                return Ok(Stmt::Block(BlockStmt {
+                    statements,
+                    span: self.span_from(start),
+                }));
+            }
+            TokenType::INDENT => {
+                let start = self.advance().span;
+                let statements = self.indented_block()?;
+                return Ok(Stmt::Block(BlockStmt {
+                    statements,
+                    span: self.span_from(start),
+                }));
             }
             TokenType::IF => {
                 self.advance();
@@ -128,210 +303,228 @@ impl Parser {
     }
 
 
-    fn return_statement(&mut self) -> Stmt {
+    fn return_statement(&mut self) -> ParseResult<Stmt> {
         let keyword = self.previous().clone();
-        let value = if !self.check(&TokenType::SEMICOLON) {
-            self.expression()
+        let value = if !self.at_terminator() {
+            self.expression()?
         } else {
-            Expr::LiteralExpr(LiteralExpr {
-                value: Literal::Nil,
-            })
+            Parser::nil_literal(keyword.span)
         };
-        self.consume(TokenType::SEMICOLON, "Expect ';' after return value.")
-            .unwrap();
-        Stmt::Return(ReturnStmt { keyword, value:Some(value) })
+        let span = self.span_from(keyword.span);
+        self.consume_terminator("E0101")?;
+        let tail_call = matches!(value, Expr::CallExpr(_));
+        Ok(Stmt::Return(ReturnStmt { keyword, value:Some(value), span, tail_call }))
     }
 
-    fn for_statement(&mut self) -> Stmt{
+    fn for_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span;
+        self.consume(TokenType::LEFT_PAREN,"E0102")?;
 
-       // println!("parse for");
-        self.consume(TokenType::LEFT_PAREN,"Expect '(' after for.").unwrap();
-
-        let init;
-
-        match self.peek().token_type{
+        let init = match self.peek().token_type{
             TokenType::SEMICOLON => {
-                init = None;
+                self.advance();
+                None
             }
             TokenType::VAR => {
                 self.advance();
-                init = Some(self.var_declaration());
+                Some(self.var_declaration()?)
             }
 
             _ => {
-                init = Some(self.expression_statement())
-            }   
+                Some(self.expression_statement()?)
+            }
         };
         let mut condition = None;
 
         if !self.check(&TokenType::SEMICOLON){
-            condition = Some(self.expression())
+            condition = Some(self.expression()?)
         };
 
-        self.consume(TokenType::SEMICOLON,"Expect ';' after loop condition");
+        self.consume(TokenType::SEMICOLON,"E0103")?;
 
         let mut increment = None;
 
         if !self.check(&TokenType::RIGHT_PAREN){
-            increment = Some(self.expression())
+            increment = Some(self.expression()?)
         };
 
-        self.consume(TokenType::RIGHT_PAREN,"Expect ')' after for clauses");
-
+        self.consume(TokenType::RIGHT_PAREN,"E0104")?;
 
-        
 
+        let mut body = self.statement()?;
+        let span = self.span_from(start);
 
-        let mut body = self.statement();
-
-      
 
         if let Some(increment) = increment {
+            let increment_span = increment.span();
             body = Stmt::Block(BlockStmt{
                 statements: vec![body, Stmt::Expression(ExpressionStmt{
-                    expression: increment
-                })]
+                    expression: increment,
+                    span: increment_span,
+                })],
+                span,
             });
         };
 
         if let Some(condition) = condition {
-            
+
             body = Stmt::While(WhileStmt{
-                condition:condition,
+                condition,
                 body:Box::new(body),
+                span,
             });
         }
 
-        
+
         if let Some(init) = init {
-            
+
             body = Stmt::Block(BlockStmt{
-                statements: vec![init,body]
+                statements: vec![init,body],
+                span,
             })
         }
-        //println!("body : {:?}",body);
-      //  panic!("test");
-        body
-        
+        Ok(body)
+
     }
 
-    fn while_statement(&mut self) -> Stmt {
+    fn while_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span;
        // self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'while'.").unwrap();
-        let condition = self.expression();
+        let condition = self.expression()?;
       //  self.consume(TokenType::RIGHT_PAREN, "Expect ')' after condition.").unwrap();
-        let body = self.statement();
-        Stmt::While(WhileStmt {
+        self.match_token(TokenType::DO);
+        let body = self.statement()?;
+        Ok(Stmt::While(WhileStmt {
             condition,
             body: Box::new(body),
-        })
+            span: self.span_from(start),
+        }))
     }
 
 
-    fn if_statement(&mut self) -> Stmt {
-        
-        let condition = self.expression();        
-        let then_branch = self.statement();
+    fn if_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span;
+        let condition = self.expression()?;
+        let then_branch = self.statement()?;
+        if self.check_after_newlines(&TokenType::ELSE) {
+            self.skip_newlines();
+        }
         let else_branch = if self.match_token(TokenType::ELSE) {
-            Some(Box::new(self.statement()))
+            Some(Box::new(self.statement()?))
         } else {
             None
         };
-        Stmt::If(IfStmt {
+        Ok(Stmt::If(IfStmt {
             condition,
             then_branch: Box::new(then_branch),
             else_branch,
-        })
+            span: self.span_from(start),
+        }))
     }
 
-    fn if_block(&mut self) -> Vec<Stmt> {
+    /// Instructions d'un bloc jusqu'à l'un des mots-clés `closers`, qui n'est
+    /// pas consommé. Une instruction erronée est signalée puis ignorée.
+    fn block_until(&mut self, closers: Vec<TokenType>) -> Vec<Stmt> {
         let mut statements = Vec::new();
-        while !self.match_tokens_na(vec![TokenType::ENDIF , TokenType::ELSE]) && !self.is_at_end() {
-            statements.push(self.declaration());
-        }
-        //self.consume(TokenType::ENDIF, "Expect 'FINSI' after if block.").unwrap();
-
-         match self.peek().token_type {
-            TokenType::ENDIF => {
-                self.consume(TokenType::ENDIF, "Expect 'FINSI' after if block.").unwrap();
-            }
-            TokenType::ELSE => {
-                //self.consume(TokenType::ENDIF, "Expect 'FINSI' after if block.").unwrap();
-            }
-            _ => {
-                println!("{:?}" , self.peek());
-                panic!("expect endif or else");
+        self.skip_separators();
+        while !self.match_tokens_na(closers.clone()) && !self.is_at_end() {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
             }
+            self.skip_separators();
         }
         statements
     }
 
-    fn start_block(&mut self) -> Vec<Stmt> {
-        let mut statements = Vec::new();
-        while !self.match_tokens_na(vec![TokenType::END]) && !self.is_at_end() {
-            statements.push(self.declaration());
+    fn if_block(&mut self) -> ParseResult<Vec<Stmt>> {
+        let statements = self.block_until(vec![TokenType::ENDIF, TokenType::ELSE]);
+        // `sinon` est laissé à if_statement, seul `finsi` est consommé ici.
+        if !self.check(&TokenType::ELSE) {
+            self.consume(TokenType::ENDIF, "E0105")?;
         }
-        self.consume(TokenType::END, "Expect 'FINSI' after if block.").unwrap();
-
-        
-        statements  
+        Ok(statements)
     }
 
-    fn brace_block(&mut self) -> Vec<Stmt> {
-        let mut statements = Vec::new();
-        while !self.check(&TokenType::RIGHT_BRACE) && !self.is_at_end() {
-            statements.push(self.declaration());
-        }
-        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after block.")
-            .unwrap();
-        statements
+    fn start_block(&mut self) -> ParseResult<Vec<Stmt>> {
+        let statements = self.block_until(vec![TokenType::END]);
+        self.consume(TokenType::END, "E0106")?;
+        Ok(statements)
     }
 
-    fn declaration(&mut self) -> Stmt {
-        if self.match_token(TokenType::VAR) {
-            return self.var_declaration();
-        }
+    /// Bloc délimité par l'indentation : l'INDENT a déjà été consommé.
+    fn indented_block(&mut self) -> ParseResult<Vec<Stmt>> {
+        let statements = self.block_until(vec![TokenType::DEDENT]);
+        self.consume(TokenType::DEDENT, "E0107")?;
+        Ok(statements)
+    }
 
+    fn brace_block(&mut self) -> ParseResult<Vec<Stmt>> {
+        let statements = self.block_until(vec![TokenType::RIGHT_BRACE]);
+        self.consume(TokenType::RIGHT_BRACE, "E0108")?;
+        Ok(statements)
+    }
 
-        match self.peek().token_type{
+    /// En cas d'erreur, celle-ci est enregistrée, le parser se resynchronise
+    /// et aucune instruction n'est produite.
+    fn declaration(&mut self) -> Option<Stmt> {
+        self.skip_newlines();
+        // Une erreur a pu interrompre une expression avant qu'elle ne
+        // rétablisse la profondeur.
+        self.depth = 0;
+        let start = self.current;
+        let result = match self.peek().token_type{
             TokenType::VAR => {
                 self.advance();
-                return self.var_declaration();
+                self.var_declaration()
             }
             TokenType::FUN => {
                 self.advance();
-                return self.function("function");
+                self.function()
             }
             _ => {
                 self.statement()
             }
-        }
+        };
 
-       
+        match result {
+            Ok(stmt) => Some(stmt),
+            Err(error) => {
+                // Un mot-clé de fin de bloc où s'est arrêtée la
+                // resynchronisation peut être relu au niveau supérieur : son
+                // erreur n'est signalée qu'une fois.
+                let reported = self
+                    .errors
+                    .last()
+                    .is_some_and(|last| last.token.span == error.token.span);
+                if !reported {
+                    self.errors.push(error);
+                }
+                // Un mot-clé de fin de bloc isolé (`fin` en trop) n'appartient
+                // à aucun bloc : on le saute pour ne pas boucler dessus.
+                if self.current == start {
+                    self.advance();
+                }
+                self.synchronize();
+                None
+            }
+        }
     }
 
-    fn function(&mut self, kind: &str) -> Stmt {
-        let name = match self.consume(TokenType::IDENTIFIER, &format!("Expect {} name.", kind)) {
-            Ok(token) => token.clone(),
-            Err(e) => {
-                println!("{}", e);
-                return Stmt::Expression(ExpressionStmt {
-                    expression: Expr::LiteralExpr(LiteralExpr {
-                        value: Literal::Nil,
-                    }),
-                });
-            }
-        };
-        self.consume(TokenType::LEFT_PAREN, &format!("Expect '(' after {} name.", kind))
-            .unwrap();
+    fn function(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span;
+        let name = self
+            .consume(TokenType::IDENTIFIER, "E0109")?
+            .clone();
+        self.consume(TokenType::LEFT_PAREN, "E0110")?;
         let mut parameters = Vec::new();
         if !self.check(&TokenType::RIGHT_PAREN) {
             loop {
                 if parameters.len() >= 255 {
-                    println!("Cannot have more than 255 parameters.");
+                    let error = self.error(self.peek(), "E0111");
+                    self.errors.push(error);
                 }
                 parameters.push(
-                    self.consume(TokenType::IDENTIFIER, "Expect parameter name.")
-                        .unwrap()
+                    self.consume(TokenType::IDENTIFIER, "E0112")?
                         .clone(),
                 );
                 if !self.match_token(TokenType::COMMA) {
@@ -339,156 +532,162 @@ impl Parser {
                 }
             }
         }
-        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after parameters.")
-            .unwrap();
-        self.consume(
-            TokenType::START,
-            &format!("Expect '{{' before {} body.", kind),
-        )
-        .unwrap();
-        let body = self.start_block();
-        //println!("[*] parsing function {:?}",body);
-        Stmt::Function(FunctionStmt {
+        self.consume(TokenType::RIGHT_PAREN, "E0113")?;
+        self.skip_newlines();
+        let body = if self.match_token(TokenType::INDENT) {
+            self.indented_block()?
+        } else {
+            self.consume(TokenType::START, "E0114")?;
+            self.start_block()?
+        };
+        Ok(Stmt::Function(Rc::new(FunctionStmt {
+            id: self.node_id(),
             name,
             params:parameters,
             body,
-        })
+            span: self.span_from(start),
+        })))
     }
 
-    fn var_declaration(&mut self) -> Stmt {
-        let name = match self.consume(TokenType::IDENTIFIER, "Expect variable name.") {
-            Ok(token) => token.clone(),
-            Err(e) => {
-                println!("{}", e);
-                return Stmt::Expression(ExpressionStmt {
-                    expression: Expr::LiteralExpr(LiteralExpr {
-                        value: Literal::Nil,
-                    }),
-                });
-            }
-        };
+    fn var_declaration(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span;
+        let name = self.consume(TokenType::IDENTIFIER, "E0115")?.clone();
         let initializer = if self.match_token(TokenType::ASSIGN) {
-            Some(self.expression())
+            Some(self.expression()?)
         } else {
             None
         };
-        match self.consume(
-            TokenType::SEMICOLON,
-            "Expect ';' after variable déclaration.",
-        ) {
-            Ok(_) => {}
-            Err(e) => {
-                println!("{}", e);
-                // return Stmt::Expression(ExpressionStmt { expression: Expr::LiteralExpr(LiteralExpr { value: Literal::Nil }) });
-            }
-        };
-        Stmt::Var(VarStmt {
-            name: name,
+        let span = self.span_from(start);
+        self.consume_terminator("E0116")?;
+        Ok(Stmt::Var(VarStmt {
+            id: self.node_id(),
+            name,
             initializer,
-        })
+            span,
+        }))
     }
 
-    fn print_statement(&mut self) -> Stmt {
-        let value = self.expression();
-        match self.consume(TokenType::SEMICOLON, "Expect ';' after value.") {
-            Ok(_) => {}
-            Err(e) => {
-                println!("{}", e);
-                return Stmt::Expression(ExpressionStmt { expression: value });
-            }
-        }
-        Stmt::Print(PrintStmt { expression: value })
+    fn print_statement(&mut self) -> ParseResult<Stmt> {
+        let start = self.previous().span;
+        let value = self.expression()?;
+        let span = self.span_from(start);
+        self.consume_terminator("E0117")?;
+        Ok(Stmt::Print(PrintStmt { expression: value, span }))
     }
 
-    fn expression_statement(&mut self) -> Stmt {
-        let expr = self.expression();
-     /*    match self.consume(TokenType::SEMICOLON, &format!("[stmt] Expect ';' after expression. {:?} ",expr)) {
-            Ok(_) => {}
-            Err(e) => {
-                println!("{}", e);
-                return Stmt::Expression(ExpressionStmt { expression: expr });
+    fn expression_statement(&mut self) -> ParseResult<Stmt> {
+        let expr = self.expression()?;
+        let span = expr.span();
+        if let Err(mut error) = self.consume_terminator("E0118") {
+            // `ecrir x` ou `print x` : un mot-clé mal orthographié est lu
+            // comme un nom suivi d'une expression.
+            if let Expr::VariableExpr(variable) = &expr {
+                error.help = suggest::keyword_help(&variable.name.lexeme);
             }
-        }*/
-        self.consume(TokenType::SEMICOLON, &format!("[stmt] Expect ';' after expression. {:?} ",expr)).unwrap();
+            return Err(error);
+        }
 
-        Stmt::Expression(ExpressionStmt { expression: expr })
+        Ok(Stmt::Expression(ExpressionStmt { expression: expr, span }))
     }
 
-    fn expression(&mut self) -> Expr {
+    fn expression(&mut self) -> ParseResult<Expr> {
         //self.equality()
         self.assignment()
     }
 
 
 
-    fn assignment(&mut self) -> Expr {
-        let expr = self.or();
+    fn assignment(&mut self) -> ParseResult<Expr> {
+        let expr = self.or()?;
         if self.match_token(TokenType::ASSIGN) {
             let equals = self.previous().clone();
-            let value = self.assignment();
+            self.nest()?;
+            let value = self.assignment()?;
+            self.depth -= 1;
             match expr {
                 Expr::VariableExpr(var_expr) => {
-                    return Expr::AssignVarExpr(crate::expr::AssignVarExpr {
+                    let span = var_expr.span.to(&value.span());
+                    return Ok(Expr::AssignVarExpr(crate::expr::AssignVarExpr {
+                        id: var_expr.id,
                         name: var_expr.name,
                         value: Box::new(value),
-                    });
+                        span,
+                    }));
                 }
                 _ => {
-                    println!("Invalid assignment target.");
+                    // Le parser n'est pas perdu : pas besoin de resynchroniser.
+                    let error = self.error(&equals, "E0119");
+                    self.errors.push(error);
                 }
             }
         }
-        expr
+        Ok(expr)
     }
 
-    fn or(&mut self) -> Expr {
-        let mut expr = self.and();
+    fn or(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.and()?;
+        let depth = self.depth;
 
         while self.match_token(TokenType::OR) {
             let operator = self.previous().clone();
-            let right = self.and();
+            self.nest()?;
+            let right = self.and()?;
+            let span = expr.span().to(&right.span());
             expr = Expr::LogicalExpr(LogicalExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
+                span,
             });
         }
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn and(&mut self) -> Expr {
-        let mut expr = self.equality();
+    fn and(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.equality()?;
+        let depth = self.depth;
 
         while self.match_token(TokenType::AND) {
             let operator = self.previous().clone();
-            let right = self.equality();
+            self.nest()?;
+            let right = self.equality()?;
+            let span = expr.span().to(&right.span());
             expr = Expr::LogicalExpr(LogicalExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
+                span,
             });
         }
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    fn equality(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.comparison()?;
+        let depth = self.depth;
 
         while self.match_tokens(vec![TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
             let operator = self.previous().clone();
-            let right = self.comparison();
+            self.nest()?;
+            let right = self.comparison()?;
+            let span = expr.span().to(&right.span());
             expr = Expr::BinaryExpr(BinaryExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
+                span,
             });
         }
 
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn comparison(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.term()?;
+        let depth = self.depth;
 
         while self.match_tokens(vec![
             TokenType::GREATER,
@@ -497,169 +696,365 @@ impl Parser {
             TokenType::LESS_EQUAL,
         ]) {
             let operator = self.previous().clone();
-            let right = self.term();
+            self.nest()?;
+            let right = self.term()?;
+            let span = expr.span().to(&right.span());
             expr = Expr::BinaryExpr(BinaryExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
+                span,
             });
         }
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
+    fn term(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.factor()?;
+        let depth = self.depth;
 
         while self.match_tokens(vec![TokenType::MINUS, TokenType::PLUS]) {
             let operator = self.previous().clone();
-            let right = self.factor();
+            self.nest()?;
+            let right = self.factor()?;
+            let span = expr.span().to(&right.span());
             expr = Expr::BinaryExpr(BinaryExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
+                span,
             });
         }
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.unary()?;
+        let depth = self.depth;
 
         while self.match_tokens(vec![TokenType::SLASH, TokenType::STAR, TokenType::MODULO]) {
             let operator = self.previous().clone();
-            let right = self.unary();
+            self.nest()?;
+            let right = self.unary()?;
+            let span = expr.span().to(&right.span());
             expr = Expr::BinaryExpr(BinaryExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
+                span,
             });
         }
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Expr {
+    fn unary(&mut self) -> ParseResult<Expr> {
         if self.match_tokens(vec![TokenType::BANG, TokenType::MINUS]) {
             let operator = self.previous().clone();
-            let right = self.unary();
-            return Expr::UnaryExpr(UnaryExpr {
+            self.nest()?;
+            let right = self.unary()?;
+            self.depth -= 1;
+            let span = operator.span.to(&right.span());
+            return Ok(Expr::UnaryExpr(UnaryExpr {
                 operator,
                 right: Box::new(right),
-            });
+                span,
+            }));
         }
-        return self.call();
+        self.call()
     }
 
-    fn call(&mut self) -> Expr{
-        let mut expr = self.primary();
+    fn call(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+        let depth = self.depth;
 
         loop{
             if self.match_token(TokenType::LEFT_PAREN){
-                expr = self.finish_call(&expr);
+                self.nest()?;
+                expr = self.finish_call(&expr)?;
             }else{
                 break;
             }
         }
 
-        expr
+        self.depth = depth;
+        Ok(expr)
     }
 
-    fn finish_call(&mut self, expr:&Expr) -> Expr {
+    fn finish_call(&mut self, expr:&Expr) -> ParseResult<Expr> {
         let mut arguments = Vec::new();
 
         if !self.check(&TokenType::RIGHT_PAREN) {
             loop{
-
-                
-
-                arguments.push(self.expression());
+                arguments.push(self.expression()?);
                 if !self.match_token(TokenType::COMMA) {
                     break;
                 }
             }
         }
 
-        let paren = self.consume(TokenType::RIGHT_PAREN, "Expect ')' after arguments.").unwrap();
+        let paren = self.consume(TokenType::RIGHT_PAREN, "E0120")?.span;
 
-        Expr::CallExpr(CallExp{
+        Ok(Expr::CallExpr(CallExp{
+            span: expr.span().to(&paren),
             callee: Box::new(expr.clone()),
-            paren:paren.clone(),
             arguments,
-        })
+        }))
     }
 
-    fn primary(&mut self) -> Expr {
+    fn primary(&mut self) -> ParseResult<Expr> {
         match self.peek() {
             Token {
                 token_type: TokenType::FALSE,
                 ..
             } => {
-                self.advance();
-                return Expr::LiteralExpr(LiteralExpr {
-                    value: Literal::Boolean(self.previous().clone()),
-                });
+                let token = self.advance().clone();
+                Ok(Expr::LiteralExpr(LiteralExpr {
+                    span: token.span,
+                    value: Literal::Boolean(token),
+                }))
             }
             Token {
                 token_type: TokenType::TRUE,
                 ..
             } => {
-                self.advance();
-                return Expr::LiteralExpr(LiteralExpr {
-                    value: Literal::Boolean(self.previous().clone()),
-                });
+                let token = self.advance().clone();
+                Ok(Expr::LiteralExpr(LiteralExpr {
+                    span: token.span,
+                    value: Literal::Boolean(token),
+                }))
             }
             Token {
                 token_type: TokenType::NIL,
                 ..
             } => {
-                self.advance();
-                return Expr::LiteralExpr(LiteralExpr {
-                    value: Literal::Nil,
-                });
+                let span = self.advance().span;
+                Ok(Parser::nil_literal(span))
             }
             Token {
                 token_type: TokenType::NUMBER,
                 ..
             } => {
-                self.advance();
-                return Expr::LiteralExpr(LiteralExpr {
-                    value: Literal::Number(self.previous().clone()),
-                });
+                let token = self.advance().clone();
+                Ok(Expr::LiteralExpr(LiteralExpr {
+                    span: token.span,
+                    value: Literal::Number(token),
+                }))
             }
             Token {
                 token_type: TokenType::STRING,
                 ..
             } => {
-                self.advance();
-                return Expr::LiteralExpr(LiteralExpr {
-                    value: Literal::String(self.previous().clone()),
-                });
+                let token = self.advance().clone();
+                let text = token.lexeme.as_str().into();
+                Ok(Expr::LiteralExpr(LiteralExpr {
+                    span: token.span,
+                    value: Literal::String(token, text),
+                }))
             }
             Token {
                 token_type: TokenType::LEFT_PAREN,
                 ..
             } => {
-                self.advance();
-                let expr = self.expression();
-                self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.")
-                    .unwrap();
-                return Expr::GroupingExpr(GroupingExpr {
+                let start = self.advance().span;
+                self.nest()?;
+                let expr = self.expression()?;
+                self.depth -= 1;
+                self.consume(TokenType::RIGHT_PAREN, "E0121")?;
+                Ok(Expr::GroupingExpr(GroupingExpr {
                     expression: Box::new(expr),
-                });
+                    span: self.span_from(start),
+                }))
             }
             Token {
                 token_type: TokenType::IDENTIFIER,
                 ..
             } => {
-                self.advance();
-                return Expr::VariableExpr(VariableExpr {
-                    name: self.previous().clone(),
-                });
-            }
-            _ => {
-                return Expr::LiteralExpr(LiteralExpr {
-                    value: Literal::Nil,
-                });
+                let name = self.advance().clone();
+                Ok(Expr::VariableExpr(VariableExpr {
+                    id: self.node_id(),
+                    span: name.span,
+                    name,
+                }))
             }
+            token => Err(self.error(token, "E0122")),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::print_visitor::PrintVisitor;
+    use crate::scanner::Lexer;
+
+    /// Arbre syntaxique affiché, ou code et ligne de chaque erreur.
+    fn parse(source: &str) -> Result<String, Vec<(&'static str, u32)>> {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        assert!(lexer.errors.is_empty());
+        Parser::new(lexer.tokens)
+            .parse()
+            .map(|statements| PrintVisitor::print(&statements))
+            .map_err(|errors| errors.iter().map(|error| (error.message.code, error.token.line)).collect())
+    }
+
+    #[test]
+    fn newlines_end_statements() {
+        let expected = "variable a <- 1\necrire a\n";
+        assert_eq!(parse("variable a <- 1\necrire a").as_deref(), Ok(expected));
+        assert_eq!(parse("variable a <- 1;\necrire a;").as_deref(), Ok(expected));
+        assert_eq!(parse("variable a <- 1; ecrire a").as_deref(), Ok(expected));
+    }
+
+    #[test]
+    fn incomplete_lines_continue() {
+        assert_eq!(
+            parse("variable a <- 1 +\n    2\necrire f(a,\n    3)").as_deref(),
+            Ok("variable a <- (+ 1 2)\necrire (appel f a 3)\n")
+        );
+        assert_eq!(parse("ecrire (1\n    + 2)").as_deref(), Ok("ecrire (groupe (+ 1 2))\n"));
+    }
+
+    #[test]
+    fn indentation_delimits_blocks() {
+        let indented = "fonction f(a)
+    si a > 1 alors
+        retourner a
+    sinon
+        retourner 0
+tantque a < 2 faire
+    a <- a + 1
+";
+        let delimited = "fonction f(a) debut
+    si a > 1 alors
+        retourner a
+    sinon alors
+        retourner 0
+    finsi
+fin
+tantque a < 2 faire {
+    a <- a + 1
+}
+";
+        let mut lexer = Lexer::new(indented);
+        lexer.set_indentation(true);
+        lexer.scan_tokens();
+        let statements = Parser::new(lexer.tokens).parse().unwrap();
+        assert_eq!(Ok(PrintVisitor::print(&statements)), parse(delimited));
+    }
+
+    fn statements(source: &str) -> Vec<Stmt> {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        Parser::new(lexer.tokens).parse().unwrap()
+    }
+
+    /// Texte couvert par `span` dans `source`.
+    fn text(source: &str, span: Span) -> &str {
+        &source[span.start..span.end]
+    }
+
+    #[test]
+    fn spans_cover_whole_nodes() {
+        let source = "ecrire -(1 + été) * f(2, 3)\nsi x alors\n    ecrire x\nfinsi";
+        let statements = statements(source);
+        let Stmt::Print(print) = &statements[0] else { panic!() };
+        assert_eq!(text(source, print.span), "ecrire -(1 + été) * f(2, 3)");
+        assert_eq!(text(source, print.expression.span()), "-(1 + été) * f(2, 3)");
+        let Expr::BinaryExpr(product) = &print.expression else { panic!() };
+        assert_eq!(text(source, product.left.span()), "-(1 + été)");
+        assert_eq!(text(source, product.right.span()), "f(2, 3)");
+
+        let span = statements[1].span();
+        assert_eq!(text(source, span), "si x alors\n    ecrire x\nfinsi");
+        assert_eq!((span.line, span.column, span.end_line, span.end_column), (2, 1, 4, 6));
+    }
+
+    #[test]
+    fn all_syntax_errors_are_reported() {
+        assert_eq!(
+            parse("variable a <- (1 + 2\nvariable b <- * 3\necrire b\nsi a alors ecrire 1\nfin\necrire a +\n"),
+            Err(vec![("E0121", 2), ("E0122", 2), ("E0122", 5), ("E0122", 7)])
+        );
+    }
+
+    #[test]
+    fn stray_block_closer_is_reported_once() {
+        assert_eq!(parse("ecrire 1\nfin\necrire 2"), Err(vec![("E0122", 2)]));
+    }
+
+    #[test]
+    fn lone_semicolons_are_accepted() {
+        assert_eq!(
+            parse("si a alors ecrire 1 finsi;\nfonction f() debut retourner 1 fin;\n;\necrire f();").as_deref(),
+            Ok("si a\n  bloc\n    ecrire 1\nfonction f()\n  retourner 1\necrire (appel f)\n")
+        );
+    }
+
+    #[test]
+    fn unclosed_parenthesis_stops_at_next_statement() {
+        assert_eq!(parse("ecrire (1 + 2\nvariable b <- 3\necrire b"), Err(vec![("E0121", 2)]));
+    }
+
+    #[test]
+    fn every_name_gets_its_own_node_id() {
+        let mut lexer = Lexer::new("x <- x + f(x)");
+        lexer.scan_tokens();
+        let mut parser = Parser::new(lexer.tokens);
+        parser.set_first_id(10);
+        let statements = parser.parse().unwrap();
+        assert_eq!(parser.next_id(), 14);
+
+        let Stmt::Expression(statement) = &statements[0] else { panic!() };
+        let Expr::AssignVarExpr(assign) = &statement.expression else { panic!() };
+        let Expr::BinaryExpr(sum) = &*assign.value else { panic!() };
+        let Expr::VariableExpr(left) = &*sum.left else { panic!() };
+        let Expr::CallExpr(call) = &*sum.right else { panic!() };
+        let Expr::VariableExpr(callee) = &*call.callee else { panic!() };
+        let Expr::VariableExpr(argument) = &call.arguments[0] else { panic!() };
+        let mut ids = [assign.id, left.id, callee.id, argument.id];
+        ids.sort();
+        assert_eq!(ids, [10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn only_a_returned_call_is_a_tail_call() {
+        let tail_calls = |source: &str| {
+            let Stmt::Function(function) = &statements(source)[0] else { panic!() };
+            function
+                .body
+                .iter()
+                .map(|statement| matches!(statement, Stmt::Return(ret) if ret.tail_call))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tail_calls("fonction f(n) debut\nretourner f(n)\nretourner f(n) + 1\nretourner 1\nretourner\nfin"),
+            [true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn expressions_nested_too_deeply_are_rejected() {
+        // L'analyse d'une parenthèse consomme plusieurs kilo-octets de pile
+        // sans optimisation : assez pour 2000 niveaux.
+        let deep = |source: String| {
+            std::thread::Builder::new()
+                .stack_size(256 * 1024 * 1024)
+                .spawn(move || parse(&source))
+                .unwrap()
+                .join()
+                .unwrap()
+        };
+        let nested = |levels: usize| format!("ecrire {}1{}", "(".repeat(levels), ")".repeat(levels));
+        assert!(deep(nested(MAX_EXPRESSION_DEPTH - 1)).is_ok());
+        assert_eq!(deep(nested(MAX_EXPRESSION_DEPTH + 1)), Err(vec![("E0123", 1)]));
+        assert_eq!(deep(nested(100_000)), Err(vec![("E0123", 1)]));
+
+        let flat = |terms: usize| format!("ecrire 1{}", " + 1".repeat(terms - 1));
+        assert!(deep(flat(MAX_EXPRESSION_DEPTH)).is_ok());
+        assert_eq!(deep(flat(200_000)), Err(vec![("E0123", 1)]));
+    }
+}
@@ -1,61 +1,162 @@
+use std::rc::Rc;
+
 use crate::{
-    expr::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr, Literal},
-    visitor::{ ExprVisitor},
+    expr::{
+        AssignVarExpr, BinaryExpr, CallExp, Expr, GroupingExpr, Literal, LiteralExpr, LogicalExpr,
+        UnaryExpr, VariableExpr,
+    },
+    stmt::{
+        BlockStmt, ExpressionStmt, FunctionStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarStmt,
+        WhileStmt,
+    },
+    visitor::{ExprVisitor, StmtVisitor},
 };
 
-pub struct PrintVisitor;
+/// Affiche l'arbre syntaxique (`--dump-ast-optimise`) : une instruction par
+/// ligne, indentée selon son imbrication, et chaque expression en notation
+/// préfixée.
+#[derive(Default)]
+pub struct PrintVisitor {
+    output: String,
+    depth: usize,
+}
 
-impl ExprVisitor for PrintVisitor {
-    type Output = ();
-    fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
-        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right]);
+impl PrintVisitor {
+    pub fn print(statements: &[Stmt]) -> String {
+        let mut visitor = PrintVisitor::default();
+        for stmt in statements {
+            stmt.accept(&mut visitor);
+        }
+        visitor.output
     }
 
-    fn visit_call_expr(&mut self, expr: &crate::expr::CallExp) -> Self::Output {
-        unimplemented!()
-    }
-    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) {
-        self.parenthesize("group", &[&expr.expression]);
+    fn line(&mut self, text: &str) {
+        self.output.push_str(&"  ".repeat(self.depth));
+        self.output.push_str(text);
+        self.output.push('\n');
     }
 
-    fn visit_literal_expr(&mut self, expr: &LiteralExpr) {
+    fn nested(&mut self, stmt: &Stmt) {
+        self.depth += 1;
+        stmt.accept(self);
+        self.depth -= 1;
+    }
 
-        match &expr.value{
-               Literal::Number(token) => print!(" Number({}) ", token.lexeme),
-               Literal::String(token) => print!(" String({}) ", token.lexeme),
-               Literal::Boolean(token) => print!(" Boolean({}) ", token.lexeme),
-               Literal::Nil => print!("nil"),
+    fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
+        let mut text = format!("({}", name);
+        for expr in exprs {
+            text.push(' ');
+            text.push_str(&expr.accept(self));
         }
-       // self.parenthesize(&expr.value.lexeme, &[]);
+        text.push(')');
+        text
+    }
+}
+
+impl ExprVisitor for PrintVisitor {
+    type Output = String;
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> String {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExp) -> String {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(&expr.arguments);
+        self.parenthesize("appel", &exprs)
     }
 
-    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
-        self.parenthesize(&expr.operator.lexeme, &[&expr.right]);
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> String {
+        self.parenthesize("groupe", &[&expr.expression])
     }
 
-    fn visit_variable_expr(&mut self, expr: &crate::expr::VariableExpr) -> Self::Output {
-        self.parenthesize(&expr.name.lexeme, &[]);
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> String {
+        match &expr.value {
+            Literal::Number(token) | Literal::Boolean(token) => token.lexeme.clone(),
+            Literal::String(_, text) => format!("{:?}", text),
+            Literal::Nil => "VIDE".to_string(),
+        }
     }
-    fn visit_assign_var_expr(&mut self, expr: &crate::expr::AssignVarExpr) -> Self::Output {
-        self.parenthesize(&expr.name.lexeme, &[]);
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> String {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.right])
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> String {
+        expr.name.lexeme.clone()
     }
-    fn visit_logical_expr(&mut self, expr: &crate::expr::LogicalExpr) -> Self::Output {
-        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right]);
+
+    fn visit_assign_var_expr(&mut self, expr: &AssignVarExpr) -> String {
+        let name = format!("<- {}", expr.name.lexeme);
+        self.parenthesize(&name, &[&expr.value])
     }
 
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> String {
+        self.parenthesize(&expr.operator.lexeme, &[&expr.left, &expr.right])
+    }
 }
 
-impl PrintVisitor {
-    pub fn print(&mut self, expr: &Expr) {
-        expr.accept(self);
-        println!();
+impl StmtVisitor for PrintVisitor {
+    type Output = ();
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        let text = stmt.expression.accept(self);
+        self.line(&text);
     }
-    pub fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) {
-        print!("({}[", name);
-        for expr in exprs {
-            expr.accept(self);
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        let text = format!("ecrire {}", stmt.expression.accept(self));
+        self.line(&text);
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        let text = match &stmt.initializer {
+            Some(initializer) => format!("variable {} <- {}", stmt.name.lexeme, initializer.accept(self)),
+            None => format!("variable {}", stmt.name.lexeme),
+        };
+        self.line(&text);
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        self.line("bloc");
+        self.depth += 1;
+        for stmt in &stmt.statements {
+            stmt.accept(self);
+        }
+        self.depth -= 1;
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        let text = format!("si {}", stmt.condition.accept(self));
+        self.line(&text);
+        self.nested(&stmt.then_branch);
+        if let Some(else_branch) = &stmt.else_branch {
+            self.line("sinon");
+            self.nested(else_branch);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        let text = format!("tantque {}", stmt.condition.accept(self));
+        self.line(&text);
+        self.nested(&stmt.body);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) {
+        let params: Vec<&str> = stmt.params.iter().map(|param| param.lexeme.as_str()).collect();
+        self.line(&format!("fonction {}({})", stmt.name.lexeme, params.join(", ")));
+        self.depth += 1;
+        for stmt in &stmt.body {
+            stmt.accept(self);
         }
+        self.depth -= 1;
+    }
 
-        print!("])");
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        let text = match &stmt.value {
+            Some(value) => format!("retourner {}", value.accept(self)),
+            None => "retourner".to_string(),
+        };
+        self.line(&text);
     }
 }
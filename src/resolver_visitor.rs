@@ -1,174 +1,756 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::{
-    expr::Expr,
+    callable::Callable,
+    catalog::{self, Message},
+    diagnostic::Diagnostic,
+    environment::Location,
+    hint,
+    expr::{Expr, Literal, NodeId},
     interpret_visitor::InterpretVisitor,
-    token::Token,
+    span::Span,
+    stmt::Stmt,
+    suggest,
+    symbol::Symbol,
+    token::{Token, TokenType},
+    value::Value,
     visitor::{ExprVisitor, StmtVisitor},
 };
 
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BindingKind {
+    Variable,
+    Parameter,
+    Function,
+}
+
+/// Ce que le resolver sait d'un nom.
+#[derive(Clone)]
+struct Binding {
+    kind: BindingKind,
+    /// Vrai dès que le nom est lu quelque part.
+    used: bool,
+    /// Faux tant que la déclaration n'a pas été exécutée.
+    defined: bool,
+    /// Endroit de la déclaration, absent pour les fonctions natives et les
+    /// variables des saisies précédentes du REPL.
+    span: Option<Span>,
+    /// Nombre de paramètres si le nom désigne une fonction connue.
+    arity: Option<usize>,
+    /// Vrai si la dernière valeur affectée vient de `lire` : c'est un texte.
+    input: bool,
+    /// Case de la variable dans l'environnement de sa portée ; inutilisée
+    /// pour les globales.
+    slot: usize,
+}
+
 pub struct ResolverVisitor<'a> {
     interpreter: &'a mut InterpretVisitor,
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<Symbol, Binding>>,
+    /// Variables globales : celles déjà présentes dans l'interpréteur
+    /// (natives, saisies précédentes du REPL) et celles déclarées au niveau
+    /// supérieur du programme.
+    globals: HashMap<Symbol, Binding>,
+    current_function: FunctionType,
+    /// Faux dans le REPL, où une fonction peut être appelée par une saisie
+    /// ultérieure.
+    check_unused_globals: bool,
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
 }
 
 impl<'a> ResolverVisitor<'a> {
     pub fn new(interpreter: &'a mut InterpretVisitor) -> Self {
+        let globals = interpreter
+            .globals
+            .iter()
+            .map(|(name, value)| (name, ResolverVisitor::global_binding(value)))
+            .collect();
+
         ResolverVisitor{
             interpreter,
             scopes: vec![],
+            globals,
+            current_function: FunctionType::None,
+            check_unused_globals: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Globales définies ailleurs que dans l'interpréteur d'arbre, comme
+    /// celles de la machine virtuelle.
+    pub fn declare_globals<'v>(&mut self, values: impl IntoIterator<Item = (Symbol, &'v Value)>) {
+        for (name, value) in values {
+            self.globals
+                .insert(name, ResolverVisitor::global_binding(value));
+        }
+    }
+
+    fn global_binding(value: &Value) -> Binding {
+        let (kind, span, arity) = match value {
+            Value::NativeFunction(function) => {
+                (BindingKind::Function, None, Some(function.arity()))
+            }
+            Value::Function(function) => (
+                BindingKind::Function,
+                Some(function.stmt.name.span),
+                Some(function.arity()),
+            ),
+            Value::Closure(closure) => {
+                (BindingKind::Function, None, Some(closure.prototype.arity))
+            }
+            _ => (BindingKind::Variable, None, None),
+        };
+        Binding {
+            kind,
+            used: true,
+            defined: true,
+            span,
+            arity,
+            input: false,
+            slot: 0,
         }
     }
 
+    pub fn set_check_unused_globals(&mut self, check_unused_globals: bool) {
+        self.check_unused_globals = check_unused_globals;
+    }
+
     fn begin_scope(&mut self) {
-        
+
         self.scopes.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
-        self.scopes.pop();
+        if let Some(scope) = self.scopes.pop() {
+            let mut unused: Vec<(&Symbol, &Binding)> = scope.iter().collect();
+            unused.sort_by_key(|(_, binding)| binding.span.map(|span| span.start));
+            for (name, binding) in unused {
+                self.warn_unused(&name.name(), binding);
+            }
+        }
+    }
+
+    fn warn_unused(&mut self, name: &str, binding: &Binding) {
+        let Some(span) = binding.span else {
+            return;
+        };
+        if binding.used || name.starts_with('_') {
+            return;
+        }
+        let diagnostic = match binding.kind {
+            BindingKind::Variable => Diagnostic::warning(Message::new("W0001", &[&name]))
+                .with_help(catalog::text("noms.supprimer-variable", &[&name])),
+            BindingKind::Parameter => Diagnostic::warning(Message::new("W0002", &[&name]))
+                .with_help(catalog::text("noms.renommer-parametre", &[&name])),
+            BindingKind::Function => Diagnostic::warning(Message::new("W0003", &[&name])),
+        };
+        self.warnings.push(diagnostic.with_label(span, ""));
     }
 
-    pub fn resolve(&mut self, statements: &Vec<crate::stmt::Stmt>) {
+    /// Résout un programme complet. Les déclarations du niveau supérieur sont
+    /// connues d'avance : une fonction peut utiliser une globale déclarée
+    /// plus loin dans le fichier.
+    pub fn resolve(&mut self, statements: &[Stmt]) {
         for stmt in statements {
+            let (name, kind, arity) = match stmt {
+                Stmt::Var(var) => (&var.name, BindingKind::Variable, None),
+                Stmt::Function(function) => (
+                    &function.name,
+                    BindingKind::Function,
+                    Some(function.params.len()),
+                ),
+                _ => continue,
+            };
+            let binding = self.globals.entry(name.symbol).or_insert(Binding {
+                kind,
+                used: false,
+                defined: false,
+                span: Some(name.span),
+                arity: None,
+                input: false,
+                slot: 0,
+            });
+            if !binding.defined {
+                binding.arity = arity;
+            }
+        }
+
+        self.resolve_statements(statements);
+
+        if self.check_unused_globals {
+            let mut functions: Vec<(Symbol, Binding)> = self
+                .globals
+                .iter()
+                .filter(|(_, binding)| binding.kind == BindingKind::Function)
+                .map(|(name, binding)| (*name, binding.clone()))
+                .collect();
+            functions.sort_by_key(|(_, binding)| binding.span.map(|span| span.start));
+            for (name, binding) in functions {
+                self.warn_unused(&name.name(), &binding);
+            }
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for (i, stmt) in statements.iter().enumerate() {
             self.resolve_stmt(stmt);
+
+            if let (Stmt::Return(ret), Some(next)) = (stmt, statements.get(i + 1)) {
+                let last = statements.last().unwrap_or(next);
+                let mut warning = Diagnostic::warning(Message::new("W0004", &[]))
+                    .with_label(next.span().to(&last.span()), "");
+                // Hors d'une fonction, `retourner` est déjà une erreur (E0207).
+                if self.current_function != FunctionType::None {
+                    warning = warning
+                        .with_secondary(ret.keyword.span, catalog::text("noms.fin-fonction", &[]));
+                }
+                self.warnings.push(warning);
+            }
+        }
+    }
+
+    /// Avertit si une condition est un littéral : sa valeur est connue
+    /// avant l'exécution. `tantque vrai`, la boucle sans fin qu'arrête un
+    /// `retourner`, est accepté.
+    fn check_condition(&mut self, condition: &Expr, is_loop: bool) {
+        let mut expr = condition;
+        while let Expr::GroupingExpr(group) = expr {
+            expr = &group.expression;
+        }
+        let Expr::LiteralExpr(literal) = expr else {
+            return;
+        };
+        let value = match &literal.value {
+            Literal::Boolean(token) => token.token_type == TokenType::TRUE,
+            Literal::Number(token) => token
+                .literal
+                .as_deref()
+                .unwrap_or(&token.lexeme)
+                .parse::<f64>()
+                .is_ok_and(|n| n != 0.0),
+            Literal::String(token, _) => !token.lexeme.is_empty(),
+            Literal::Nil => false,
+        };
+        if value && is_loop {
+            return;
         }
+        let code = if value { "W0005" } else { "W0006" };
+        self.warnings.push(
+            Diagnostic::warning(Message::new(code, &[])).with_label(condition.span(), ""),
+        );
     }
 
-    fn resolve_stmt(&mut self, stmt: &crate::stmt::Stmt) {
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
         stmt.accept(self);
     }
 
-    fn declare(&mut self, name: &str) {
-        let mut is_defined = false;
+    fn declare(&mut self, name: &Token, kind: BindingKind, arity: Option<usize>) {
+        let mut binding = Binding {
+            kind,
+            used: false,
+            defined: false,
+            span: Some(name.span),
+            arity,
+            input: false,
+            slot: 0,
+        };
+
+        let Some(scope) = self.scopes.last_mut() else {
+            // Une globale peut être redéclarée.
+            let global = self.globals.entry(name.symbol).or_insert(binding);
+            global.kind = kind;
+            global.span = Some(name.span);
+            global.arity = arity;
+            return;
+        };
+        // Une redéclaration (signalée plus bas) réutilise la case.
+        binding.slot = scope.get(&name.symbol).map_or(scope.len(), |previous| previous.slot);
+
+        if !scope.contains_key(&name.symbol) {
+            let shadowed = self
+                .scopes
+                .iter()
+                .rev()
+                .skip(1)
+                .find_map(|scope| scope.get(&name.symbol))
+                .or_else(|| self.globals.get(&name.symbol))
+                .and_then(|binding| binding.span);
+            if let Some(shadowed) = shadowed {
+                self.warnings.push(
+                    Diagnostic::warning(Message::new("W0007", &[&name.lexeme]))
+                        .with_label(name.span, "")
+                        .with_secondary(shadowed, catalog::text("noms.variable-masquee", &[])),
+                );
+            }
+        }
+
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if let Some(previous) = scope.insert(name.symbol, binding) {
+            let mut diagnostic = Diagnostic::error(Message::new("E0201", &[&name.lexeme]))
+                .with_label(name.span, catalog::text("noms.nouvelle-declaration", &[]))
+                .with_help(catalog::text("noms.renommer", &[]));
+            if let Some(span) = previous.span {
+                diagnostic = diagnostic.with_secondary(span, catalog::text("noms.declaree-ici", &[]));
+            }
+            self.errors.push(diagnostic);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        let scope = match self.scopes.last_mut() {
+            Some(scope) => scope,
+            None => &mut self.globals,
+        };
+        if let Some(binding) = scope.get_mut(&name.symbol) {
+            binding.defined = true;
+        }
+    }
+
+    /// Cherche un nom dans les portées locales puis dans les globales.
+    fn lookup(&self, name: Symbol) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name))
+            .or_else(|| self.globals.get(&name))
+    }
 
-        if let Some(scope) = self.scopes.last_mut() {
-            is_defined = scope.contains_key(name);
-            scope.insert(name.to_string(), false);
+    fn lookup_mut(&mut self, name: Symbol) -> Option<&mut Binding> {
+        match self.scopes.iter().rposition(|scope| scope.contains_key(&name)) {
+            Some(index) => self.scopes[index].get_mut(&name),
+            None => self.globals.get_mut(&name),
         }
+    }
 
-        if is_defined {
-            panic!("Variable with this name already declared in this scope.");
+    /// Vrai si l'expression est un appel à `lire` ou une variable qui en
+    /// contient le résultat.
+    fn is_input(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::CallExpr(call) => matches!(
+                &*call.callee,
+                Expr::VariableExpr(callee) if callee.name.lexeme == "lire"
+            ),
+            Expr::VariableExpr(variable) => self
+                .lookup(variable.name.symbol)
+                .is_some_and(|binding| binding.input),
+            Expr::GroupingExpr(grouping) => self.is_input(&grouping.expression),
+            _ => false,
         }
     }
 
-    fn define(&mut self, name: &str) {
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name.to_string(), true);
+    /// `lire(...) == 5` est toujours faux : le texte lu n'est jamais égal à
+    /// un nombre.
+    fn check_input_comparison(&mut self, expr: &crate::expr::BinaryExpr) {
+        let (input, other) = if self.is_input(&expr.left) {
+            (&expr.left, &expr.right)
+        } else if self.is_input(&expr.right) {
+            (&expr.right, &expr.left)
+        } else {
+            return;
+        };
+        if !matches!(&**other, Expr::LiteralExpr(literal) if matches!(literal.value, Literal::Number(_))) {
+            return;
         }
+        let name = match &**input {
+            Expr::VariableExpr(variable) => Some(variable.name.lexeme.as_str()),
+            _ => None,
+        };
+        let label = if expr.operator.token_type == TokenType::EQUAL_EQUAL {
+            "noms.toujours-faux"
+        } else {
+            "noms.toujours-vrai"
+        };
+        let diagnostic = Diagnostic::warning(Message::new("W0008", &[]))
+            .with_label(expr.span, catalog::text(label, &[]));
+        self.warnings
+            .push(hint::text_compared_to_number(name).attach(diagnostic));
+    }
+
+    /// Aide « vouliez-vous dire ... ? » pour un nom inconnu.
+    fn suggestion(&self, name: &str) -> Option<String> {
+        let visible: Vec<_> = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.keys())
+            .chain(self.globals.keys())
+            .map(|symbol| symbol.name())
+            .collect();
+        suggest::help(name, visible.iter().map(|name| &**name))
     }
 
-    fn resolve_local(&mut self, name: &Token) {
-        for (i,scope) in self.scopes.iter().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                self.interpreter.resolve(name, i);
+    /// Indique à l'interpréteur où trouver la variable nommée par le nœud
+    /// `id` : une case locale `depth` portées plus haut, ou la table des
+    /// globales.
+    fn resolve_local(&mut self, id: NodeId, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(binding) = scope.get(&name.symbol) {
+                let slot = binding.slot;
+                self.interpreter.resolve(id, Location::Local { depth, slot });
                 return;
             }
         }
+        let index = self.interpreter.globals.index(name.symbol);
+        self.interpreter.resolve(id, Location::Global(index));
     }
 
-    fn resolve_function(&mut self, stmt: &crate::stmt::FunctionStmt) {
+    /// Vérifie qu'un nom lu ou affecté existe au moment où il est utilisé.
+    fn check_declared(&mut self, name: &Token, span: Span, assignment: bool) {
+        let is_global = !self.scopes.iter().any(|scope| scope.contains_key(&name.symbol));
+        let binding = self.lookup(name.symbol).cloned();
+        match binding {
+            None => {
+                let mut diagnostic = if assignment {
+                    Diagnostic::error(Message::new("E0203", &[&name.lexeme])).with_label(span, "")
+                } else {
+                    Diagnostic::error(Message::new("E0202", &[&name.lexeme]))
+                        .with_label(span, catalog::text("noms.introuvable", &[]))
+                };
+                match self.suggestion(&name.lexeme) {
+                    Some(help) => diagnostic = diagnostic.with_help(help),
+                    None if assignment => {
+                        diagnostic = diagnostic
+                            .with_help(catalog::text("noms.declarer-avant", &[&name.lexeme]))
+                    }
+                    None => {}
+                }
+                self.errors.push(diagnostic);
+            }
+            // Dans une fonction, une globale déclarée plus loin le sera au
+            // moment de l'appel : seul le code exécuté directement est vérifié.
+            Some(binding)
+                if !binding.defined && is_global && self.current_function == FunctionType::None =>
+            {
+                let mut diagnostic =
+                    Diagnostic::error(Message::new("E0204", &[&name.lexeme])).with_label(span, "");
+                if let Some(declaration) = binding.span {
+                    diagnostic = diagnostic
+                        .with_secondary(declaration, catalog::text("noms.declaree-ici", &[]));
+                }
+                self.errors.push(diagnostic);
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn resolve_function(&mut self, stmt: &crate::stmt::FunctionStmt, kind: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = kind;
+
         self.begin_scope();
         for param in stmt.params.iter() {
-            self.declare(&param.lexeme);
-            self.define(&param.lexeme);
+            self.declare(param, BindingKind::Parameter, None);
+            self.define(param);
         }
 
-        self.resolve(&stmt.body);
+        self.resolve_statements(&stmt.body);
         self.end_scope();
+
+        self.current_function = enclosing_function;
     }
 }
 
 impl ExprVisitor for ResolverVisitor<'_> {
     type Output = ();
     fn visit_binary_expr(&mut self, expr: &crate::expr::BinaryExpr) {
-        &expr.left.accept(self);
-        &expr.right.accept(self);
+        expr.left.accept(self);
+        expr.right.accept(self);
+        if matches!(expr.operator.token_type, TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL) {
+            self.check_input_comparison(expr);
+        }
     }
 
     fn visit_call_expr(&mut self,  expr: &crate::expr::CallExp) -> Self::Output {
-        &expr.callee.accept(self);
+        expr.callee.accept(self);
 
         for arg in expr.arguments.iter() {
-            &arg.accept(self);
+            arg.accept(self);
+        }
+
+        let crate::expr::Expr::VariableExpr(callee) = &*expr.callee else {
+            return;
+        };
+        let Some(binding) = self.lookup(callee.name.symbol).cloned() else {
+            return;
+        };
+        if let Some(arity) = binding.arity {
+            if arity != expr.arguments.len() {
+                let mut diagnostic = Diagnostic::error(Message::new(
+                    "E0205",
+                    &[
+                        &callee.name.lexeme,
+                        &arity.to_string(),
+                        &expr.arguments.len().to_string(),
+                    ],
+                ))
+                .with_label(expr.span, "");
+                if let Some(declaration) = binding.span {
+                    diagnostic = diagnostic
+                        .with_secondary(declaration, catalog::text("noms.fonction-declaree-ici", &[]));
+                }
+                self.errors.push(diagnostic);
+            }
         }
     }
     fn visit_grouping_expr(&mut self, expr: &crate::expr::GroupingExpr) {
-        &expr.expression.accept(self);
+        expr.expression.accept(self);
     }
 
-    fn visit_literal_expr(&mut self, _expr: &crate::expr::LiteralExpr) -> Self::Output{
-        ();
-    }
+    fn visit_literal_expr(&mut self, _expr: &crate::expr::LiteralExpr) -> Self::Output {}
 
     fn visit_unary_expr(&mut self, expr: &crate::expr::UnaryExpr) {
-        &expr.right.accept(self);
+        expr.right.accept(self);
     }
 
     fn visit_variable_expr(&mut self, expr: &crate::expr::VariableExpr) -> Self::Output {
         if let Some(scope) = self.scopes.last() {
-            if let Some(declared) = scope.get(&expr.name.lexeme) {
-                if !declared {
-                    panic!("Cannot read local variable in its own initializer.");
+            if let Some(Binding { defined: false, span, .. }) = scope.get(&expr.name.symbol) {
+                let mut diagnostic = Diagnostic::error(Message::new("E0206", &[&expr.name.lexeme]))
+                    .with_label(expr.span, "")
+                    .with_note(catalog::text("noms.initialisation", &[]));
+                if let Some(span) = span {
+                    diagnostic =
+                        diagnostic.with_secondary(*span, catalog::text("noms.declaree-ici", &[]));
                 }
+                self.errors.push(diagnostic);
+                return;
             }
         }
 
-        self.resolve_local(&expr.name);
+        self.check_declared(&expr.name, expr.span, false);
+        if let Some(binding) = self.lookup_mut(expr.name.symbol) {
+            binding.used = true;
+        }
+        self.resolve_local(expr.id, &expr.name);
     }
     fn visit_assign_var_expr(&mut self, expr: &crate::expr::AssignVarExpr) -> Self::Output {
-        &expr.value.accept(self);
-        self.resolve_local(&expr.name);
+        expr.value.accept(self);
+        self.check_declared(&expr.name, expr.span, true);
+        // Après une affectation, le nom peut désigner n'importe quelle valeur.
+        if let Some(binding) = self.lookup_mut(expr.name.symbol) {
+            binding.arity = None;
+        }
+        let input = self.is_input(&expr.value);
+        if let Some(binding) = self.lookup_mut(expr.name.symbol) {
+            binding.input = input;
+        }
+        self.resolve_local(expr.id, &expr.name);
     }
     fn visit_logical_expr(&mut self, expr: &crate::expr::LogicalExpr) -> Self::Output {
-        &expr.left.accept(self);
-        &expr.right.accept(self);
+        expr.left.accept(self);
+        expr.right.accept(self);
     }
 }
 
 impl StmtVisitor for ResolverVisitor<'_> {
     type Output = ();
     fn visit_expression_stmt(&mut self, stmt: &crate::stmt::ExpressionStmt) {
-        &stmt.expression.accept(self);
+        stmt.expression.accept(self);
     }
     fn visit_print_stmt(&mut self, stmt: &crate::stmt::PrintStmt) {
-        &stmt.expression.accept(self);
+        stmt.expression.accept(self);
     }
     fn visit_var_stmt(&mut self, stmt: &crate::stmt::VarStmt) {
-        self.declare(&stmt.name.lexeme);
+        self.declare(&stmt.name, BindingKind::Variable, None);
+        self.resolve_local(stmt.id, &stmt.name);
 
         if let Some(ref initializer) = stmt.initializer {
             initializer.accept(self);
+            if self.is_input(initializer) {
+                if let Some(binding) = self.lookup_mut(stmt.name.symbol) {
+                    binding.input = true;
+                }
+            }
         }
 
-        self.define(&stmt.name.lexeme);
+        self.define(&stmt.name);
     }
     fn visit_block_stmt(&mut self, stmt: &crate::stmt::BlockStmt) {
         self.begin_scope();
-        self.resolve(&stmt.statements);
+        self.resolve_statements(&stmt.statements);
         self.end_scope();
     }
     fn visit_if_stmt(&mut self, stmt: &crate::stmt::IfStmt) {
-        &stmt.condition.accept(self);
-        &stmt.then_branch.accept(self);
+        self.check_condition(&stmt.condition, false);
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
 
         if let Some(ref else_branch) = stmt.else_branch {
             else_branch.accept(self);
         }
     }
     fn visit_while_stmt(&mut self, stmt: &crate::stmt::WhileStmt) {
-        &stmt.condition.accept(self);
-        &stmt.body.accept(self);
+        self.check_condition(&stmt.condition, true);
+        stmt.condition.accept(self);
+        stmt.body.accept(self);
     }
-    fn visit_function_stmt(&mut self, stmt: &crate::stmt::FunctionStmt) {
-        self.declare(&stmt.name.lexeme);
-        self.define(&stmt.name.lexeme);
+    fn visit_function_stmt(&mut self, stmt: &Rc<crate::stmt::FunctionStmt>) {
+        self.declare(&stmt.name, BindingKind::Function, Some(stmt.params.len()));
+        self.resolve_local(stmt.id, &stmt.name);
+        self.define(&stmt.name);
 
-        self.resolve_function(&stmt);
+        self.resolve_function(stmt, FunctionType::Function);
     }
     fn visit_return_stmt(&mut self, stmt: &crate::stmt::ReturnStmt) {
+        if self.current_function == FunctionType::None {
+            self.errors.push(
+                Diagnostic::error(Message::new("E0207", &[]))
+                    .with_label(stmt.keyword.span, ""),
+            );
+        }
+
         if let Some(ref value) = stmt.value {
             value.accept(self);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Lexer;
+
+    /// Code et ligne de chaque erreur, puis de chaque avertissement.
+    type Reported = Vec<(String, u32)>;
+
+    /// Erreurs et avertissements signalés sur `source`.
+    fn diagnostics(source: &str) -> (Vec<Diagnostic>, Vec<Diagnostic>) {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        let statements = Parser::new(lexer.tokens).parse().unwrap();
+        let mut interpreter = InterpretVisitor::new();
+        let mut resolver = ResolverVisitor::new(&mut interpreter);
+        resolver.resolve(&statements);
+        (resolver.errors, resolver.warnings)
+    }
+
+    fn resolve(source: &str) -> (Reported, Reported) {
+        let (errors, warnings) = diagnostics(source);
+        let reported = |diagnostics: &[Diagnostic]| {
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code.clone().unwrap(), diagnostic.labels[0].span.line))
+                .collect()
+        };
+        (reported(&errors), reported(&warnings))
+    }
+
+    fn errors(source: &str) -> Vec<(String, u32)> {
+        resolve(source).0
+    }
+
+    fn warnings(source: &str) -> Vec<(String, u32)> {
+        resolve(source).1
+    }
+
+    fn reported(code: &str, line: u32) -> (String, u32) {
+        (code.to_string(), line)
+    }
+
+    #[test]
+    fn redeclaration_and_own_initializer() {
+        assert_eq!(
+            errors("fonction f() debut\nvariable a <- 1\nvariable a <- 2\necrire a\nfin\nf()"),
+            [reported("E0201", 3)]
+        );
+        assert_eq!(errors("{\nvariable a <- a\necrire a\n}"), [reported("E0206", 2)]);
+    }
+
+    #[test]
+    fn undeclared_names() {
+        assert_eq!(errors("ecrire inconnu"), [reported("E0202", 1)]);
+        assert_eq!(errors("inconnu <- 1"), [reported("E0203", 1)]);
+        assert_eq!(errors("ecrire a\nvariable a <- 1"), [reported("E0204", 1)]);
+    }
+
+    #[test]
+    fn unknown_names_suggest_a_close_name() {
+        let (errors, _) = diagnostics("variable total <- 1\necrire totl");
+        assert_eq!(errors[0].help, ["vouliez-vous dire 'total' ?"]);
+        let (errors, _) = diagnostics("fonction f() debut\nvariable compte <- 1\nretourner compt\nfin\necrire f()");
+        assert_eq!(errors[0].help, ["vouliez-vous dire 'compte' ?"]);
+    }
+
+    #[test]
+    fn wrong_argument_count() {
+        assert_eq!(
+            errors("fonction f(a) debut retourner a fin\necrire f(1, 2)"),
+            [reported("E0205", 2)]
+        );
+        assert_eq!(errors("ecrire lire()"), [reported("E0205", 1)]);
+    }
+
+    #[test]
+    fn top_level_return() {
+        assert_eq!(errors("retourner 1"), [reported("E0207", 1)]);
+    }
+
+    #[test]
+    fn all_errors_are_collected() {
+        assert_eq!(
+            errors("ecrire a\nb <- 1\nretourner"),
+            [reported("E0202", 1), reported("E0203", 2), reported("E0207", 3)]
+        );
+    }
+
+    #[test]
+    fn unused_names_and_dead_code() {
+        assert_eq!(
+            warnings("fonction f(p) debut\nvariable a <- 1\nretourner 2\necrire 3\nfin\nfonction g() debut fin\necrire f(1)"),
+            [reported("W0004", 4), reported("W0002", 1), reported("W0001", 2), reported("W0003", 6)]
+        );
+    }
+
+    #[test]
+    fn dead_code_points_at_the_return_inside_functions_only() {
+        let (_, warnings) = diagnostics("fonction f() debut\nretourner 1\necrire 2\nfin\necrire f()");
+        assert_eq!(warnings[0].labels.len(), 2);
+        assert!(!warnings[0].labels[1].primary);
+        assert_eq!(warnings[0].labels[1].span.line, 2);
+
+        let (errors, warnings) = diagnostics("retourner 1\necrire 2");
+        assert_eq!(errors[0].code.as_deref(), Some("E0207"));
+        assert_eq!(warnings[0].code.as_deref(), Some("W0004"));
+        assert_eq!(warnings[0].labels.len(), 1);
+    }
+
+    #[test]
+    fn constant_conditions_and_shadowing() {
+        assert_eq!(
+            warnings(
+                "variable x <- 1\nfonction f() debut\nvariable x <- 2\nretourner x\nfin\n\
+                 si vrai alors ecrire f() finsi\nsi 0 alors ecrire x finsi"
+            ),
+            [reported("W0007", 3), reported("W0005", 6), reported("W0006", 7)]
+        );
+    }
+
+    #[test]
+    fn endless_loop_is_not_a_constant_condition() {
+        assert_eq!(
+            warnings("fonction f() debut\ntantque vrai faire {\nretourner 1\n}\nfin\necrire f()"),
+            []
+        );
+        assert_eq!(warnings("tantque faux faire ecrire 1"), [reported("W0006", 1)]);
+    }
+
+    #[test]
+    fn input_compared_to_a_number() {
+        assert_eq!(warnings("ecrire lire(\"? \") == 5"), [reported("W0008", 1)]);
+        assert_eq!(
+            warnings("variable r <- lire(\"? \")\nsi r != 5 alors ecrire r finsi"),
+            [reported("W0008", 2)]
+        );
+        assert_eq!(warnings("variable r <- lire(\"? \")\nsi r == \"5\" alors ecrire r finsi"), []);
+    }
+}
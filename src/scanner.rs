@@ -1,18 +1,48 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 
+use crate::diagnostic::Suppressions;
+use crate::catalog::{self, Message};
+use crate::error::LexerError;
+use crate::span::{FileId, Span};
 use crate::token::{Token, TokenType};
 
+/// Pragma qui active, en première ligne d'un fichier, le mode où les blocs
+/// sont délimités par l'indentation.
+const INDENTATION_PRAGMA: &str = "#mode:indentation";
+
+/// Analyseur lexical. `start` et `current` sont des positions en octets dans
+/// `source` et avancent toujours d'un caractère UTF-8 complet, ce qui permet
+/// de découper les lexèmes directement et garde l'analyse linéaire.
 pub struct Lexer<'a> {
     source: &'a str,
     pub tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: u32,
+    line_start: usize,
+    start_line: u32,
+    start_line_start: usize,
+    column_cache: Cell<(usize, usize)>,
+    file: FileId,
+    paren_depth: usize,
+    /// Profondeurs des parenthèses et crochets ouverts dont les virgules
+    /// séparent des éléments : arguments d'un appel, éléments d'une liste.
+    argument_lists: Vec<usize>,
     keywords: HashMap<String, TokenType>,
+    pub errors: Vec<LexerError>,
+    pub suppressions: Suppressions,
+    indentation: bool,
+    indent_stack: Vec<String>,
+    at_line_start: bool,
+    pending_dedents: usize,
+    decimal_comma: bool,
+    literal: Option<String>,
 }
 
 impl Lexer<'_> {
-    pub fn new(src: &String) -> Lexer {
+    /// Table des mots-clés du langage.
+    pub fn keyword_table() -> HashMap<String, TokenType> {
         let mut keywords = HashMap::new();
         keywords.insert("et".to_string(), TokenType::AND);
         keywords.insert("class".to_string(), TokenType::CLASS);
@@ -36,6 +66,11 @@ impl Lexer<'_> {
         keywords.insert("fintantque".to_string(), TokenType::ENDWHILE);
         keywords.insert("debut".to_string(), TokenType::START);
         keywords.insert("fin".to_string(), TokenType::END);
+        keywords
+    }
+
+    pub fn new(src: &str) -> Lexer<'_> {
+        let keywords = Lexer::keyword_table();
 
         Lexer {
             source: src,
@@ -43,22 +78,111 @@ impl Lexer<'_> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_line_start: 0,
+            column_cache: Cell::new((0, 1)),
+            file: 0,
+            paren_depth: 0,
+            argument_lists: Vec::new(),
             keywords,
+            errors: Vec::new(),
+            suppressions: Suppressions::default(),
+            indentation: Lexer::has_indentation_pragma(src),
+            indent_stack: vec![String::new()],
+            at_line_start: true,
+            pending_dedents: 0,
+            decimal_comma: false,
+            literal: None,
         }
     }
 
+    fn has_indentation_pragma(src: &str) -> bool {
+        src.lines().next().is_some_and(|line| {
+            line.chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                == INDENTATION_PRAGMA
+        })
+    }
+
+    /// Active le mode indentation (fichiers `.fri`).
+    pub fn set_indentation(&mut self, indentation: bool) {
+        self.indentation = indentation;
+    }
+
+    /// Accepte la virgule décimale (`3,14`) dans les littéraux numériques.
+    pub fn set_decimal_comma(&mut self, decimal_comma: bool) {
+        self.decimal_comma = decimal_comma;
+    }
+
+    /// Fichier auquel appartiennent les spans des tokens produits.
+    pub fn set_file(&mut self, file: FileId) {
+        self.file = file;
+    }
+
+    /// Colonne (en caractères, à partir de 1) d'une position de la ligne courante.
+    fn column(&self, offset: usize) -> usize {
+        self.column_in_line(self.line_start, offset)
+    }
+
+    /// Colonne d'une position dans la ligne commençant à `line_start`. Le
+    /// dernier calcul est mémorisé pour que les lignes très longues restent
+    /// analysées en temps linéaire.
+    fn column_in_line(&self, line_start: usize, offset: usize) -> usize {
+        let (cached_offset, cached_column) = self.column_cache.get();
+        let column = if cached_offset >= line_start && cached_offset <= offset {
+            cached_column + self.source[cached_offset..offset].chars().count()
+        } else {
+            self.source[line_start..offset].chars().count() + 1
+        };
+        self.column_cache.set((offset, column));
+        column
+    }
+
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_line_start = self.line_start;
+    }
+
+    fn token_span(&self) -> Span {
+        Span {
+            file: self.file,
+            start: self.start,
+            end: self.current,
+            line: self.start_line,
+            column: self.column_in_line(self.start_line_start, self.start),
+            end_line: self.line,
+            end_column: self.column(self.current),
+        }
+    }
+
+    /// Span d'une portion de la ligne courante.
+    fn span_in_line(&self, start: usize, end: usize) -> Span {
+        Span {
+            file: self.file,
+            start,
+            end,
+            line: self.line,
+            column: self.column_in_line(self.line_start, start),
+            end_line: self.line,
+            end_column: self.column_in_line(self.line_start, end),
+        }
+    }
+
+    fn error(&mut self, span: Span, message: Message) {
+        self.errors.push(LexerError { span, message });
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
     fn advance(&mut self) -> Option<char> {
-        match self.source.chars().nth(self.current) {
-            Some(c) => {
-                self.current += 1;
-                Some(c)
-            }
-            None => None,
-        }
+        let c = self.peek()?;
+        self.current += c.len_utf8();
+        Some(c)
     }
 
     fn add_token(&mut self, token_type: TokenType) {
@@ -69,7 +193,13 @@ impl Lexer<'_> {
 
         let text = match token_type{
             TokenType::STRING => {
-                self.source[self.start+1..self.current-1].to_string()
+                // Une chaîne non terminée n'a pas de guillemet fermant à retirer.
+                let end = if self.current > self.start + 1 && self.source[..self.current].ends_with('"') {
+                    self.current - 1
+                } else {
+                    self.current
+                };
+                self.source[self.start+1..end].to_string()
             }
             _ => {
                 self.source[self.start..self.current].to_string()
@@ -79,179 +209,485 @@ impl Lexer<'_> {
             Some(l) => l,
             None => text.clone(),
         };
+        let span = self.token_span();
         self.tokens
-            .push(Token::new(token_type, text, literal, self.line));
+            .push(Token::new(token_type, text, literal, self.start_line, span));
     }
 
     fn match_next(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.peek() != Some(expected) {
             return false;
         }
-        self.current += 1;
+        self.current += expected.len_utf8();
         true
     }
 
     fn peek(&self) -> Option<char> {
-        match self.source.chars().nth(self.current) {
-            Some(c) => Some(c),
-            None => None,
-        }
+        self.source[self.current..].chars().next()
     }
 
-    pub fn next_token(&mut self) -> TokenType {
-        self.start = self.current;
-        if self.is_at_end() {
-            return TokenType::EOF;
+    fn peek_next(&self) -> Option<char> {
+        self.source[self.current..].chars().nth(1)
+    }
+
+    fn skip_line(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.advance();
         }
+    }
 
-        let c = self.advance();
-        match c {
-            Some('(') => TokenType::LEFT_PAREN,
-            Some(')') => TokenType::RIGHT_PAREN,
-            Some('{') => TokenType::LEFT_BRACE,
-            Some('}') => TokenType::RIGHT_BRACE,
-            Some(',') => TokenType::COMMA,
-            Some('.') => TokenType::DOT,
-            Some('-') => TokenType::MINUS,
-            Some('+') => TokenType::PLUS,
-            Some(';') => TokenType::SEMICOLON,
-            Some('*') => TokenType::STAR,
-            Some('%') => TokenType::MODULO,
-            Some(':') => TokenType::COLON,
-            Some('[') => TokenType::LEFT_BRACKET,
-            Some(']') => TokenType::RIGHT_BRACKET,
-            Some('!') => {
-                if self.match_next('=') {
-                    TokenType::BANG_EQUAL
-                } else {
-                    TokenType::BANG
-                }
+    /// Saute un commentaire et relève les `frenchy:ignorer` qu'il contient.
+    fn comment(&mut self) {
+        self.skip_line();
+        let text = &self.source[self.start..self.current];
+        let Some(position) = text.find(Suppressions::MARKER) else {
+            return;
+        };
+        let codes = text[position + Suppressions::MARKER.len()..]
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|code| !code.is_empty())
+            .map(str::to_string)
+            .collect();
+        let alone = self.source[self.line_start..self.start].trim().is_empty();
+        let line = if alone { self.line + 1 } else { self.line };
+        self.suppressions.add(self.file, line, codes);
+    }
+
+    pub fn next_token(&mut self) -> TokenType {
+        loop {
+            self.begin_token();
+            if self.pending_dedents > 0 {
+                self.pending_dedents -= 1;
+                return TokenType::DEDENT;
             }
-            Some('=') => {
-                if self.match_next('=') {
-                    TokenType::EQUAL_EQUAL
-                } else {
-                    TokenType::EQUAL
+            if self.indentation && self.at_line_start {
+                if let Some(token_type) = self.indent() {
+                    return token_type;
                 }
             }
-            Some('<') => {
-              /*   if self.match_next('=') {
-                    TokenType::LESS_EQUAL
-                } else {
-                    TokenType::LESS
-                }*/
-
-                match self.match_next('='){
-                    true => TokenType::LESS_EQUAL,
-                    false => {
-                        match self.match_next('-'){
-                            true => TokenType::ASSIGN,
-                            false => TokenType::LESS
-                        }
+
+            self.begin_token();
+            let c = match self.advance() {
+                Some(c) => c,
+                None => return TokenType::EOF,
+            };
+            return match c {
+                '(' => {
+                    let call = self.tokens.last().is_some_and(|token| {
+                        matches!(token.token_type, TokenType::IDENTIFIER | TokenType::RIGHT_PAREN)
+                    });
+                    self.open_bracket(call);
+                    TokenType::LEFT_PAREN
+                }
+                ')' => {
+                    self.close_bracket();
+                    TokenType::RIGHT_PAREN
+                }
+                '{' => TokenType::LEFT_BRACE,
+                '}' => TokenType::RIGHT_BRACE,
+                ',' => TokenType::COMMA,
+                '.' => TokenType::DOT,
+                '-' => TokenType::MINUS,
+                '+' => TokenType::PLUS,
+                ';' => TokenType::SEMICOLON,
+                '*' => TokenType::STAR,
+                '%' => TokenType::MODULO,
+                ':' => TokenType::COLON,
+                '[' => {
+                    self.open_bracket(true);
+                    TokenType::LEFT_BRACKET
+                }
+                ']' => {
+                    self.close_bracket();
+                    TokenType::RIGHT_BRACKET
+                }
+                '!' => {
+                    if self.match_next('=') {
+                        TokenType::BANG_EQUAL
+                    } else {
+                        TokenType::BANG
                     }
                 }
-            }
-            Some('>') => {
-                if self.match_next('=') {
-                    TokenType::GREATER_EQUAL
-                } else {
-                    TokenType::GREATER
+                '=' => {
+                    if self.match_next('=') {
+                        TokenType::EQUAL_EQUAL
+                    } else {
+                        TokenType::EQUAL
+                    }
                 }
-            }
-            Some('/') => {
-                if self.match_next('/') {
-                    while self.peek() != Some('\n') && !self.is_at_end() {
-                        self.advance();
+                '<' => {
+                    if self.match_next('=') {
+                        TokenType::LESS_EQUAL
+                    } else if self.match_next('-') {
+                        TokenType::ASSIGN
+                    } else {
+                        TokenType::LESS
+                    }
+                }
+                '>' => {
+                    if self.match_next('=') {
+                        TokenType::GREATER_EQUAL
+                    } else {
+                        TokenType::GREATER
+                    }
+                }
+                '/' => {
+                    if self.match_next('/') {
+                        self.comment();
+                        continue;
                     }
-                    self.next_token()
-                } else {
                     TokenType::SLASH
                 }
+                '#' => {
+                    self.comment();
+                    continue;
+                }
+                ' ' | '\r' | '\t' => continue,
+                '\n' => {
+                    self.line_start = self.current;
+                    self.at_line_start = self.paren_depth == 0
+                        && self
+                            .tokens
+                            .last()
+                            .is_none_or(|token| !Lexer::is_continuation(&token.token_type));
+                    if self.newline_ends_statement() {
+                        TokenType::NEWLINE
+                    } else {
+                        self.line += 1;
+                        continue;
+                    }
+                }
+                '"' => self.string(),
+                c if c.is_ascii_digit() => self.number(),
+                c if Lexer::is_identifier_start(c) => self.identifier(),
+                c => {
+                    let span = self.token_span();
+                    self.error(span, Message::new("E0001", &[&c]));
+                    continue;
+                }
+            };
+        }
+    }
+
+    fn open_bracket(&mut self, argument_list: bool) {
+        self.paren_depth += 1;
+        if argument_list {
+            self.argument_lists.push(self.paren_depth);
+        }
+    }
+
+    fn close_bracket(&mut self) {
+        if self.argument_lists.last() == Some(&self.paren_depth) {
+            self.argument_lists.pop();
+        }
+        self.paren_depth = self.paren_depth.saturating_sub(1);
+    }
+
+    /// Une fin de ligne ne termine l'instruction que si celle-ci est complète :
+    /// pas de parenthèse ouverte, pas d'opérateur en fin de ligne, et pas de
+    /// ligne vide ou d'instruction déjà terminée par `;`.
+    fn newline_ends_statement(&self) -> bool {
+        if self.paren_depth > 0 {
+            return false;
+        }
+        match self.tokens.last() {
+            None => false,
+            Some(token) => {
+                !matches!(
+                    token.token_type,
+                    TokenType::NEWLINE | TokenType::SEMICOLON | TokenType::INDENT | TokenType::DEDENT
+                ) && !Lexer::is_continuation(&token.token_type)
             }
-            Some(' ') | Some('\r') | Some('\t') => self.next_token(),
-            Some('\n') => {
-                self.line += 1;
-                self.next_token()
+        }
+    }
+
+    /// Tokens après lesquels une fin de ligne continue l'instruction.
+    fn is_continuation(token_type: &TokenType) -> bool {
+        matches!(
+            token_type,
+            TokenType::LEFT_PAREN
+                    | TokenType::LEFT_BRACKET
+                    | TokenType::COMMA
+                    | TokenType::DOT
+                    | TokenType::MINUS
+                    | TokenType::PLUS
+                    | TokenType::SLASH
+                    | TokenType::STAR
+                    | TokenType::MODULO
+                    | TokenType::BANG
+                    | TokenType::BANG_EQUAL
+                    | TokenType::EQUAL
+                    | TokenType::EQUAL_EQUAL
+                    | TokenType::GREATER
+                    | TokenType::GREATER_EQUAL
+                    | TokenType::LESS
+                    | TokenType::LESS_EQUAL
+                    | TokenType::ASSIGN
+                    | TokenType::AND
+                    | TokenType::OR
+        )
+    }
+
+    /// Mesure l'indentation en début de ligne et produit un INDENT ou des
+    /// DEDENT. Les lignes vides ou ne contenant qu'un commentaire sont ignorées.
+    fn indent(&mut self) -> Option<TokenType> {
+        let rest = &self.source[self.current..];
+        let width = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        let indent = rest[..width].to_string();
+        let after = &rest[width..];
+        if after.is_empty() || after.starts_with(['\n', '\r', '#']) || after.starts_with("//") {
+            return None;
+        }
+
+        self.at_line_start = false;
+        self.current += width;
+
+        if indent.contains(' ') && indent.contains('\t') {
+            let column = indent
+                .find(if indent.starts_with(' ') { '\t' } else { ' ' })
+                .unwrap_or(0);
+            let span = self.span_in_line(self.line_start + column, self.current);
+            self.error(span, Message::new("E0003", &[]));
+            return None;
+        }
+
+        let top = self.indent_stack.last().unwrap().clone();
+        if indent == top {
+            return None;
+        }
+        if indent.starts_with(&top) {
+            self.indent_stack.push(indent);
+            return Some(TokenType::INDENT);
+        }
+        if top.starts_with(&indent) {
+            let mut dedents = 0;
+            while self.indent_stack.last().unwrap().len() > indent.len() {
+                self.indent_stack.pop();
+                dedents += 1;
             }
-            Some('"') => self.string(),
-            Some(c) => {
-                if c.is_digit(10) {
-                    self.number()
-                } else if c.is_alphabetic() {
-                    self.identifier()
-                } else {
-                   
-                    self.next_token()
-                }
+            if *self.indent_stack.last().unwrap() != indent {
+                let span = self.span_in_line(self.line_start, self.current);
+                self.error(span, Message::new("E0004", &[]));
+                self.indent_stack.push(indent);
             }
-            None => TokenType::EOF,
+            self.pending_dedents = dedents - 1;
+            return Some(TokenType::DEDENT);
         }
+
+        let column = indent
+            .chars()
+            .zip(top.chars())
+            .position(|(a, b)| a != b)
+            .unwrap_or(0);
+        let (attendu, trouve) = match top.chars().nth(column) {
+            Some('\t') => ("indentation.tabulation", "indentation.espace"),
+            _ => ("indentation.espace", "indentation.tabulation"),
+        };
+        let span = self.span_in_line(self.line_start + column, self.current);
+        self.error(
+            span,
+            Message::new(
+                "E0005",
+                &[&catalog::text(trouve, &[]), &catalog::text(attendu, &[])],
+            ),
+        );
+        None
     }
 
     fn string(&mut self) -> TokenType {
-        while self.peek() != Some('"') && !self.is_at_end() {
-            if self.peek() == Some('\n') {
+        let (line, line_start) = (self.line, self.line_start);
+        let mut first_newline = None;
+        while let Some(c) = self.peek() {
+            if c == '"' {
+                break;
+            }
+            if c == '\n' {
+                first_newline.get_or_insert(self.current);
                 self.line += 1;
+                self.line_start = self.current + 1;
             }
             self.advance();
         }
 
         if self.is_at_end() {
-            return TokenType::EOF;
-        } else {
-            self.advance();
+            // On coupe la chaîne à la fin de sa première ligne pour que la
+            // suite du fichier soit analysée normalement.
+            self.line = line;
+            self.line_start = line_start;
+            if let Some(end) = first_newline {
+                self.current = end;
+            }
+            let span = self.token_span();
+            self.error(span, Message::new("E0002", &[]));
+            return TokenType::STRING;
         }
+        self.advance();
 
         TokenType::STRING
     }
 
+    /// Littéral numérique : décimal (`12`, `12.5`, `6.02e23`, `1_000`),
+    /// hexadécimal (`0x1F`), binaire (`0b1010`) ou octal (`0o17`). La valeur
+    /// normalisée est placée dans `literal`.
     fn number(&mut self) -> TokenType {
-        while let Some(c) = self.peek() {
-            if c.is_digit(10) {
+        let first = &self.source[self.start..self.current];
+        let radix = match (first, self.peek()) {
+            ("0", Some('x' | 'X')) => Some(16),
+            ("0", Some('b' | 'B')) => Some(2),
+            ("0", Some('o' | 'O')) => Some(8),
+            _ => None,
+        };
+
+        let value = match radix {
+            Some(radix) => {
                 self.advance();
+                self.radix_number(radix)
+            }
+            None => self.decimal_number(),
+        };
+
+        // Des lettres collées au nombre (`12abc`) rendent le littéral invalide.
+        let trailing = self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_');
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.advance();
+        }
+
+        let value = match value {
+            Ok(_) if trailing => {
+                let text = &self.source[self.start..self.current];
+                Err(Message::new("E0006", &[&text]))
+            }
+            value => value,
+        };
+        self.literal = Some(value.unwrap_or_else(|message| {
+            self.number_error(message);
+            "0".to_string()
+        }));
+        TokenType::NUMBER
+    }
+
+    fn number_error(&mut self, message: Message) {
+        let span = self.token_span();
+        self.error(span, message);
+    }
+
+    /// Consomme des chiffres séparés par `_`. Un séparateur doit être placé
+    /// entre deux chiffres.
+    fn digits(&mut self, radix: u32) -> Result<String, Message> {
+        let mut digits = String::new();
+        let mut last_underscore = false;
+        while let Some(c) = self.peek() {
+            if c == '_' {
+                if digits.is_empty() || last_underscore {
+                    return Err(Message::new("E0007", &[]));
+                }
+                last_underscore = true;
+            } else if c.is_digit(radix) {
+                digits.push(c);
+                last_underscore = false;
             } else {
                 break;
             }
+            self.advance();
+        }
+        if last_underscore {
+            return Err(Message::new("E0008", &[]));
+        }
+        Ok(digits)
+    }
+
+    fn radix_number(&mut self, radix: u32) -> Result<String, Message> {
+        let digits = self.digits(radix)?;
+        if let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+            return Err(Message::new("E0009", &[&c.to_string(), &radix.to_string()]));
+        }
+        if digits.is_empty() {
+            let prefix = &self.source[self.start..self.current];
+            return Err(Message::new("E0010", &[&prefix]));
+        }
+        u64::from_str_radix(&digits, radix)
+            .map(|value| (value as f64).to_string())
+            .map_err(|_| Message::new("E0011", &[&radix]))
+    }
+
+    fn decimal_number(&mut self) -> Result<String, Message> {
+        self.current = self.start;
+        let mut text = self.digits(10)?;
+
+        // Entre les parenthèses d'un appel, `f(1,5)` reçoit deux arguments.
+        let in_arguments = self.argument_lists.last() == Some(&self.paren_depth);
+        let separator = self.peek();
+        let is_separator = separator == Some('.')
+            || (self.decimal_comma && !in_arguments && separator == Some(','));
+        if is_separator && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+            text.push('.');
+            text.push_str(&self.digits(10)?);
         }
 
-        if self.peek() == Some('.') && self.peek_next().unwrap().is_digit(10) {
+        if let Some(e @ ('e' | 'E')) = self.peek() {
+            let rest = &self.source[self.current + 1..];
+            let has_exponent = rest.starts_with(|c: char| c.is_ascii_digit())
+                || (rest.starts_with(['+', '-'])
+                    && rest[1..].starts_with(|c: char| c.is_ascii_digit()));
+            if !has_exponent {
+                return Err(Message::new("E0012", &[&e]));
+            }
             self.advance();
-            while self.peek().unwrap().is_digit(10) {
+            text.push('e');
+            if let Some(sign @ ('+' | '-')) = self.peek() {
                 self.advance();
+                text.push(sign);
             }
+            text.push_str(&self.digits(10)?);
         }
 
-        TokenType::NUMBER
+        Ok(text)
     }
 
-    fn peek_next(&self) -> Option<char> {
-        match self.source.chars().nth(self.current + 1) {
-            Some(c) => Some(c),
-            None => None,
-        }
+    fn is_identifier_start(c: char) -> bool {
+        c.is_alphabetic() || c == '_'
     }
 
     fn identifier(&mut self) -> TokenType {
-        while let Some(c) = self.peek() {
-            if c.is_alphanumeric() {
-                self.advance();
-            } else {
-                break;
-            }
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.advance();
         }
-    
+
         let text = &self.source[self.start..self.current];
         let token_type = match self.keywords.get(text) {
-            Some(t) => (*t).clone(),
+            Some(t) => t.clone(),
             None => TokenType::IDENTIFIER,
         };
-    
+        // Une parenthèse oubliée ne doit pas joindre tout le reste du fichier
+        // à l'instruction : un mot-clé d'instruction en début de ligne ne
+        // peut pas faire partie d'une expression, il en commence une autre.
+        if self.paren_depth > 0
+            && Lexer::starts_statement(&token_type)
+            && self.source[self.line_start..self.start].trim().is_empty()
+        {
+            self.paren_depth = 0;
+            self.argument_lists.clear();
+        }
         token_type
     }
 
+    fn starts_statement(token_type: &TokenType) -> bool {
+        matches!(
+            token_type,
+            TokenType::VAR
+                | TokenType::FUN
+                | TokenType::IF
+                | TokenType::WHILE
+                | TokenType::FOR
+                | TokenType::RETURN
+                | TokenType::PRINT
+                | TokenType::END
+                | TokenType::ENDIF
+                | TokenType::ENDWHILE
+        )
+    }
+
     pub fn scan_tokens(&mut self) {
         loop {
             let token_type = self.next_token();
@@ -260,8 +696,250 @@ impl Lexer<'_> {
                 break;
             }
 
-            self.add_token(token_type);
+            let is_newline = token_type == TokenType::NEWLINE;
+            let literal = self.literal.take();
+            self.add_token_literal(token_type, literal);
+            if is_newline {
+                self.line += 1;
+            }
+        }
+        if self.indentation {
+            self.begin_token();
+            if self.tokens.last().is_some_and(|token| {
+                !matches!(token.token_type, TokenType::NEWLINE | TokenType::DEDENT)
+            }) {
+                self.add_token(TokenType::NEWLINE);
+            }
+            while self.indent_stack.len() > 1 {
+                self.indent_stack.pop();
+                self.add_token(TokenType::DEDENT);
+            }
         }
+        self.begin_token();
         self.add_token(TokenType::EOF);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Lexer<'_> {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        lexer
+    }
+
+    /// Type et lexème de chaque token, sans la fin de fichier.
+    fn lexemes(source: &str) -> Vec<(TokenType, String)> {
+        let mut tokens: Vec<(TokenType, String)> = scan(source)
+            .tokens
+            .into_iter()
+            .map(|token| (token.token_type, token.lexeme))
+            .collect();
+        tokens.pop();
+        tokens
+    }
+
+    #[test]
+    fn accented_identifiers_and_strings() {
+        assert_eq!(
+            lexemes("variable été <- \"à bientôt\""),
+            vec![
+                (TokenType::VAR, "variable".to_string()),
+                (TokenType::IDENTIFIER, "été".to_string()),
+                (TokenType::ASSIGN, "<-".to_string()),
+                (TokenType::STRING, "à bientôt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_are_byte_offsets_and_columns_count_characters() {
+        let lexer = scan("ecrire été + 1");
+        let plus = &lexer.tokens[2];
+        assert_eq!(plus.token_type, TokenType::PLUS);
+        // `é` occupe deux octets.
+        assert_eq!((plus.span.start, plus.span.end), (13, 14));
+        assert_eq!((plus.span.line, plus.span.column), (1, 12));
+    }
+
+    /// Code, ligne, colonne et texte d'une erreur lexicale.
+    type Reported = (&'static str, u32, usize, String);
+
+    fn errors(lexer: &Lexer) -> Vec<Reported> {
+        lexer
+            .errors
+            .iter()
+            .map(|error| {
+                let span = error.span;
+                (error.message.code, span.line, span.column, error.message.text.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unexpected_characters_are_all_reported() {
+        let lexer = scan("variable a <- 1 @ 2\necrire $a & 3");
+        assert_eq!(
+            errors(&lexer),
+            vec![
+                ("E0001", 1, 17, "caractère inattendu '@'.".to_string()),
+                ("E0001", 2, 8, "caractère inattendu '$'.".to_string()),
+                ("E0001", 2, 11, "caractère inattendu '&'.".to_string()),
+            ]
+        );
+        // L'analyse continue après chaque erreur.
+        assert!(lexer.tokens.iter().any(|token| token.lexeme == "3"));
+    }
+
+    #[test]
+    fn unterminated_string_stops_at_end_of_line() {
+        let lexer = scan("ecrire \"bonjour\necrire 2");
+        assert_eq!(
+            errors(&lexer),
+            vec![("E0002", 1, 8, "chaîne de caractères non terminée.".to_string())]
+        );
+        assert_eq!(lexer.tokens[1].lexeme, "bonjour");
+        assert!(lexer.tokens.iter().any(|token| token.lexeme == "2" && token.line == 2));
+    }
+
+    #[test]
+    fn number_at_end_of_input() {
+        for source in ["1", "1.", "0x", "1e"] {
+            let lexer = scan(source);
+            assert_eq!(lexer.tokens[0].token_type, TokenType::NUMBER, "{}", source);
+        }
+    }
+
+    /// Valeur normalisée des littéraux numériques de `source`.
+    fn numbers(source: &str, decimal_comma: bool) -> Vec<String> {
+        let mut lexer = Lexer::new(source);
+        lexer.set_decimal_comma(decimal_comma);
+        lexer.scan_tokens();
+        assert!(lexer.errors.is_empty(), "{:?}", errors(&lexer));
+        lexer
+            .tokens
+            .into_iter()
+            .filter(|token| token.token_type == TokenType::NUMBER)
+            .map(|token| token.literal.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(numbers("0x1F 0XfF 0b1010 0o17", false), ["31", "255", "10", "15"]);
+    }
+
+    #[test]
+    fn digit_separators_and_exponents() {
+        assert_eq!(
+            numbers("1_000_000 0xFF_FF 6.02e23 1E-3 2e+2", false),
+            ["1000000", "65535", "6.02e23", "1e-3", "2e+2"]
+        );
+    }
+
+    #[test]
+    fn decimal_comma() {
+        assert_eq!(numbers("3,14", true), ["3.14"]);
+        assert_eq!(numbers("3,14", false), ["3", "14"]);
+        // Entre les parenthèses d'un appel ou les crochets d'une liste, la
+        // virgule sépare les éléments ; une parenthèse de groupement garde
+        // la virgule décimale.
+        assert_eq!(numbers("f(1,2)", true), ["1", "2"]);
+        assert_eq!(numbers("[1,5]", true), ["1", "5"]);
+        assert_eq!(numbers("f((1,5), 2)", true), ["1.5", "2"]);
+        assert_eq!(numbers("(3,5 + 1) * 2", true), ["3.5", "1", "2"]);
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let cases = [
+            ("12abc", "E0006", "littéral numérique mal formé '12abc'."),
+            ("1__0", "E0007", "séparateur '_' mal placé dans un nombre."),
+            ("1_", "E0008", "un nombre ne peut pas se terminer par '_'."),
+            ("0b102", "E0009", "chiffre '2' invalide en base 2."),
+            ("0x", "E0010", "aucun chiffre après le préfixe '0x'."),
+            ("0xFFFFFFFFFFFFFFFFF", "E0011", "nombre trop grand pour la base 16."),
+            ("1e", "E0012", "exposant sans chiffres après 'e'."),
+        ];
+        for (source, code, text) in cases {
+            let lexer = scan(source);
+            assert_eq!(
+                errors(&lexer),
+                vec![(code, 1, 1, text.to_string())],
+                "{}",
+                source
+            );
+            assert_eq!(lexer.tokens[0].token_type, TokenType::NUMBER);
+        }
+    }
+
+    /// Types des tokens de `source` en mode indentation.
+    fn indented(source: &str) -> (Vec<TokenType>, Vec<Reported>) {
+        let mut lexer = Lexer::new(source);
+        lexer.set_indentation(true);
+        lexer.scan_tokens();
+        let types = lexer.tokens.iter().map(|token| token.token_type.clone()).collect();
+        (types, errors(&lexer))
+    }
+
+    #[test]
+    fn indentation_produces_indent_and_dedent() {
+        use TokenType::*;
+        let (types, errors) = indented("si a alors\n    si b alors\n        ecrire 1\n\n    ecrire 2\necrire 3");
+        assert!(errors.is_empty());
+        assert_eq!(
+            types,
+            vec![
+                IF, IDENTIFIER, THEN, NEWLINE, INDENT, IF, IDENTIFIER, THEN, NEWLINE, INDENT,
+                PRINT, NUMBER, NEWLINE, DEDENT, PRINT, NUMBER, NEWLINE, DEDENT, PRINT, NUMBER,
+                NEWLINE, EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn indentation_pragma() {
+        let lexer = scan("# mode: indentation\nsi a alors\n    ecrire 1");
+        assert!(lexer.tokens.iter().any(|token| token.token_type == TokenType::INDENT));
+        let lexer = scan("si a alors\n    ecrire 1 finsi");
+        assert!(!lexer.tokens.iter().any(|token| token.token_type == TokenType::INDENT));
+    }
+
+    #[test]
+    fn inconsistent_indentation() {
+        let (_, errors) = indented("si a alors\n    ecrire 1\n    \tecrire 2");
+        assert_eq!(
+            errors,
+            vec![("E0003", 3, 5, "mélange de tabulations et d'espaces dans l'indentation.".to_string())]
+        );
+        let (_, errors) = indented("si a alors\n    ecrire 1\n  ecrire 2");
+        assert_eq!(
+            errors,
+            vec![("E0004", 3, 1, "l'indentation ne correspond à aucun bloc englobant.".to_string())]
+        );
+        let (_, errors) = indented("si a alors\n    ecrire 1\n\tecrire 2");
+        assert_eq!(
+            errors,
+            vec![(
+                "E0005",
+                3,
+                1,
+                "indentation incohérente : une tabulation ici, un espace dans le bloc englobant."
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn long_sources_are_scanned_in_one_pass() {
+        let source = "variable déjà <- \"é\" + 1\n".repeat(50_000);
+        let lexer = scan(&source);
+        assert!(lexer.errors.is_empty());
+        // Six tokens et une fin de ligne par ligne, plus la fin de fichier.
+        assert_eq!(lexer.tokens.len(), 50_000 * 7 + 1);
+        let last = &lexer.tokens[lexer.tokens.len() - 2];
+        assert_eq!(last.span.line, 50_000);
+    }
+}
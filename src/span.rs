@@ -0,0 +1,89 @@
+use std::fmt;
+
+/// Identifiant d'un fichier source (le script, ou une saisie du REPL).
+pub type FileId = usize;
+
+/// Portion du source couverte par un token ou un nœud de l'arbre.
+/// `start` et `end` sont des positions en octets (`end` exclu) ; les lignes et
+/// colonnes commencent à 1, `end_column` désigne la colonne après le dernier
+/// caractère.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+    pub line: u32,
+    pub column: usize,
+    pub end_line: u32,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Span allant du début de `self` à la fin de `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            file: self.file,
+            start: self.start,
+            end: other.end.max(self.start),
+            line: self.line,
+            column: self.column,
+            end_line: other.end_line,
+            end_column: other.end_column,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Un fichier source connu du gestionnaire de diagnostics.
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    /// Texte de la ligne `line` (à partir de 1), sans le saut de ligne.
+    pub fn line(&self, line: u32) -> Option<&str> {
+        let index = (line as usize).checked_sub(1)?;
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.text.len());
+        Some(self.text[start..end].trim_end_matches(['\n', '\r']))
+    }
+}
+
+/// Ensemble des sources analysées, indexé par `FileId`.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, name: &str, text: &str) -> FileId {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.files.push(SourceFile {
+            name: name.to_string(),
+            text: text.to_string(),
+            line_starts,
+        });
+        self.files.len() - 1
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+}
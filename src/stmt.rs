@@ -1,4 +1,6 @@
-use crate::{expr::Expr, visitor::StmtVisitor, token::Token};
+use std::rc::Rc;
+
+use crate::{expr::{Expr, NodeId}, span::Span, visitor::StmtVisitor, token::Token};
 
 
 #[derive(Debug,Clone)]
@@ -9,7 +11,9 @@ pub enum Stmt {
     Block(BlockStmt),
     If(IfStmt),
     While(WhileStmt),
-    Function(FunctionStmt),
+    /// Partagée avec les valeurs `Function` créées à chaque exécution de la
+    /// déclaration.
+    Function(Rc<FunctionStmt>),
     Return(ReturnStmt),
 }
 
@@ -17,19 +21,26 @@ pub enum Stmt {
 pub struct ReturnStmt {
     pub keyword: Token,
     pub value: Option<Expr>,
+    pub span: Span,
+    /// `retourner f(...)` : l'appel termine la fonction, il peut remplacer
+    /// l'appel en cours au lieu de s'y empiler.
+    pub tail_call: bool,
 }
 
 #[derive(Debug,Clone)]
 pub struct WhileStmt {
     pub condition: Expr,
     pub body: Box<Stmt>,
+    pub span: Span,
 }
 
 #[derive(Debug,Clone)]
 pub struct FunctionStmt {
+    pub id: NodeId,
     pub name: Token,
     pub params: Vec<Token>,
     pub body: Vec<Stmt>,
+    pub span: Span,
 }
 
 #[derive(Debug,Clone)]
@@ -37,28 +48,47 @@ pub struct IfStmt {
     pub condition: Expr,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
+    pub span: Span,
 }
 #[derive(Debug,Clone)]
 pub struct VarStmt {
+    pub id: NodeId,
     pub name: Token,
     pub initializer: Option<Expr>,
+    pub span: Span,
 }
 #[derive(Debug,Clone)]
 pub struct BlockStmt {
     pub statements: Vec<Stmt>,
+    pub span: Span,
 }
 
 
 #[derive(Debug,Clone)]
 pub struct ExpressionStmt {
     pub expression: Expr,
+    pub span: Span,
 }
 #[derive(Debug,Clone)]
 pub struct PrintStmt {
     pub expression: Expr,
+    pub span: Span,
 }
 
 impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression(stmt) => stmt.span,
+            Stmt::Print(stmt) => stmt.span,
+            Stmt::Var(stmt) => stmt.span,
+            Stmt::Block(stmt) => stmt.span,
+            Stmt::If(stmt) => stmt.span,
+            Stmt::While(stmt) => stmt.span,
+            Stmt::Function(stmt) => stmt.span,
+            Stmt::Return(stmt) => stmt.span,
+        }
+    }
+
     pub fn accept<V: StmtVisitor>(&self, visitor: &mut V) -> V::Output {
         match self {
             Stmt::Expression(ref stmt) => stmt.accept(visitor),
@@ -80,7 +110,7 @@ impl ReturnStmt {
     }
 }
 impl FunctionStmt {
-    pub fn accept<V: StmtVisitor>(&self, visitor: &mut V) -> V::Output {
+    pub fn accept<V: StmtVisitor>(self: &Rc<Self>, visitor: &mut V) -> V::Output {
         visitor.visit_function_stmt(self)
     }
 }
@@ -0,0 +1,107 @@
+use crate::{catalog, scanner::Lexer};
+
+/// Mots-clés anglais (ou d'autres langages) qu'on tape par habitude, avec
+/// leur équivalent français.
+const ENGLISH_KEYWORDS: &[(&str, &str)] = &[
+    ("if", "si"),
+    ("then", "alors"),
+    ("else", "sinon"),
+    ("endif", "finsi"),
+    ("while", "tantque"),
+    ("do", "faire"),
+    ("print", "ecrire"),
+    ("return", "retourner"),
+    ("var", "variable"),
+    ("let", "variable"),
+    ("fun", "fonction"),
+    ("fn", "fonction"),
+    ("function", "fonction"),
+    ("def", "fonction"),
+    ("begin", "debut"),
+    ("end", "fin"),
+    ("true", "vrai"),
+    ("false", "faux"),
+    ("nil", "VIDE"),
+    ("null", "VIDE"),
+    ("and", "et"),
+    ("or", "ou"),
+];
+
+/// Distance d'édition (Levenshtein) entre deux mots, en caractères.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Candidat le plus proche de `name`, s'il l'est assez pour être une faute
+/// de frappe plausible. À distance égale, l'ordre alphabétique départage.
+fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max = (name.chars().count() + 1) / 3;
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Aide pour un nom inconnu : équivalent français d'un mot-clé anglais, ou
+/// nom visible / mot-clé le plus proche.
+pub fn help<'a>(name: &str, visible: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    if let Some((_, french)) = ENGLISH_KEYWORDS.iter().find(|(english, _)| *english == name) {
+        return Some(catalog::text("suggestion.anglais", &[&name, french]));
+    }
+    let keywords = Lexer::keyword_table();
+    let mut candidates: Vec<&str> = visible.into_iter().collect();
+    candidates.extend(keywords.keys().map(String::as_str));
+    closest(name, candidates).map(|candidate| catalog::text("suggestion.proche", &[&candidate]))
+}
+
+/// Comme `help`, mais seulement parmi les mots-clés : pour le parser, qui ne
+/// connaît pas les noms en portée.
+pub fn keyword_help(name: &str) -> Option<String> {
+    help(name, std::iter::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance() {
+        assert_eq!(distance("total", "total"), 0);
+        assert_eq!(distance("totl", "total"), 1);
+        assert_eq!(distance("été", "ete"), 2);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn close_names_are_suggested() {
+        assert_eq!(help("totl", ["total", "titre"]).as_deref(), Some("vouliez-vous dire 'total' ?"));
+        assert_eq!(help("ecrir", []).as_deref(), Some("vouliez-vous dire 'ecrire' ?"));
+        // À distance égale, l'ordre alphabétique départage.
+        assert_eq!(help("abcd", ["abce", "abcc"]).as_deref(), Some("vouliez-vous dire 'abcc' ?"));
+    }
+
+    #[test]
+    fn distant_names_are_not_suggested() {
+        assert_eq!(help("x", ["y"]), None);
+        assert_eq!(help("compteur", ["somme", "resultat"]), None);
+    }
+
+    #[test]
+    fn english_keywords_are_translated() {
+        assert_eq!(help("while", ["whilo"]).as_deref(), Some("en frenchy, 'while' s'écrit 'tantque'."));
+        assert_eq!(keyword_help("print").as_deref(), Some("en frenchy, 'print' s'écrit 'ecrire'."));
+    }
+}
@@ -0,0 +1,88 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+/// Identifiant interné : deux symboles sont égaux si et seulement si leurs
+/// noms le sont, et se comparent comme des entiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// Table des noms internés, partagée par tout le programme.
+struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    names: Vec<Rc<str>>,
+}
+
+impl Interner {
+    /// Table qui contient déjà le texte vide, sous `Symbol::EMPTY`.
+    fn new() -> Interner {
+        let empty: Rc<str> = Rc::from("");
+        Interner {
+            symbols: HashMap::from([(Rc::clone(&empty), Symbol::EMPTY)]),
+            names: vec![empty],
+        }
+    }
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::new());
+}
+
+impl Symbol {
+    /// Symbole du texte vide, porté par les tokens qui ne sont pas des
+    /// identifiants.
+    pub const EMPTY: Symbol = Symbol(0);
+
+    pub fn intern(name: &str) -> Symbol {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+            if let Some(&symbol) = interner.symbols.get(name) {
+                return symbol;
+            }
+            let symbol = Symbol(interner.names.len() as u32);
+            let name: Rc<str> = Rc::from(name);
+            interner.names.push(Rc::clone(&name));
+            interner.symbols.insert(name, symbol);
+            symbol
+        })
+    }
+
+    pub fn name(self) -> Rc<str> {
+        INTERNER.with(|interner| Rc::clone(&interner.borrow().names[self.0 as usize]))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        span::Span,
+        token::{Token, TokenType},
+    };
+
+    #[test]
+    fn equal_names_share_a_symbol() {
+        let total = Symbol::intern("total");
+        assert_eq!(Symbol::intern("total"), total);
+        assert_ne!(Symbol::intern("totale"), total);
+        assert_eq!(&*Symbol::intern("été").name(), "été");
+        assert_eq!(total.to_string(), "total");
+        assert!(Rc::ptr_eq(&total.name(), &Symbol::intern("total").name()));
+    }
+
+    #[test]
+    fn only_identifiers_are_interned() {
+        assert_eq!(Symbol::intern(""), Symbol::EMPTY);
+        assert_eq!(&*Symbol::EMPTY.name(), "");
+        let token = |token_type, lexeme: &str| {
+            Token::new(token_type, lexeme.to_string(), lexeme.to_string(), 1, Span::default())
+        };
+        assert_eq!(token(TokenType::IDENTIFIER, "x").symbol, Symbol::intern("x"));
+        assert_eq!(token(TokenType::PLUS, "+").symbol, Symbol::EMPTY);
+        assert_eq!(token(TokenType::STRING, "x").symbol, Symbol::EMPTY);
+    }
+}
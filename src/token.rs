@@ -1,5 +1,8 @@
 use std::hash::{Hash, Hasher};
 
+use crate::{span::Span, symbol::Symbol};
+
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq,Clone)]
 pub enum TokenType {
     // Single-character tokens.
@@ -60,6 +63,12 @@ pub enum TokenType {
     DO,
     THEN,
 
+    // Fin de ligne significative (terminateur d'instruction).
+    NEWLINE,
+    // Blocs délimités par l'indentation (mode `# mode: indentation`).
+    INDENT,
+    DEDENT,
+
     EOF,
 }
 #[derive(Debug, PartialEq,Clone)]
@@ -68,15 +77,26 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<String>,
     pub line: u32,
+    pub span: Span,
+    /// Nom interné d'un identifiant ; celui du texte vide pour les autres
+    /// tokens.
+    pub symbol: Symbol,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: String, line: u32) -> Token {
+    pub fn new(token_type: TokenType, lexeme: String, literal: String, line: u32, span: Span) -> Token {
+        let symbol = if token_type == TokenType::IDENTIFIER {
+            Symbol::intern(&lexeme)
+        } else {
+            Symbol::EMPTY
+        };
         Token {
             token_type,
             lexeme,
             literal:Some(literal),
             line,
+            span,
+            symbol,
         }
     }
 }
@@ -2,52 +2,44 @@ use std::cell::RefCell;
 use std::cmp::PartialEq;
 use std::cmp::PartialOrd;
 use std::fmt;
-use std::fmt::Pointer;
 use std::ops::{Add, Div, Mul, Rem, Sub};
 use std::rc::Rc;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering;
 
 use crate::callable::Callable;
-use crate::environment;
+use crate::catalog::{self, Message};
 use crate::environment::Environment;
 use crate::error::RuntimeError;
+use crate::expr::CallExp;
 use crate::interpret_visitor::InterpretVisitor;
 use crate::stmt::FunctionStmt;
-use crate::stmt::Stmt;
-
-static GLOBAL_COUNTER: AtomicUsize = AtomicUsize::new(0);
-
-fn increment_counter() {
-    GLOBAL_COUNTER.fetch_add(1, Ordering::SeqCst);
-}
-
-fn get_counter() -> usize {
-    GLOBAL_COUNTER.load(Ordering::SeqCst)
-}
-
+use crate::vm::Closure;
 
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
-    String(String),
+    /// Texte partagé : copier la valeur ne copie pas les caractères.
+    String(Rc<str>),
     Boolean(bool),
     Nil,
     NativeFunction(NativeFunction),
     Function(Function),
+    /// Fonction compilée pour la machine virtuelle.
+    Closure(Rc<Closure>),
 }
 
 #[derive(Clone)]
 pub struct NativeFunction {
     pub arity: usize,
     pub name: String,
-    pub function: fn(&mut InterpretVisitor, Vec<Value>) -> Result<Value, RuntimeError>,
+    pub function: fn(Vec<Value>) -> Result<Value, Message>,
 }
 
 #[derive(Clone)]
 pub struct Function {
-    pub stmt: FunctionStmt,
-    pub closure: Rc<RefCell<Environment>>,
+    pub stmt: Rc<FunctionStmt>,
+    /// Environnement de la déclaration ; absent au niveau supérieur, où les
+    /// variables sont des globales.
+    pub closure: Option<Rc<RefCell<Environment>>>,
 }
 
 impl fmt::Debug for Function {
@@ -67,24 +59,31 @@ impl Callable for Function {
         &self,
         interpreter: &mut InterpretVisitor,
         arguments: Vec<Value>,
+        _call: &CallExp,
     ) -> Result<Value, RuntimeError> {
 
 
-        //println!("////////////////////////////////////{:?}",&self.closure);
-        let environment = Rc::new(RefCell::new(Environment::new_enclosed(&self.closure,get_counter())));
-        increment_counter();
+        // Un appel terminal remplace la fonction exécutée et recommence la
+        // boucle : la pile Rust ne grandit pas.
+        let mut function = self.clone();
+        let mut arguments = arguments;
+        loop {
+            let environment = Environment::new_enclosed(function.closure.as_ref());
 
-       // println!("Function call : {:?}",self.stmt.name.lexeme);
-
-        for (param, arg) in self.stmt.params.iter().zip(arguments.iter()) {
-            environment
-                .borrow_mut()
-                .define(param.lexeme.clone(), arg.clone());
-        }
-        match interpreter.execute_block(&self.stmt.body, environment){
-            Ok(_) => Ok(Value::Nil),
-            Err(RuntimeError::Return(value)) => Ok(value),
-            Err(e) => Err(e),
+            // Les paramètres occupent les premières cases, dans l'ordre.
+            for (slot, arg) in arguments.into_iter().enumerate() {
+                environment.borrow_mut().define(slot, arg);
+            }
+            match interpreter.execute_block(&function.stmt.body, environment) {
+                Ok(_) => return Ok(Value::Nil),
+                Err(RuntimeError::Return(value)) => return Ok(value),
+                Err(RuntimeError::TailCall { function: next, arguments: next_arguments, call_site }) => {
+                    interpreter.replace_frame(&next, call_site, &next_arguments);
+                    function = next;
+                    arguments = next_arguments;
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
 }
@@ -105,49 +104,71 @@ impl Callable for NativeFunction {
 
     fn call(
         &self,
-        interpreter: &mut InterpretVisitor,
+        _interpreter: &mut InterpretVisitor,
         arguments: Vec<Value>,
+        call: &CallExp,
     ) -> Result<Value, RuntimeError> {
-        (self.function)(interpreter, arguments)
+        (self.function)(arguments).map_err(|message| RuntimeError::at(call.span, message))
+    }
+}
+
+impl Value {
+    /// Nom du type tel qu'il apparaît dans les messages d'erreur.
+    pub fn type_name(&self) -> String {
+        let key = match self {
+            Value::Number(_) => "type.nombre",
+            Value::String(_) => "type.texte",
+            Value::Boolean(_) => "type.booleen",
+            Value::Nil => "type.vide",
+            Value::NativeFunction(_) | Value::Function(_) | Value::Closure(_) => "type.fonction",
+        };
+        catalog::text(key, &[])
+    }
+
+    fn type_error(code: &'static str, left: &Value, right: &Value) -> Message {
+        Message::new(code, &[&left.type_name(), &right.type_name()])
     }
 }
 
+// Les opérateurs renvoient un message d'erreur plutôt que de paniquer :
+// l'interpréteur y rattache le token de l'opérateur.
+
 impl Add for Value {
-    type Output = Self;
+    type Output = Result<Value, Message>;
 
-    fn add(self, other: Self) -> Self {
+    fn add(self, other: Self) -> Self::Output {
         match (self, other) {
-            (Value::Number(n1), Value::Number(n2)) => Value::Number(n1 + n2),
-            (Value::String(s1), Value::String(s2)) => Value::String(s1 + &s2),
-            _ => panic!("Addition not supported for these Value types"),
+            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 + n2)),
+            (Value::String(s1), Value::String(s2)) => Ok(Value::String(format!("{}{}", s1, s2).into())),
+            (left, right) => Err(Value::type_error("E0306", &left, &right)),
         }
     }
 }
 
 impl Rem for Value {
-    type Output = Self;
+    type Output = Result<Value, Message>;
 
-    fn rem(self, other: Self) -> Self {
+    fn rem(self, other: Self) -> Self::Output {
         match (self, other) {
             (Value::Number(n1), Value::Number(n2)) => {
                 if n2 != 0.0 {
-                    Value::Number(n1 % n2)
+                    Ok(Value::Number(n1 % n2))
                 } else {
-                    panic!("Cannot divide by zero")
+                    Err(Message::new("E0312", &[]))
                 }
             }
-            _ => panic!("Modulo operation not supported for these Value types"),
+            (left, right) => Err(Value::type_error("E0310", &left, &right)),
         }
     }
 }
 
 impl Sub for Value {
-    type Output = Self;
+    type Output = Result<Value, Message>;
 
-    fn sub(self, other: Self) -> Self {
+    fn sub(self, other: Self) -> Self::Output {
         match (self, other) {
-            (Value::Number(n1), Value::Number(n2)) => Value::Number(n1 - n2),
-            _ => panic!("Subtraction not supported for these Value types"),
+            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 - n2)),
+            (left, right) => Err(Value::type_error("E0307", &left, &right)),
         }
     }
 }
@@ -174,29 +195,29 @@ impl PartialOrd for Value {
 }
 
 impl Mul for Value {
-    type Output = Self;
+    type Output = Result<Value, Message>;
 
-    fn mul(self, other: Self) -> Self {
+    fn mul(self, other: Self) -> Self::Output {
         match (self, other) {
-            (Value::Number(n1), Value::Number(n2)) => Value::Number(n1 * n2),
-            _ => panic!("Multiplication not supported for these Value types"),
+            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 * n2)),
+            (left, right) => Err(Value::type_error("E0308", &left, &right)),
         }
     }
 }
 
 impl Div for Value {
-    type Output = Self;
+    type Output = Result<Value, Message>;
 
-    fn div(self, other: Self) -> Self {
+    fn div(self, other: Self) -> Self::Output {
         match (self, other) {
             (Value::Number(n1), Value::Number(n2)) => {
                 if n2 != 0.0 {
-                    Value::Number(n1 / n2)
+                    Ok(Value::Number(n1 / n2))
                 } else {
-                    panic!("Cannot divide by zero")
+                    Err(Message::new("E0311", &[]))
                 }
             }
-            _ => panic!("Division not supported for these Value types"),
+            (left, right) => Err(Value::type_error("E0309", &left, &right)),
         }
     }
 }
@@ -210,6 +231,50 @@ impl std::fmt::Display for Value {
             Value::Nil => write!(f, "nil"),
             Value::NativeFunction(nf) => write!(f, "<native fn {}>", nf.name),
             Value::Function(func) => write!(f, "{:?}", func),
+            Value::Closure(closure) => write!(f, "{:?}", closure),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Value {
+        Value::String(value.into())
+    }
+
+    /// Code et texte de l'erreur d'une opération.
+    fn error(result: Result<Value, Message>) -> (&'static str, String) {
+        let message = result.unwrap_err();
+        (message.code, message.text)
+    }
+
+    #[test]
+    fn operators_on_matching_types() {
+        assert_eq!((Value::Number(1.0) + Value::Number(2.0)).unwrap(), Value::Number(3.0));
+        assert_eq!((text("a") + text("b")).unwrap(), text("ab"));
+        assert_eq!((Value::Number(7.0) % Value::Number(4.0)).unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn type_mismatches_are_errors() {
+        assert_eq!(
+            error(text("a") + Value::Number(1.0)),
+            ("E0306", "impossible d'additionner texte et nombre".to_string())
+        );
+        assert_eq!(error(Value::Nil - Value::Number(1.0)).0, "E0307");
+        assert_eq!(error(Value::Boolean(true) * Value::Number(1.0)).0, "E0308");
+        assert_eq!(error(text("a") / text("b")).0, "E0309");
+        assert_eq!(error(text("a") % Value::Number(2.0)).0, "E0310");
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert_eq!(
+            error(Value::Number(1.0) / Value::Number(0.0)),
+            ("E0311", "division par zéro".to_string())
+        );
+        assert_eq!(error(Value::Number(1.0) % Value::Number(0.0)).0, "E0312");
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{expr::{BinaryExpr, GroupingExpr, LiteralExpr, UnaryExpr, VariableExpr}, stmt::{Stmt, ExpressionStmt, PrintStmt}};
+use std::rc::Rc;
+
+use crate::{expr::{BinaryExpr, GroupingExpr, LiteralExpr, UnaryExpr, VariableExpr}, stmt::{ExpressionStmt, PrintStmt}};
 
 
 
@@ -22,6 +24,6 @@ pub trait StmtVisitor {
     fn visit_block_stmt(&mut self, stmt: &crate::stmt::BlockStmt) -> Self::Output;
     fn visit_if_stmt(&mut self, stmt: &crate::stmt::IfStmt) -> Self::Output;
     fn visit_while_stmt(&mut self, stmt: &crate::stmt::WhileStmt) -> Self::Output;
-    fn visit_function_stmt(&mut self, stmt: &crate::stmt::FunctionStmt) -> Self::Output;
+    fn visit_function_stmt(&mut self, stmt: &Rc<crate::stmt::FunctionStmt>) -> Self::Output;
     fn visit_return_stmt(&mut self, stmt: &crate::stmt::ReturnStmt) -> Self::Output;
 }
\ No newline at end of file
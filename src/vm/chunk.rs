@@ -0,0 +1,166 @@
+use std::rc::Rc;
+
+use crate::{span::Span, symbol::Symbol, value::Value};
+
+/// Instructions de la machine virtuelle. Chaque instruction tient sur un
+/// octet, suivi de ses opérandes, en gros-boutiste : `u8` pour les
+/// emplacements locaux, les variables capturées et le nombre d'arguments,
+/// `u16` pour les sauts et les fonctions, 3 octets pour les constantes et
+/// les noms de globales.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// Constante (3 octets) : l'empile.
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    /// `u8` : emplacement dans la fenêtre de l'appel courant.
+    GetLocal,
+    SetLocal,
+    /// Indice (3 octets) du nom de la globale dans `Chunk::names`.
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    /// `u8` : indice dans les variables capturées de la fonction courante.
+    GetUpvalue,
+    SetUpvalue,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Not,
+    Negate,
+    Print,
+    /// `u16` : déplacement vers l'avant.
+    Jump,
+    /// `u16` : saut si le sommet de pile est faux, sans le dépiler.
+    JumpIfFalse,
+    /// `u16` : déplacement vers l'arrière.
+    Loop,
+    /// `u8` : nombre d'arguments. L'octet d'opérande porte la position de
+    /// la valeur appelée, pour l'erreur « impossible d'appeler ».
+    Call,
+    /// Comme `Call`, pour `retourner f(...)` : une fonction compilée prend la
+    /// place de l'appel en cours. Toujours suivie de `Return`, exécuté quand
+    /// la valeur appelée est une fonction native.
+    TailCall,
+    /// `u16` : indice dans `Chunk::functions`, suivi pour chaque variable
+    /// capturée d'un octet « locale ? » et d'un octet d'indice.
+    Closure,
+    /// Ferme la variable capturée au sommet de pile avant de la dépiler.
+    CloseUpvalue,
+    Return,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 34] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::Equal,
+        OpCode::NotEqual,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Modulo,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::TailCall,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        OpCode::ALL.get(byte as usize).copied()
+    }
+}
+
+/// Code compilé d'une fonction, avec ses constantes et les fonctions qu'elle
+/// déclare.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    /// Portion du source à l'origine de chaque octet de `code`, pour les
+    /// erreurs d'exécution.
+    pub spans: Vec<Span>,
+    /// Nombres et textes littéraux.
+    pub constants: Vec<Value>,
+    /// Noms des globales lues ou écrites.
+    pub names: Vec<Symbol>,
+    pub functions: Vec<Rc<Prototype>>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, span: Span) {
+        self.code.push(byte);
+        self.spans.push(span);
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// Taille de l'instruction qui commence à `offset`, opérandes compris,
+    /// ou `None` si l'octet n'est pas une instruction connue.
+    pub fn instruction_len(&self, offset: usize) -> Option<usize> {
+        let op = OpCode::from_byte(*self.code.get(offset)?)?;
+        Some(match op {
+            OpCode::Constant | OpCode::GetGlobal | OpCode::DefineGlobal | OpCode::SetGlobal => 4,
+            OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::Call
+            | OpCode::TailCall => 2,
+            OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => 3,
+            OpCode::Closure => {
+                let index = u16::from_be_bytes([*self.code.get(offset + 1)?, *self.code.get(offset + 2)?]);
+                3 + 2 * self.functions.get(index as usize)?.upvalue_count
+            }
+            _ => 1,
+        })
+    }
+
+    pub fn read_constant_index(&self, offset: usize) -> usize {
+        u32::from_be_bytes([0, self.code[offset], self.code[offset + 1], self.code[offset + 2]]) as usize
+    }
+}
+
+/// Fonction compilée. Le programme principal est une fonction sans
+/// paramètre nommée `<script>`.
+#[derive(Debug, Default)]
+pub struct Prototype {
+    pub name: String,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
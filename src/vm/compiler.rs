@@ -0,0 +1,612 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    catalog::Message,
+    diagnostic::Diagnostic,
+    expr::{
+        AssignVarExpr, BinaryExpr, CallExp, Expr, GroupingExpr, Literal, LiteralExpr, LogicalExpr,
+        UnaryExpr, VariableExpr,
+    },
+    span::Span,
+    symbol::Symbol,
+    stmt::{
+        BlockStmt, ExpressionStmt, FunctionStmt, IfStmt, PrintStmt, ReturnStmt, Stmt, VarStmt,
+        WhileStmt,
+    },
+    token::Token,
+    value::Value,
+    visitor::{ExprVisitor, StmtVisitor},
+};
+
+use super::chunk::{Chunk, OpCode, Prototype};
+
+type CompileResult = Result<(), Box<Diagnostic>>;
+
+/// Plus grand indice de constante, codé sur 3 octets.
+const MAX_CONSTANT: usize = (1 << 24) - 1;
+
+/// Variable locale d'une fonction, rangée dans l'emplacement de pile qui
+/// porte son indice.
+struct Local {
+    name: Symbol,
+    depth: usize,
+    /// Vrai si une fonction imbriquée la capture : elle doit être fermée,
+    /// pas simplement dépilée, à la sortie de son bloc.
+    captured: bool,
+}
+
+/// Variable capturée : un emplacement local de la fonction englobante, ou
+/// une de ses propres variables capturées.
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+/// Clé de déduplication des constantes.
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Number(u64),
+    String(Rc<str>),
+}
+
+/// Fonction en cours de compilation.
+struct FunctionState {
+    prototype: Prototype,
+    constants: HashMap<ConstantKey, u32>,
+    names: HashMap<Symbol, u32>,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+}
+
+impl FunctionState {
+    fn new(name: &str, arity: usize) -> Self {
+        FunctionState {
+            prototype: Prototype {
+                name: name.to_string(),
+                arity,
+                ..Prototype::default()
+            },
+            constants: HashMap::new(),
+            names: HashMap::new(),
+            // L'emplacement 0 contient la fonction appelée.
+            locals: vec![Local {
+                name: Symbol::intern(""),
+                depth: 0,
+                captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+        }
+    }
+
+    fn resolve_local(&self, name: Symbol) -> Option<u8> {
+        self.locals
+            .iter()
+            .rposition(|local| local.depth > 0 && local.name == name)
+            .map(|slot| slot as u8)
+    }
+}
+
+/// Traduit l'arbre résolu en bytecode pour la machine virtuelle. Les
+/// variables du niveau supérieur sont des globales nommées ; toutes les
+/// autres deviennent des emplacements de pile ou des variables capturées.
+pub struct Compiler {
+    functions: Vec<FunctionState>,
+}
+
+impl Compiler {
+    pub fn compile(statements: &[Stmt]) -> Result<Rc<Prototype>, Box<Diagnostic>> {
+        let mut compiler = Compiler {
+            functions: vec![FunctionState::new("<script>", 0)],
+        };
+        for stmt in statements {
+            stmt.accept(&mut compiler)?;
+        }
+        let end = statements.last().map(Stmt::span).unwrap_or_default();
+        compiler.emit_op(OpCode::Nil, end);
+        compiler.emit_op(OpCode::Return, end);
+        let script = compiler.functions.pop().expect("fonction principale");
+        Ok(Rc::new(script.prototype))
+    }
+
+    fn current(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().expect("fonction en cours")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.current().prototype.chunk
+    }
+
+    fn error(&self, code: &'static str, span: Span) -> Box<Diagnostic> {
+        let name = &self.functions.last().expect("fonction en cours").prototype.name;
+        Box::new(Diagnostic::error(Message::new(code, &[name])).with_label(span, ""))
+    }
+
+    fn emit_op(&mut self, op: OpCode, span: Span) {
+        self.chunk().write(op as u8, span);
+    }
+
+    fn emit_with_u8(&mut self, op: OpCode, operand: u8, span: Span) {
+        self.emit_op(op, span);
+        self.chunk().write(operand, span);
+    }
+
+    fn emit_constant_op(&mut self, op: OpCode, index: u32, span: Span) {
+        self.emit_op(op, span);
+        for byte in &index.to_be_bytes()[1..] {
+            self.chunk().write(*byte, span);
+        }
+    }
+
+    fn emit_with_u16(&mut self, op: OpCode, operand: u16, span: Span) {
+        self.emit_op(op, span);
+        for byte in operand.to_be_bytes() {
+            self.chunk().write(byte, span);
+        }
+    }
+
+    fn make_constant(&mut self, value: Value, span: Span) -> Result<u32, Box<Diagnostic>> {
+        let key = match &value {
+            Value::Number(number) => ConstantKey::Number(number.to_bits()),
+            Value::String(text) => ConstantKey::String(text.clone()),
+            _ => unreachable!("seuls les nombres et les textes sont des constantes"),
+        };
+        if let Some(index) = self.current().constants.get(&key) {
+            return Ok(*index);
+        }
+        let index = self.chunk().constants.len();
+        if index > MAX_CONSTANT {
+            return Err(self.error("E0501", span));
+        }
+        self.chunk().constants.push(value);
+        self.current().constants.insert(key, index as u32);
+        Ok(index as u32)
+    }
+
+    /// Indice du nom de globale `name` dans `Chunk::names`.
+    fn name_index(&mut self, name: &Token) -> Result<u32, Box<Diagnostic>> {
+        if let Some(index) = self.current().names.get(&name.symbol) {
+            return Ok(*index);
+        }
+        let index = self.chunk().names.len();
+        if index > MAX_CONSTANT {
+            return Err(self.error("E0501", name.span));
+        }
+        self.chunk().names.push(name.symbol);
+        self.current().names.insert(name.symbol, index as u32);
+        Ok(index as u32)
+    }
+
+    /// Appel `op` (`Call` ou `TailCall`) : la valeur appelée, ses arguments
+    /// puis l'instruction.
+    fn call(&mut self, expr: &CallExp, op: OpCode) -> CompileResult {
+        expr.callee.accept(self)?;
+        for argument in &expr.arguments {
+            argument.accept(self)?;
+        }
+        let count = u8::try_from(expr.arguments.len()).map_err(|_| {
+            Box::new(Diagnostic::error(Message::new("E0505", &[])).with_label(expr.span, ""))
+        })?;
+        self.emit_op(op, expr.span);
+        self.chunk().write(count, expr.callee.span());
+        Ok(())
+    }
+
+    /// Émet un saut dont la destination sera fixée par `patch_jump`.
+    fn emit_jump(&mut self, op: OpCode, span: Span) -> usize {
+        self.emit_with_u16(op, u16::MAX, span);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize, span: Span) -> CompileResult {
+        let distance = self.chunk().code.len() - offset - 2;
+        let distance = u16::try_from(distance).map_err(|_| self.error("E0504", span))?;
+        let [high, low] = distance.to_be_bytes();
+        self.chunk().code[offset] = high;
+        self.chunk().code[offset + 1] = low;
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, start: usize, span: Span) -> CompileResult {
+        let distance = self.chunk().code.len() + 3 - start;
+        let distance = u16::try_from(distance).map_err(|_| self.error("E0504", span))?;
+        self.emit_with_u16(OpCode::Loop, distance, span);
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.current().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self, span: Span) {
+        let state = self.current();
+        state.scope_depth -= 1;
+        let depth = state.scope_depth;
+        while let Some(local) = self.current().locals.last() {
+            if local.depth <= depth {
+                break;
+            }
+            let op = if local.captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            };
+            self.emit_op(op, span);
+            self.current().locals.pop();
+        }
+    }
+
+    fn add_local(&mut self, name: &Token) -> CompileResult {
+        if self.current().locals.len() > u8::MAX as usize {
+            return Err(self.error("E0502", name.span));
+        }
+        let depth = self.current().scope_depth;
+        self.current().locals.push(Local {
+            name: name.symbol,
+            depth,
+            captured: false,
+        });
+        Ok(())
+    }
+
+    /// Range dans une variable la valeur au sommet de pile : une globale au
+    /// niveau supérieur, un emplacement local sinon.
+    fn define_variable(&mut self, name: &Token, span: Span) -> CompileResult {
+        if self.current().scope_depth > 0 {
+            return self.add_local(name);
+        }
+        let index = self.name_index(name)?;
+        self.emit_constant_op(OpCode::DefineGlobal, index, span);
+        Ok(())
+    }
+
+    /// Cherche `name` dans les fonctions englobantes et l'ajoute, s'il y est,
+    /// aux variables capturées de la fonction `function` et des fonctions
+    /// intermédiaires.
+    fn resolve_upvalue(&mut self, function: usize, name: &Token) -> Result<Option<u8>, Box<Diagnostic>> {
+        if function == 0 {
+            return Ok(None);
+        }
+        if let Some(slot) = self.functions[function - 1].resolve_local(name.symbol) {
+            self.functions[function - 1].locals[slot as usize].captured = true;
+            return self.add_upvalue(function, slot, true, name.span).map(Some);
+        }
+        match self.resolve_upvalue(function - 1, name)? {
+            Some(index) => self.add_upvalue(function, index, false, name.span).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_upvalue(&mut self, function: usize, index: u8, is_local: bool, span: Span) -> Result<u8, Box<Diagnostic>> {
+        let upvalues = &mut self.functions[function].upvalues;
+        if let Some(existing) = upvalues
+            .iter()
+            .position(|upvalue| upvalue.index == index && upvalue.is_local == is_local)
+        {
+            return Ok(existing as u8);
+        }
+        if upvalues.len() > u8::MAX as usize {
+            return Err(self.error("E0503", span));
+        }
+        upvalues.push(UpvalueRef { index, is_local });
+        Ok((upvalues.len() - 1) as u8)
+    }
+
+    /// Instruction de lecture ou d'écriture de la variable `name`.
+    fn variable_op(&mut self, name: &Token, get: bool, span: Span) -> CompileResult {
+        let (local, upvalue, global) = if get {
+            (OpCode::GetLocal, OpCode::GetUpvalue, OpCode::GetGlobal)
+        } else {
+            (OpCode::SetLocal, OpCode::SetUpvalue, OpCode::SetGlobal)
+        };
+        if let Some(slot) = self.current().resolve_local(name.symbol) {
+            self.emit_with_u8(local, slot, span);
+        } else if let Some(index) = self.resolve_upvalue(self.functions.len() - 1, name)? {
+            self.emit_with_u8(upvalue, index, span);
+        } else {
+            let index = self.name_index(name)?;
+            self.emit_constant_op(global, index, span);
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, stmt: &FunctionStmt) -> CompileResult {
+        self.functions.push(FunctionState::new(&stmt.name.lexeme, stmt.params.len()));
+        self.begin_scope();
+        for param in &stmt.params {
+            self.add_local(param)?;
+        }
+        for body_stmt in &stmt.body {
+            body_stmt.accept(self)?;
+        }
+        let end = stmt.body.last().map_or(stmt.span, Stmt::span);
+        self.emit_op(OpCode::Nil, end);
+        self.emit_op(OpCode::Return, end);
+
+        let mut state = self.functions.pop().expect("fonction compilée");
+        state.prototype.upvalue_count = state.upvalues.len();
+        let chunk = self.chunk();
+        chunk.functions.push(Rc::new(state.prototype));
+        let index = chunk.functions.len() - 1;
+        let index = u16::try_from(index).map_err(|_| self.error("E0501", stmt.span))?;
+        self.emit_with_u16(OpCode::Closure, index, stmt.span);
+        for upvalue in state.upvalues {
+            self.chunk().write(upvalue.is_local as u8, stmt.span);
+            self.chunk().write(upvalue.index, stmt.span);
+        }
+        Ok(())
+    }
+}
+
+impl ExprVisitor for Compiler {
+    type Output = CompileResult;
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::Output {
+        expr.left.accept(self)?;
+        expr.right.accept(self)?;
+        let op = match expr.operator.lexeme.as_str() {
+            "+" => OpCode::Add,
+            "-" => OpCode::Subtract,
+            "*" => OpCode::Multiply,
+            "/" => OpCode::Divide,
+            "%" => OpCode::Modulo,
+            ">" => OpCode::Greater,
+            "<" => OpCode::Less,
+            ">=" => OpCode::GreaterEqual,
+            "<=" => OpCode::LessEqual,
+            "==" => OpCode::Equal,
+            "!=" => OpCode::NotEqual,
+            _ => {
+                return Err(Box::new(
+                    Diagnostic::error(Message::new("E0303", &[&expr.operator.lexeme]))
+                        .with_label(expr.span, ""),
+                ))
+            }
+        };
+        self.emit_op(op, expr.span);
+        Ok(())
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) -> Self::Output {
+        expr.expression.accept(self)
+    }
+
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Self::Output {
+        let value = match &expr.value {
+            Literal::Number(token) => {
+                let number = token
+                    .literal
+                    .as_deref()
+                    .unwrap_or(&token.lexeme)
+                    .parse::<f64>()
+                    .map_err(|_| {
+                        Box::new(
+                            Diagnostic::error(Message::new("E0304", &[&token.lexeme]))
+                                .with_label(token.span, ""),
+                        )
+                    })?;
+                Value::Number(number)
+            }
+            Literal::String(_, text) => Value::String(Rc::clone(text)),
+            Literal::Boolean(token) => {
+                let op = if token.token_type == crate::token::TokenType::TRUE {
+                    OpCode::True
+                } else {
+                    OpCode::False
+                };
+                self.emit_op(op, expr.span);
+                return Ok(());
+            }
+            Literal::Nil => {
+                self.emit_op(OpCode::Nil, expr.span);
+                return Ok(());
+            }
+        };
+        let constant = self.make_constant(value, expr.span)?;
+        self.emit_constant_op(OpCode::Constant, constant, expr.span);
+        Ok(())
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::Output {
+        expr.right.accept(self)?;
+        let op = match expr.operator.lexeme.as_str() {
+            "-" => OpCode::Negate,
+            "!" => OpCode::Not,
+            _ => {
+                return Err(Box::new(
+                    Diagnostic::error(Message::new("E0303", &[&expr.operator.lexeme]))
+                        .with_label(expr.operator.span, ""),
+                ))
+            }
+        };
+        // Comme dans l'interpréteur, une erreur désigne l'opérande.
+        self.emit_op(op, expr.right.span());
+        Ok(())
+    }
+
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) -> Self::Output {
+        self.variable_op(&expr.name, true, expr.span)
+    }
+
+    fn visit_assign_var_expr(&mut self, expr: &AssignVarExpr) -> Self::Output {
+        expr.value.accept(self)?;
+        self.variable_op(&expr.name, false, expr.span)
+    }
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) -> Self::Output {
+        expr.left.accept(self)?;
+        // Même test que l'interpréteur d'arbre, pour que les deux moteurs
+        // donnent les mêmes résultats.
+        if expr.operator.lexeme == "or" {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse, expr.span);
+            let end_jump = self.emit_jump(OpCode::Jump, expr.span);
+            self.patch_jump(else_jump, expr.span)?;
+            self.emit_op(OpCode::Pop, expr.span);
+            expr.right.accept(self)?;
+            self.patch_jump(end_jump, expr.span)
+        } else {
+            let end_jump = self.emit_jump(OpCode::JumpIfFalse, expr.span);
+            self.emit_op(OpCode::Pop, expr.span);
+            expr.right.accept(self)?;
+            self.patch_jump(end_jump, expr.span)
+        }
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExp) -> Self::Output {
+        self.call(expr, OpCode::Call)
+    }
+}
+
+impl StmtVisitor for Compiler {
+    type Output = CompileResult;
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) -> Self::Output {
+        stmt.expression.accept(self)?;
+        self.emit_op(OpCode::Pop, stmt.span);
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) -> Self::Output {
+        stmt.expression.accept(self)?;
+        self.emit_op(OpCode::Print, stmt.span);
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Self::Output {
+        match &stmt.initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => self.emit_op(OpCode::Nil, stmt.span),
+        }
+        self.define_variable(&stmt.name, stmt.span)
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Self::Output {
+        self.begin_scope();
+        for inner in &stmt.statements {
+            inner.accept(self)?;
+        }
+        self.end_scope(stmt.span);
+        Ok(())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Self::Output {
+        stmt.condition.accept(self)?;
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse, stmt.span);
+        self.emit_op(OpCode::Pop, stmt.span);
+        stmt.then_branch.accept(self)?;
+        let else_jump = self.emit_jump(OpCode::Jump, stmt.span);
+        self.patch_jump(then_jump, stmt.span)?;
+        self.emit_op(OpCode::Pop, stmt.span);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self)?;
+        }
+        self.patch_jump(else_jump, stmt.span)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Self::Output {
+        let start = self.chunk().code.len();
+        stmt.condition.accept(self)?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse, stmt.span);
+        self.emit_op(OpCode::Pop, stmt.span);
+        stmt.body.accept(self)?;
+        self.emit_loop(start, stmt.span)?;
+        self.patch_jump(exit_jump, stmt.span)?;
+        self.emit_op(OpCode::Pop, stmt.span);
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Rc<FunctionStmt>) -> Self::Output {
+        // Une fonction locale est visible dans son propre corps, pour la
+        // récursion : son emplacement est réservé avant la compilation.
+        if self.current().scope_depth > 0 {
+            self.add_local(&stmt.name)?;
+            self.function(stmt)
+        } else {
+            self.function(stmt)?;
+            self.define_variable(&stmt.name, stmt.span)
+        }
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::Output {
+        match &stmt.value {
+            Some(Expr::CallExpr(call)) if stmt.tail_call => self.call(call, OpCode::TailCall)?,
+            Some(value) => value.accept(self)?,
+            None => self.emit_op(OpCode::Nil, stmt.span),
+        }
+        self.emit_op(OpCode::Return, stmt.span);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Lexer, vm::disassembler::disassemble};
+
+    fn compile(source: &str) -> Result<Rc<Prototype>, Box<Diagnostic>> {
+        let mut lexer = Lexer::new(source);
+        lexer.scan_tokens();
+        Compiler::compile(&Parser::new(lexer.tokens).parse().unwrap())
+    }
+
+    fn error(source: &str) -> Option<String> {
+        compile(source).unwrap_err().code
+    }
+
+    #[test]
+    fn bytecode() {
+        assert_eq!(
+            disassemble(&compile("variable a <- 1\nsi a alors ecrire a + 2 finsi").unwrap(), None),
+            "== <script> : 0 paramètre(s), 0 variable(s) capturée(s) ==
+0000     1  Constant          0 '1'
+0004     1  DefineGlobal      0 a
+0008     2  GetGlobal         0 a
+0012     2  JumpIfFalse      14 -> 0029
+0015     2  Pop
+0016     2  GetGlobal         0 a
+0020     2  Constant          1 '2'
+0024     2  Add
+0025     2  Print
+0026     2  Jump              1 -> 0030
+0029     2  Pop
+0030     2  Nil
+0031     2  Return
+"
+        );
+    }
+
+    #[test]
+    fn returned_calls_are_tail_calls() {
+        let listing = |source: &str| disassemble(&compile(source).unwrap(), None);
+        let tail = listing("fonction f(n) debut retourner f(n) fin");
+        assert!(tail.contains("TailCall") && !tail.contains(" Call "), "{tail}");
+        let not_tail = listing("fonction f(n) debut retourner f(n) + 1 fin");
+        assert!(!not_tail.contains("TailCall") && not_tail.contains("Call "), "{not_tail}");
+    }
+
+    #[test]
+    fn at_most_255_arguments() {
+        let arguments = |count: usize| vec!["1"; count].join(", ");
+        assert!(compile(&format!("f({})", arguments(255))).is_ok());
+        assert_eq!(error(&format!("f({})", arguments(256))).as_deref(), Some("E0505"));
+    }
+
+    #[test]
+    fn at_most_256_locals() {
+        let locals = |count: usize| {
+            let declarations: String = (0..count).map(|i| format!("variable v{i} <- {i}\n")).collect();
+            format!("fonction f() debut\n{declarations}fin")
+        };
+        // L'emplacement 0 est celui de la fonction appelée.
+        assert!(compile(&locals(255)).is_ok());
+        assert_eq!(error(&locals(256)).as_deref(), Some("E0502"));
+    }
+
+    #[test]
+    fn jumps_fit_in_16_bits() {
+        let block = |count: usize| format!("si vrai alors\n{}finsi", "ecrire 1\n".repeat(count));
+        assert!(compile(&block(1000)).is_ok());
+        assert_eq!(error(&block(30000)).as_deref(), Some("E0504"));
+    }
+}
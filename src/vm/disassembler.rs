@@ -0,0 +1,115 @@
+use std::fmt::Write;
+
+use crate::{catalog, span::SourceFile, value::Value};
+
+use super::chunk::{OpCode, Prototype};
+
+/// Listing lisible d'une fonction compilée puis des fonctions qu'elle
+/// déclare. Chaque changement de ligne est précédé de la ligne du source
+/// correspondante, quand il est disponible.
+pub fn disassemble(prototype: &Prototype, source: Option<&SourceFile>) -> String {
+    let mut out = String::new();
+    disassemble_into(&mut out, prototype, source);
+    out
+}
+
+fn disassemble_into(out: &mut String, prototype: &Prototype, source: Option<&SourceFile>) {
+    let chunk = &prototype.chunk;
+    let _ = writeln!(
+        out,
+        "{}",
+        catalog::text(
+            "desassemblage.fonction",
+            &[&prototype.name, &prototype.arity, &prototype.upvalue_count]
+        )
+    );
+
+    let mut offset = 0;
+    let mut line = 0;
+    while offset < chunk.code.len() {
+        let span = chunk.spans[offset];
+        if span.line != line {
+            line = span.line;
+            if let Some(text) = source.and_then(|source| source.line(line)) {
+                let _ = writeln!(out, "{:>11} ; {:>4} | {}", "", line, text.trim());
+            }
+        }
+        let _ = write!(out, "{:04} {:>5}  ", offset, span.line);
+        offset = instruction(out, prototype, offset);
+    }
+
+    for function in &chunk.functions {
+        let _ = writeln!(out);
+        disassemble_into(out, function, source);
+    }
+}
+
+/// Écrit l'instruction située à `offset` et renvoie la position de la
+/// suivante.
+fn instruction(out: &mut String, prototype: &Prototype, offset: usize) -> usize {
+    let chunk = &prototype.chunk;
+    let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+        let _ = writeln!(out, "?? {}", chunk.code[offset]);
+        return offset + 1;
+    };
+    let name = format!("{:?}", op);
+    match op {
+        OpCode::Constant => {
+            let index = chunk.read_constant_index(offset + 1);
+            let _ = writeln!(out, "{:<14} {:>4} {}", name, index, constant(&chunk.constants[index]));
+        }
+        OpCode::GetGlobal | OpCode::DefineGlobal | OpCode::SetGlobal => {
+            let index = chunk.read_constant_index(offset + 1);
+            let _ = writeln!(out, "{:<14} {:>4} {}", name, index, chunk.names[index]);
+        }
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::Call
+        | OpCode::TailCall => {
+            let _ = writeln!(out, "{:<14} {:>4}", name, chunk.code[offset + 1]);
+        }
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => {
+            let distance = chunk.read_u16(offset + 1) as usize;
+            let target = if op == OpCode::Loop {
+                offset + 3 - distance
+            } else {
+                offset + 3 + distance
+            };
+            let _ = writeln!(out, "{:<14} {:>4} -> {:04}", name, distance, target);
+        }
+        OpCode::Closure => {
+            let index = chunk.read_u16(offset + 1) as usize;
+            let function = &chunk.functions[index];
+            let _ = writeln!(out, "{:<14} {:>4} <fn {}>", name, index, function.name);
+            for capture in 0..function.upvalue_count {
+                let at = offset + 3 + 2 * capture;
+                let key = if chunk.code[at] == 1 {
+                    "desassemblage.locale"
+                } else {
+                    "desassemblage.capturee"
+                };
+                let _ = writeln!(
+                    out,
+                    "{:04} {:>5}  {:>19} {}",
+                    at,
+                    "|",
+                    "",
+                    catalog::text(key, &[&chunk.code[at + 1]])
+                );
+            }
+        }
+        _ => {
+            let _ = writeln!(out, "{}", name);
+        }
+    }
+    offset + chunk.instruction_len(offset).unwrap_or(1)
+}
+
+fn constant(value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{:?}", text),
+        other => format!("'{}'", other),
+    }
+}
@@ -0,0 +1,542 @@
+//! Fichiers `.frc` : programme compilé par `frenchy compiler`, exécuté sans
+//! nouvelle analyse.
+//!
+//! Tous les entiers sont en petit-boutiste. Le fichier contient, dans
+//! l'ordre : la signature `FRC\0`, la version du format (`u16`), le nom et le
+//! texte du source (pour les diagnostics et le désassemblage), puis la table
+//! des fonctions. Chaque fonction y suit les fonctions qu'elle déclare ; le
+//! programme principal est la dernière. Une fonction comprend son nom, son
+//! nombre de paramètres et de variables capturées, son code, ses constantes,
+//! les noms des globales qu'elle utilise, les indices de ses fonctions
+//! imbriquées et sa table des positions, codée par plages d'octets partageant
+//! la même portion du source.
+
+use std::rc::Rc;
+
+use crate::{
+    catalog::{self, Message},
+    span::{FileId, Span},
+    symbol::Symbol,
+    value::Value,
+};
+
+use super::chunk::{Chunk, OpCode, Prototype};
+
+const MAGIC: &[u8; 4] = b"FRC\0";
+/// À augmenter à chaque changement du jeu d'instructions ou de l'encodage.
+pub const VERSION: u16 = 3;
+
+const NUMBER: u8 = 0;
+const STRING: u8 = 1;
+
+/// Programme compilé, avec le source dont il est issu.
+pub struct Program {
+    pub source_name: String,
+    pub source_text: String,
+    pub script: Rc<Prototype>,
+}
+
+pub fn write(program: &Program) -> Vec<u8> {
+    let mut table = Vec::new();
+    flatten(&program.script, &mut table);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    write_string(&mut out, &program.source_name);
+    write_string(&mut out, &program.source_text);
+    write_u32(&mut out, table.len());
+    for (prototype, children) in &table {
+        write_prototype(&mut out, prototype, children);
+    }
+    out
+}
+
+/// Range `prototype` après ses fonctions imbriquées et renvoie son indice.
+fn flatten<'a>(prototype: &'a Prototype, table: &mut Vec<(&'a Prototype, Vec<usize>)>) -> usize {
+    let children = prototype
+        .chunk
+        .functions
+        .iter()
+        .map(|child| flatten(child, table))
+        .collect();
+    table.push((prototype, children));
+    table.len() - 1
+}
+
+fn write_prototype(out: &mut Vec<u8>, prototype: &Prototype, children: &[usize]) {
+    let chunk = &prototype.chunk;
+    write_string(out, &prototype.name);
+    write_u32(out, prototype.arity);
+    write_u32(out, prototype.upvalue_count);
+
+    write_u32(out, chunk.code.len());
+    out.extend_from_slice(&chunk.code);
+
+    write_u32(out, chunk.constants.len());
+    for constant in &chunk.constants {
+        match constant {
+            Value::Number(number) => {
+                out.push(NUMBER);
+                out.extend_from_slice(&number.to_le_bytes());
+            }
+            Value::String(text) => {
+                out.push(STRING);
+                write_string(out, text);
+            }
+            _ => unreachable!("seuls les nombres et les textes sont des constantes"),
+        }
+    }
+
+    write_u32(out, chunk.names.len());
+    for name in &chunk.names {
+        write_string(out, &name.name());
+    }
+
+    write_u32(out, children.len());
+    for child in children {
+        write_u32(out, *child);
+    }
+
+    let mut runs: Vec<(usize, Span)> = Vec::new();
+    for span in &chunk.spans {
+        match runs.last_mut() {
+            Some((length, last)) if last == span => *length += 1,
+            _ => runs.push((1, *span)),
+        }
+    }
+    write_u32(out, runs.len());
+    for (length, span) in runs {
+        write_u32(out, length);
+        for field in [span.start, span.end, span.line as usize, span.column, span.end_line as usize, span.end_column] {
+            write_u32(out, field);
+        }
+    }
+}
+
+fn write_u32(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn write_string(out: &mut Vec<u8>, text: &str) {
+    write_u32(out, text.len());
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Relit un fichier `.frc`. Les positions sont rattachées au fichier `file`,
+/// sous lequel le source embarqué doit être enregistré. `name` sert aux
+/// messages d'erreur.
+pub fn read(bytes: &[u8], name: &str, file: FileId) -> Result<Program, Message> {
+    let invalid = |key: &str, args: &[&dyn std::fmt::Display]| {
+        Message::new("E0406", &[&name, &catalog::text(key, args)])
+    };
+    let mut reader = Reader { bytes, position: 0 };
+
+    if reader.take(MAGIC.len()) != Some(MAGIC.as_slice()) {
+        return Err(invalid("frc.signature", &[]));
+    }
+    let version = reader.u16().ok_or_else(|| invalid("frc.tronque", &[]))?;
+    if version != VERSION {
+        return Err(Message::new("E0407", &[&name, &version, &VERSION]));
+    }
+
+    let truncated = || invalid("frc.tronque", &[]);
+    let source_name = reader.string().ok_or_else(truncated)?;
+    let source_text = reader.string().ok_or_else(truncated)?;
+    let count = reader.u32().ok_or_else(truncated)?;
+
+    let mut table: Vec<Rc<Prototype>> = Vec::new();
+    for index in 0..count {
+        let prototype = reader
+            .prototype(&table, file)
+            .ok_or_else(|| invalid("frc.fonction", &[&index]))?;
+        if let Some(offset) = verify(&prototype) {
+            return Err(invalid("frc.instruction", &[&prototype.name, &offset]));
+        }
+        table.push(Rc::new(prototype));
+    }
+    let script = table.pop().ok_or_else(truncated)?;
+    // Le programme principal démarre sans argument.
+    if script.arity != 0 {
+        return Err(invalid("frc.fonction", &[&(count - 1)]));
+    }
+    if reader.position != bytes.len() {
+        return Err(invalid("frc.reste", &[]));
+    }
+
+    Ok(Program {
+        source_name,
+        source_text,
+        script,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.position..self.position.checked_add(length)?)?;
+        self.position += length;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<usize> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?) as usize)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = self.u32()?;
+        String::from_utf8(self.take(length)?.to_vec()).ok()
+    }
+
+    /// Fonction suivante ; ses fonctions imbriquées sont déjà dans `table`.
+    fn prototype(&mut self, table: &[Rc<Prototype>], file: FileId) -> Option<Prototype> {
+        let name = self.string()?;
+        let arity = self.u32()?;
+        let upvalue_count = self.u32()?;
+
+        let length = self.u32()?;
+        let code = self.take(length)?.to_vec();
+
+        let mut constants = Vec::new();
+        for _ in 0..self.u32()? {
+            constants.push(match self.u8()? {
+                NUMBER => Value::Number(self.f64()?),
+                STRING => Value::String(self.string()?.into()),
+                _ => return None,
+            });
+        }
+
+        let mut names = Vec::new();
+        for _ in 0..self.u32()? {
+            names.push(Symbol::intern(&self.string()?));
+        }
+
+        let mut functions = Vec::new();
+        for _ in 0..self.u32()? {
+            functions.push(Rc::clone(table.get(self.u32()?)?));
+        }
+
+        let mut spans = Vec::with_capacity(code.len());
+        for _ in 0..self.u32()? {
+            let length = self.u32()?;
+            let span = Span {
+                file,
+                start: self.u32()?,
+                end: self.u32()?,
+                line: self.u32()? as u32,
+                column: self.u32()?,
+                end_line: self.u32()? as u32,
+                end_column: self.u32()?,
+            };
+            if spans.len() + length > code.len() {
+                return None;
+            }
+            spans.extend(std::iter::repeat_n(span, length));
+        }
+        if spans.len() != code.len() {
+            return None;
+        }
+
+        Some(Prototype {
+            name,
+            arity,
+            upvalue_count,
+            chunk: Chunk {
+                code,
+                spans,
+                constants,
+                names,
+                functions,
+            },
+        })
+    }
+}
+
+/// Vérifie que le code ne fera pas sortir la machine virtuelle de ses
+/// tables : instructions connues, constantes et fonctions existantes, sauts
+/// internes à la fonction, `Return` final, puis hauteur de pile (voir
+/// `verify_stack`). Renvoie la position de la première instruction fautive.
+fn verify(prototype: &Prototype) -> Option<usize> {
+    let chunk = &prototype.chunk;
+    let mut offset = 0;
+    let mut last = None;
+    let mut starts = vec![false; chunk.code.len()];
+    while offset < chunk.code.len() {
+        starts[offset] = true;
+        let Some(length) = chunk.instruction_len(offset) else {
+            return Some(offset);
+        };
+        if offset + length > chunk.code.len() {
+            return Some(offset);
+        }
+        let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+            return Some(offset);
+        };
+        let valid = match op {
+            OpCode::Constant => chunk.read_constant_index(offset + 1) < chunk.constants.len(),
+            OpCode::GetGlobal | OpCode::DefineGlobal | OpCode::SetGlobal => {
+                chunk.read_constant_index(offset + 1) < chunk.names.len()
+            }
+            OpCode::Jump | OpCode::JumpIfFalse => {
+                offset + 3 + (chunk.read_u16(offset + 1) as usize) < chunk.code.len()
+            }
+            OpCode::Loop => chunk.read_u16(offset + 1) as usize <= offset + 3,
+            OpCode::GetUpvalue | OpCode::SetUpvalue => {
+                (chunk.code[offset + 1] as usize) < prototype.upvalue_count
+            }
+            _ => true,
+        };
+        if !valid {
+            return Some(offset);
+        }
+        last = Some(op);
+        offset += length;
+    }
+    if last != Some(OpCode::Return) {
+        return Some(offset);
+    }
+    verify_stack(prototype, &starts)
+}
+
+/// Suit chaque chemin d'exécution en comptant les valeurs de la fenêtre de
+/// l'appel : la fonction appelée puis ses paramètres à l'entrée. Une
+/// instruction ne doit dépiler que ce qu'elle a empilé au-dessus des
+/// paramètres, et n'accéder qu'aux emplacements locaux présents ; les chemins
+/// qui se rejoignent doivent arriver avec la même hauteur. Les sauts doivent
+/// mener au début d'une instruction, déjà vérifiée par `verify`.
+fn verify_stack(prototype: &Prototype, starts: &[bool]) -> Option<usize> {
+    let chunk = &prototype.chunk;
+    let parameters = prototype.arity + 1;
+    let mut heights: Vec<Option<usize>> = vec![None; chunk.code.len()];
+    let mut pending = vec![(0, parameters)];
+    while let Some((offset, height)) = pending.pop() {
+        if !starts.get(offset).copied().unwrap_or(false) {
+            return Some(offset);
+        }
+        match heights[offset] {
+            Some(known) if known == height => continue,
+            Some(_) => return Some(offset),
+            None => heights[offset] = Some(height),
+        }
+        let (Some(op), Some(length)) = (
+            OpCode::from_byte(chunk.code[offset]),
+            chunk.instruction_len(offset),
+        ) else {
+            return Some(offset);
+        };
+        let operand = chunk.code.get(offset + 1).copied().unwrap_or(0) as usize;
+        // Valeurs retirées du sommet de la pile, puis valeurs empilées.
+        let (popped, pushed) = match op {
+            OpCode::Constant
+            | OpCode::Nil
+            | OpCode::True
+            | OpCode::False
+            | OpCode::GetGlobal
+            | OpCode::GetUpvalue => (0, 1),
+            OpCode::Jump | OpCode::Loop => (0, 0),
+            OpCode::GetLocal | OpCode::SetLocal if operand >= height => return Some(offset),
+            OpCode::GetLocal => (0, 1),
+            OpCode::Pop | OpCode::DefineGlobal | OpCode::Print | OpCode::CloseUpvalue | OpCode::Return => {
+                (1, 0)
+            }
+            OpCode::SetLocal
+            | OpCode::SetGlobal
+            | OpCode::SetUpvalue
+            | OpCode::Not
+            | OpCode::Negate
+            | OpCode::JumpIfFalse => (1, 1),
+            OpCode::Equal
+            | OpCode::NotEqual
+            | OpCode::Greater
+            | OpCode::GreaterEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide
+            | OpCode::Modulo => (2, 1),
+            OpCode::Call | OpCode::TailCall => (operand + 1, 1),
+            OpCode::Closure => {
+                // Chaque variable capturée : un emplacement local présent, ou
+                // une variable déjà capturée par la fonction englobante.
+                let valid = chunk.code[offset + 3..offset + length].chunks(2).all(|capture| {
+                    match capture[0] {
+                        1 => (capture[1] as usize) < height,
+                        0 => (capture[1] as usize) < prototype.upvalue_count,
+                        _ => false,
+                    }
+                });
+                if !valid {
+                    return Some(offset);
+                }
+                (0, 1)
+            }
+        };
+        if height < parameters + popped {
+            return Some(offset);
+        }
+        let after = height - popped + pushed;
+        let next = offset + length;
+        match op {
+            OpCode::Return => {}
+            OpCode::Jump => pending.push((next + chunk.read_u16(offset + 1) as usize, after)),
+            OpCode::Loop => pending.push((next - chunk.read_u16(offset + 1) as usize, after)),
+            OpCode::JumpIfFalse => {
+                pending.push((next + chunk.read_u16(offset + 1) as usize, after));
+                pending.push((next, after));
+            }
+            _ => pending.push((next, after)),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::Parser,
+        scanner::Lexer,
+        vm::{disassembler::disassemble, Compiler, Vm},
+    };
+
+    const SOURCE: &str = "fonction compteur() debut
+    variable n <- 0
+    fonction suivant() debut
+        n <- n + 1
+        retourner n
+    fin
+    retourner suivant
+fin
+variable c <- compteur()
+c()
+ecrire c() + 0.5
+ecrire \"fin\"";
+
+    fn compiled() -> Vec<u8> {
+        let mut lexer = Lexer::new(SOURCE);
+        lexer.scan_tokens();
+        let statements = Parser::new(lexer.tokens).parse().unwrap();
+        write(&Program {
+            source_name: "essai.fr".to_string(),
+            source_text: SOURCE.to_string(),
+            script: Compiler::compile(&statements).unwrap(),
+        })
+    }
+
+    /// Fichier dont le programme principal est le code `code`, sans
+    /// constante.
+    fn with_code(code: &[u8]) -> Vec<u8> {
+        let mut chunk = Chunk::default();
+        for byte in code {
+            chunk.write(*byte, Span::default());
+        }
+        write(&Program {
+            source_name: "essai.fr".to_string(),
+            source_text: String::new(),
+            script: Rc::new(Prototype {
+                name: "<script>".to_string(),
+                chunk,
+                ..Default::default()
+            }),
+        })
+    }
+
+    fn rejection(bytes: &[u8]) -> String {
+        match read(bytes, "essai.frc", 0) {
+            Ok(_) => panic!("fichier accepté"),
+            Err(message) => message.text,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = compiled();
+        let program = read(&bytes, "essai.frc", 0).unwrap();
+        assert_eq!(program.source_name, "essai.fr");
+        assert_eq!(program.source_text, SOURCE);
+        assert_eq!(write(&program), bytes);
+
+        let mut vm = Vm::new();
+        let output = vm.capture_output();
+        vm.interpret(program.script).unwrap();
+        assert_eq!(*output.borrow(), "2.5\nfin\n");
+    }
+
+    #[test]
+    fn disassembly_is_unchanged_by_a_round_trip() {
+        let mut lexer = Lexer::new(SOURCE);
+        lexer.scan_tokens();
+        let statements = Parser::new(lexer.tokens).parse().unwrap();
+        let script = Compiler::compile(&statements).unwrap();
+        let program = read(&compiled(), "essai.frc", 0).unwrap();
+        assert_eq!(disassemble(&program.script, None), disassemble(&script, None));
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let bytes = compiled();
+        for length in 0..bytes.len() {
+            assert!(read(&bytes[..length], "essai.frc", 0).is_err(), "{length} octets acceptés");
+        }
+    }
+
+    #[test]
+    fn header_is_checked() {
+        let mut bytes = compiled();
+        bytes[0] = b'X';
+        assert_eq!(rejection(&bytes), "fichier compilé invalide 'essai.frc' : signature absente");
+
+        let mut bytes = compiled();
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(
+            rejection(&bytes),
+            format!("'essai.frc' a été compilé au format {}, cette version de frenchy lit le format {}", VERSION + 1, VERSION)
+        );
+
+        let mut bytes = compiled();
+        bytes.push(0);
+        assert_eq!(rejection(&bytes), "fichier compilé invalide 'essai.frc' : données en trop après le programme");
+    }
+
+    #[test]
+    fn malformed_code_is_rejected() {
+        use OpCode::*;
+
+        assert!(read(&with_code(&[Nil as u8, Return as u8]), "essai.frc", 0).is_ok());
+        let invalid = |offset: usize| {
+            format!("fichier compilé invalide 'essai.frc' : instruction invalide dans '<script>' à la position {offset}")
+        };
+        // Pas de `Return` final.
+        assert_eq!(rejection(&with_code(&[Nil as u8])), invalid(1));
+        // Octet qui n'est pas une instruction.
+        assert_eq!(rejection(&with_code(&[Nil as u8, 200, Return as u8])), invalid(1));
+        // Pile vide au moment de dépiler.
+        assert_eq!(rejection(&with_code(&[Pop as u8, Nil as u8, Return as u8])), invalid(0));
+        assert_eq!(rejection(&with_code(&[Nil as u8, Add as u8, Return as u8])), invalid(1));
+        // Constante absente de la table.
+        assert_eq!(rejection(&with_code(&[Constant as u8, 0, 0, 0, Return as u8])), invalid(0));
+        // Saut hors de la fonction.
+        assert_eq!(rejection(&with_code(&[Jump as u8, 0, 9, Nil as u8, Return as u8])), invalid(0));
+        // Variable capturée ou emplacement local inexistants.
+        assert_eq!(rejection(&with_code(&[GetUpvalue as u8, 0, Return as u8])), invalid(0));
+        assert_eq!(rejection(&with_code(&[GetLocal as u8, 1, Return as u8])), invalid(0));
+    }
+}
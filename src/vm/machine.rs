@@ -0,0 +1,510 @@
+use std::{cell::RefCell, collections::HashMap, fmt, fmt::Write, rc::Rc};
+
+use crate::{
+    budget::{Budget, Limits},
+    builtin,
+    catalog::Message,
+    diagnostic::Frame,
+    error::RuntimeError,
+    gc::{self, Trace},
+    hint,
+    interpret_visitor::{summarize, InterpretVisitor},
+    options::DEFAULT_MAX_DEPTH,
+    span::Span,
+    suggest,
+    symbol::Symbol,
+    value::Value,
+};
+
+use super::chunk::{OpCode, Prototype};
+
+/// Fonction compilée associée aux variables qu'elle capture.
+pub struct Closure {
+    pub prototype: Rc<Prototype>,
+    upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("<fn :")
+            .field("name", &self.prototype.name)
+            .finish()
+    }
+}
+
+/// Variable capturée : elle désigne un emplacement de la pile tant que son
+/// bloc est actif, puis garde sa propre copie de la valeur.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+impl Trace for Closure {
+    fn trace(&self, visit: &mut dyn FnMut(*const ())) {
+        for upvalue in &self.upvalues {
+            visit(Rc::as_ptr(upvalue) as *const ());
+        }
+    }
+}
+
+impl Trace for Upvalue {
+    fn trace(&self, visit: &mut dyn FnMut(*const ())) {
+        if let Upvalue::Closed(value) = self {
+            gc::trace_value(value, visit);
+        }
+    }
+}
+
+/// Appel en cours : `base` est l'emplacement de la fonction appelée, suivie
+/// de ses arguments et de ses variables locales.
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+    call_site: Span,
+}
+
+/// Machine virtuelle à pile. Les globales sont conservées d'une exécution à
+/// l'autre, pour le REPL.
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<Symbol, Value>,
+    /// Variables capturées encore ouvertes, par emplacement croissant.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// Nombre maximal d'appels imbriqués, le script lui-même exclu.
+    max_depth: usize,
+    /// Étapes, durée et mémoire consommées par l'exécution en cours.
+    budget: Budget,
+    /// Reçoit ce qu'écrit `ecrire` à la place de la sortie standard.
+    output: Option<Rc<RefCell<String>>>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let globals = builtin::natives()
+            .into_iter()
+            .map(|(name, function)| (Symbol::intern(name), Value::NativeFunction(function)))
+            .collect();
+        Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals,
+            open_upvalues: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            budget: Budget::new(Limits::default()),
+            output: None,
+        }
+    }
+
+    /// Limites appliquées à chaque appel de `interpret` ; une étape est une
+    /// instruction du bytecode.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits);
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Détourne la sortie de `ecrire` vers un texte partagé.
+    #[cfg(test)]
+    pub fn capture_output(&mut self) -> Rc<RefCell<String>> {
+        Rc::clone(self.output.insert(Rc::default()))
+    }
+
+    pub fn globals(&self) -> impl Iterator<Item = (Symbol, &Value)> {
+        self.globals.iter().map(|(name, value)| (*name, value))
+    }
+
+    pub fn interpret(&mut self, script: Rc<Prototype>) -> Result<(), RuntimeError> {
+        let closure = Rc::new(Closure {
+            prototype: script,
+            upvalues: Vec::new(),
+        });
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: 0,
+            call_site: Span::default(),
+        });
+        self.budget.restart();
+        let result = self.run();
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+        result
+    }
+
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        let mut closure = Rc::clone(&self.frames.last().expect("appel en cours").closure);
+        let mut ip = 0;
+        let mut base = 0;
+
+        loop {
+            let chunk = &closure.prototype.chunk;
+            let start = ip;
+            let op = OpCode::from_byte(chunk.code[ip]).expect("instruction valide");
+            ip += 1;
+            if let Err(message) = self.budget.charge() {
+                return Err(self.error(chunk.spans[start], message));
+            }
+
+            match op {
+                OpCode::Constant => {
+                    let index = chunk.read_constant_index(ip);
+                    ip += 3;
+                    self.stack.push(chunk.constants[index].clone());
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(Value::Boolean(true)),
+                OpCode::False => self.stack.push(Value::Boolean(false)),
+                OpCode::Pop => {
+                    self.stack.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = chunk.code[ip] as usize;
+                    ip += 1;
+                    self.stack.push(self.stack[base + slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = chunk.code[ip] as usize;
+                    ip += 1;
+                    self.stack[base + slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = chunk.read_constant_index(ip);
+                    ip += 3;
+                    let name = chunk.names[name];
+                    match self.globals.get(&name) {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(self.undefined(name, chunk.spans[start], true)),
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = chunk.read_constant_index(ip);
+                    ip += 3;
+                    let value = self.stack.pop().expect("valeur à définir");
+                    self.globals.insert(chunk.names[name], value);
+                }
+                OpCode::SetGlobal => {
+                    let name = chunk.read_constant_index(ip);
+                    ip += 3;
+                    let name = chunk.names[name];
+                    let value = self.peek(0).clone();
+                    match self.globals.get_mut(&name) {
+                        Some(slot) => *slot = value,
+                        None => return Err(self.undefined(name, chunk.spans[start], false)),
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let index = chunk.code[ip] as usize;
+                    ip += 1;
+                    let value = match &*closure.upvalues[index].borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = chunk.code[ip] as usize;
+                    ip += 1;
+                    let value = self.peek(0).clone();
+                    let mut upvalue = closure.upvalues[index].borrow_mut();
+                    match &mut *upvalue {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::Equal | OpCode::NotEqual => {
+                    let right = self.stack.pop().expect("opérande droite");
+                    let left = self.stack.pop().expect("opérande gauche");
+                    let equal = left == right;
+                    self.stack.push(Value::Boolean(equal == (op == OpCode::Equal)));
+                }
+                OpCode::Greater | OpCode::GreaterEqual | OpCode::Less | OpCode::LessEqual => {
+                    let right = self.stack.pop().expect("opérande droite");
+                    let left = self.stack.pop().expect("opérande gauche");
+                    let result = match op {
+                        OpCode::Greater => left > right,
+                        OpCode::GreaterEqual => left >= right,
+                        OpCode::Less => left < right,
+                        _ => left <= right,
+                    };
+                    self.stack.push(Value::Boolean(result));
+                }
+                OpCode::Add | OpCode::Subtract | OpCode::Multiply | OpCode::Divide | OpCode::Modulo => {
+                    let right = self.stack.pop().expect("opérande droite");
+                    let left = self.stack.pop().expect("opérande gauche");
+                    let text_and_number = matches!(
+                        (&left, &right),
+                        (Value::String(_), Value::Number(_)) | (Value::Number(_), Value::String(_))
+                    );
+                    let result = match op {
+                        OpCode::Add => left + right,
+                        OpCode::Subtract => left - right,
+                        OpCode::Multiply => left * right,
+                        OpCode::Divide => left / right,
+                        _ => left % right,
+                    };
+                    match result {
+                        Ok(value) => self.stack.push(value),
+                        Err(message) => {
+                            let error = self.error(chunk.spans[start], message);
+                            return Err(if text_and_number {
+                                error.with_hint(hint::text_used_as_number())
+                            } else {
+                                error
+                            });
+                        }
+                    }
+                }
+                OpCode::Not | OpCode::Negate => {
+                    let operand = match self.stack.pop().expect("opérande") {
+                        Value::Number(number) => number,
+                        Value::Boolean(boolean) => boolean as i32 as f64,
+                        _ => return Err(self.error(chunk.spans[start], Message::new("E0305", &[]))),
+                    };
+                    self.stack.push(if op == OpCode::Negate {
+                        Value::Number(-operand)
+                    } else {
+                        Value::Boolean(operand != 1.0)
+                    });
+                }
+                OpCode::Print => {
+                    let value = self.stack.pop().expect("valeur à écrire");
+                    match &self.output {
+                        Some(output) => {
+                            writeln!(output.borrow_mut(), "{}", InterpretVisitor::stringify(&value)).unwrap()
+                        }
+                        None => println!("{}", InterpretVisitor::stringify(&value)),
+                    }
+                }
+                OpCode::Jump => {
+                    let offset = chunk.read_u16(ip) as usize;
+                    ip += 2 + offset;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = chunk.read_u16(ip) as usize;
+                    ip += 2;
+                    if !InterpretVisitor::is_truthy(self.peek(0)) {
+                        ip += offset;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = chunk.read_u16(ip) as usize;
+                    ip = ip + 2 - offset;
+                }
+                OpCode::Call => {
+                    let count = chunk.code[ip] as usize;
+                    ip += 1;
+                    let (span, callee_span) = (chunk.spans[start], chunk.spans[start + 1]);
+                    self.frames.last_mut().expect("appel en cours").ip = ip;
+                    if let Some(callee) = self.call(count, span, callee_span)? {
+                        // Comme dans l'interpréteur d'arbre, l'appel de trop
+                        // n'apparaît pas dans la pile d'appels.
+                        if self.frames.len() - 1 > self.max_depth {
+                            self.frames.pop();
+                            return Err(self.error(span, Message::new("E0318", &[&self.max_depth])));
+                        }
+                        closure = callee;
+                        let frame = self.frames.last().expect("appel en cours");
+                        ip = frame.ip;
+                        base = frame.base;
+                    }
+                }
+                OpCode::TailCall => {
+                    let count = chunk.code[ip] as usize;
+                    ip += 1;
+                    let (span, callee_span) = (chunk.spans[start], chunk.spans[start + 1]);
+                    self.frames.last_mut().expect("appel en cours").ip = ip;
+                    if let Some(callee) = self.call(count, span, callee_span)? {
+                        // L'appelé reprend la fenêtre de l'appel en cours,
+                        // dont les variables capturées sont d'abord fermées.
+                        let frame = self.frames.pop().expect("appel en cours");
+                        self.frames.pop();
+                        self.close_upvalues(base);
+                        self.stack.drain(base..frame.base);
+                        self.frames.push(CallFrame { base, ..frame });
+                        closure = callee;
+                        ip = 0;
+                    }
+                }
+                OpCode::Closure => {
+                    let index = chunk.read_u16(ip) as usize;
+                    ip += 2;
+                    let prototype = Rc::clone(&chunk.functions[index]);
+                    let mut upvalues = Vec::with_capacity(prototype.upvalue_count);
+                    for _ in 0..prototype.upvalue_count {
+                        let is_local = chunk.code[ip] == 1;
+                        let index = chunk.code[ip + 1] as usize;
+                        ip += 2;
+                        upvalues.push(if is_local {
+                            self.capture_upvalue(base + index)
+                        } else {
+                            Rc::clone(&closure.upvalues[index])
+                        });
+                    }
+                    let closure = Rc::new(Closure { prototype, upvalues });
+                    // Seule une fonction qui capture des variables peut faire
+                    // partie d'un cycle.
+                    if !closure.upvalues.is_empty() {
+                        gc::track_closure(&closure);
+                    }
+                    self.stack.push(Value::Closure(closure));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.stack.pop();
+                }
+                OpCode::Return => {
+                    let result = self.stack.pop().expect("valeur de retour");
+                    self.close_upvalues(base);
+                    self.frames.pop();
+                    self.stack.truncate(base);
+                    let Some(frame) = self.frames.last() else {
+                        return Ok(());
+                    };
+                    self.stack.push(result);
+                    closure = Rc::clone(&frame.closure);
+                    ip = frame.ip;
+                    base = frame.base;
+                }
+            }
+        }
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    /// Appelle la valeur placée sous les `count` arguments. Renvoie la
+    /// fonction compilée dont l'exécution commence ; une fonction native est
+    /// exécutée directement.
+    fn call(&mut self, count: usize, span: Span, callee_span: Span) -> Result<Option<Rc<Closure>>, RuntimeError> {
+        // Comme l'interpréteur d'arbre, les arguments VIDE ne sont pas transmis.
+        let first = self.stack.len() - count;
+        let mut kept = first;
+        for index in first..self.stack.len() {
+            if self.stack[index] != Value::Nil {
+                self.stack.swap(kept, index);
+                kept += 1;
+            }
+        }
+        self.stack.truncate(kept);
+        let count = kept - first;
+        let callee_slot = first - 1;
+
+        match self.stack[callee_slot].clone() {
+            Value::Closure(closure) => {
+                if count != closure.prototype.arity {
+                    return Err(self.arity_error(span, closure.prototype.arity, count));
+                }
+                self.frames.push(CallFrame {
+                    closure: Rc::clone(&closure),
+                    ip: 0,
+                    base: callee_slot,
+                    call_site: span,
+                });
+                Ok(Some(closure))
+            }
+            Value::NativeFunction(native) => {
+                if count != native.arity {
+                    return Err(self.arity_error(span, native.arity, count));
+                }
+                let arguments = self.stack.split_off(first);
+                let frame = Frame {
+                    function: native.name.clone(),
+                    arguments: arguments.iter().map(summarize).collect::<Vec<_>>().join(", "),
+                    call_site: span,
+                };
+                match (native.function)(arguments) {
+                    Ok(value) => {
+                        self.stack.pop();
+                        self.stack.push(value);
+                        Ok(None)
+                    }
+                    Err(message) => {
+                        let error = RuntimeError::at(span, message);
+                        Err(self.with_trace(error, Some(frame)))
+                    }
+                }
+            }
+            callee => Err(self.error(callee_span, Message::new("E0302", &[&callee.type_name()]))),
+        }
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self
+            .open_upvalues
+            .iter()
+            .position(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open >= slot));
+        if let Some(position) = position {
+            let upvalue = &self.open_upvalues[position];
+            if matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot) {
+                return Rc::clone(upvalue);
+            }
+        }
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        gc::track_upvalue(&upvalue);
+        let position = position.unwrap_or(self.open_upvalues.len());
+        self.open_upvalues.insert(position, Rc::clone(&upvalue));
+        upvalue
+    }
+
+    /// Ferme les variables capturées situées à partir de l'emplacement `from`.
+    fn close_upvalues(&mut self, from: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let slot = match *upvalue.borrow() {
+                Upvalue::Open(slot) if slot >= from => slot,
+                _ => break,
+            };
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[slot].clone());
+            self.open_upvalues.pop();
+        }
+    }
+
+    fn arity_error(&self, span: Span, arity: usize, count: usize) -> RuntimeError {
+        self.error(span, Message::new("E0301", &[&arity, &count]))
+    }
+
+    fn undefined(&self, name: Symbol, span: Span, suggest: bool) -> RuntimeError {
+        let mut message = Message::new("E0313", &[&name]);
+        if suggest {
+            let defined: Vec<_> = self.globals.keys().map(|name| name.name()).collect();
+            if let Some(help) = suggest::help(&name.name(), defined.iter().map(|name| &**name)) {
+                message.text = format!("{} ; {}", message.text, help);
+            }
+        }
+        self.error(span, message)
+    }
+
+    fn error(&self, span: Span, message: Message) -> RuntimeError {
+        self.with_trace(RuntimeError::at(span, message), None)
+    }
+
+    /// Ajoute à l'erreur la pile des appels en cours, la plus récente en
+    /// premier, précédée de l'appel natif qui a échoué s'il y en a un.
+    fn with_trace(&self, mut error: RuntimeError, native: Option<Frame>) -> RuntimeError {
+        if let RuntimeError::Error { trace, .. } = &mut error {
+            trace.extend(native);
+            trace.extend(self.frames.iter().skip(1).rev().map(|frame| {
+                let arity = frame.closure.prototype.arity;
+                let arguments = self.stack[frame.base + 1..frame.base + 1 + arity]
+                    .iter()
+                    .map(summarize)
+                    .collect::<Vec<_>>();
+                Frame {
+                    function: frame.closure.prototype.name.clone(),
+                    arguments: arguments.join(", "),
+                    call_site: frame.call_site,
+                }
+            }));
+        }
+        error
+    }
+}
@@ -0,0 +1,13 @@
+//! Moteur d'exécution à bytecode, choisi par `--moteur=vm` : l'arbre résolu
+//! est compilé en instructions compactes, exécutées par une machine à pile.
+
+mod chunk;
+mod compiler;
+mod disassembler;
+mod frc;
+mod machine;
+
+pub use compiler::Compiler;
+pub use disassembler::disassemble;
+pub use frc::{read, write, Program};
+pub use machine::{Closure, Upvalue, Vm};